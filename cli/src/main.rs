@@ -0,0 +1,213 @@
+use std::{path::Path, sync::Arc, time::Instant};
+
+mod serve;
+
+use clap::Parser;
+use rolldown::{
+  structs::RolldownOutput,
+  types::{
+    load_config_file, InternalModuleFormat, NormalizedInputOptions, NormalizedOutputOptions,
+    SourceMapMode,
+  },
+  utils::resolve_id,
+  watcher::{Watcher, WatcherEvent},
+  RolldownBuild,
+};
+
+#[derive(Parser, Debug)]
+#[clap(name = "rolldown", version, about = "Bundle JavaScript modules")]
+struct Cli {
+  /// Entry modules
+  #[clap(value_name = "INPUT")]
+  entries: Vec<String>,
+  /// Entry modules, same as the positional arguments
+  #[clap(short, long, multiple_occurrences(true))]
+  input: Vec<String>,
+  /// Single output file
+  #[clap(short = 'o', long)]
+  file: Option<String>,
+  /// Directory for chunks, required when there are multiple chunks
+  #[clap(short, long)]
+  dir: Option<String>,
+  /// Output format: es, cjs, amd, umd, iife or system
+  #[clap(short, long)]
+  format: Option<String>,
+  /// Name of the global variable for UMD and IIFE bundles
+  #[clap(short, long)]
+  name: Option<String>,
+  /// Generate source maps: true, inline or hidden
+  #[clap(short, long, min_values(0), max_values(1))]
+  sourcemap: Option<Option<String>>,
+  /// Minify the generated code
+  #[clap(long)]
+  minify: bool,
+  /// Comma-separated list of module ids to exclude from the bundle
+  #[clap(short, long, use_value_delimiter(true), multiple_occurrences(true))]
+  external: Vec<String>,
+  /// Remove unused code
+  #[clap(long)]
+  treeshake: bool,
+  /// Directory to cache parsed modules across builds
+  #[clap(long)]
+  cache_dir: Option<String>,
+  /// Read options from a JSON or TOML file. Command line options take precedence.
+  #[clap(short, long)]
+  config: Option<String>,
+  /// Rebuild the bundle when its source files change
+  #[clap(short, long)]
+  watch: bool,
+  /// Serve the bundle on localhost (default port 3000) and apply changes to open pages without reloading.
+  /// An `index.html` in the current directory must load `/__rolldown/bundle.js`.
+  #[clap(long, value_name = "PORT", min_values(0), max_values(1))]
+  serve: Option<Option<u16>>,
+}
+
+fn main() {
+  env_logger::init();
+  let cli = Cli::parse();
+  if let Err(err) = run(cli) {
+    eprintln!("[!] Error: {}", err);
+    std::process::exit(1);
+  }
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+  let start = Instant::now();
+  let mut configs = match &cli.config {
+    Some(path) => load_config_file(path).map_err(|err| err.to_string())?,
+    None => vec![Default::default()],
+  };
+
+  let mut input = cli.entries;
+  input.extend(cli.input);
+  let format = cli
+    .format
+    .map(|format| format.parse::<InternalModuleFormat>())
+    .transpose()?;
+  let sourcemap = cli
+    .sourcemap
+    .map(|mode| mode.as_deref().unwrap_or("true").parse::<SourceMapMode>())
+    .transpose()?;
+
+  // Command line options take precedence over every config and output in the config file.
+  for config in &mut configs {
+    if !input.is_empty() {
+      config.input = input.clone();
+    }
+    if !cli.external.is_empty() {
+      config.external = cli.external.clone();
+    }
+    if cli.treeshake {
+      config.treeshake = Some(true);
+    }
+    if cli.cache_dir.is_some() {
+      config.cache_dir = cli.cache_dir.clone();
+    }
+    if config.output.is_empty() {
+      config.output.push(Default::default());
+    }
+    for output in &mut config.output {
+      output.file = cli.file.clone().or_else(|| output.file.take());
+      output.dir = cli.dir.clone().or_else(|| output.dir.take());
+      output.format = format.or(output.format);
+      output.name = cli.name.clone().or_else(|| output.name.take());
+      output.sourcemap = sourcemap.or(output.sourcemap);
+      if cli.minify {
+        output.minify.get_or_insert_with(Default::default);
+      }
+    }
+  }
+
+  let mut watchers = vec![];
+  for config in configs {
+    if config.input.is_empty() {
+      return Err("You must supply an input via \"--input\" or the config file.".to_string());
+    }
+    if let Some(missing) = config
+      .input
+      .iter()
+      .find(|entry| !Path::new(resolve_id(entry, None, false).id.as_str()).exists())
+    {
+      return Err(format!("Could not resolve entry module \"{}\".", missing));
+    }
+    let (mut input_options, outputs) = config.normalize().map_err(|err| err.to_string())?;
+    input_options.on_warn = Arc::new(|warning| eprintln!("(!) {}", warning));
+    if let Some(port) = cli.serve {
+      // Modules are bundled in memory for the dev server, so output options are ignored.
+      return serve::serve(input_options, port.unwrap_or(3000));
+    }
+    for output_options in outputs {
+      if cli.watch {
+        watchers.push(watch(input_options.clone(), output_options)?);
+      } else {
+        build(input_options.clone(), output_options, start)?;
+      }
+    }
+  }
+  if !watchers.is_empty() {
+    eprintln!("waiting for changes...");
+    loop {
+      std::thread::park();
+    }
+  }
+  Ok(())
+}
+
+fn watch(
+  input_options: NormalizedInputOptions,
+  output_options: NormalizedOutputOptions,
+) -> Result<Watcher, String> {
+  let destination = output_options
+    .file
+    .clone()
+    .or_else(|| output_options.dir.clone())
+    .ok_or_else(|| "You must specify \"--file\" or \"--dir\" in watch mode.".to_string())?;
+  Watcher::new(input_options, output_options, move |event| match event {
+    WatcherEvent::BuildStart => {}
+    WatcherEvent::BundleEnd { duration, .. } => {
+      eprintln!("created {} in {}ms", destination, duration.as_millis());
+    }
+    WatcherEvent::Error(err) => eprintln!("[!] Error: {}", err),
+  })
+  .map_err(|err| err.to_string())
+}
+
+fn build(
+  input_options: NormalizedInputOptions,
+  output_options: NormalizedOutputOptions,
+  start: Instant,
+) -> Result<(), String> {
+  let build = RolldownBuild::try_new(input_options).map_err(|err| err.to_string())?;
+  match output_options
+    .file
+    .clone()
+    .or_else(|| output_options.dir.clone())
+  {
+    Some(destination) => {
+      build.write(output_options).map_err(|err| err.to_string())?;
+      eprintln!(
+        "created {} in {}ms",
+        destination,
+        start.elapsed().as_millis()
+      );
+    }
+    None => {
+      let output = build
+        .generate(output_options)
+        .map_err(|err| err.to_string())?;
+      let chunks = output
+        .iter()
+        .filter(|output| matches!(output, RolldownOutput::Chunk(_)))
+        .collect::<Vec<_>>();
+      if chunks.len() > 1 {
+        return Err(
+          "Multiple chunks are generated, use \"--dir\" to write them to a directory.".to_string(),
+        );
+      }
+      chunks
+        .iter()
+        .for_each(|chunk| print!("{}", chunk.get_content()));
+    }
+  }
+  Ok(())
+}
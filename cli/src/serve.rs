@@ -0,0 +1,213 @@
+use std::{
+  collections::HashSet,
+  path::{Path, PathBuf},
+  sync::{mpsc, Arc, Mutex},
+  thread,
+  time::{Duration, Instant},
+};
+
+use rolldown::{
+  hmr::{DevBuild, BUNDLE_PATH, HMR_PATH},
+  types::NormalizedInputOptions,
+  watcher::{collect_changes, update_watched},
+};
+use serde_json::json;
+use tiny_http::{Header, Request, Response, Server, StatusCode};
+use tungstenite::{handshake::derive_accept_key, protocol::Role, Message, WebSocket};
+
+type Socket = WebSocket<Box<dyn tiny_http::ReadWrite + Send>>;
+
+const INDEX_HTML: &str = "<!DOCTYPE html>\n<html>\n  <head>\n    <meta charset=\"utf-8\" />\n  </head>\n  <body>\n    <script src=\"/__rolldown/bundle.js\"></script>\n  </body>\n</html>\n";
+
+/// Serve the bundle and files of the current directory on `port`, and push updates to pages when modules change.
+pub fn serve(input_options: NormalizedInputOptions, port: u16) -> Result<(), String> {
+  let start = Instant::now();
+  let build = DevBuild::new(input_options).map_err(|err| err.to_string())?;
+  let script = Arc::new(Mutex::new(build.script()));
+  let clients: Arc<Mutex<Vec<Socket>>> = Default::default();
+
+  let server = Server::http(("127.0.0.1", port)).map_err(|err| err.to_string())?;
+  eprintln!(
+    "serving on http://localhost:{} in {}ms",
+    port,
+    start.elapsed().as_millis()
+  );
+  {
+    let script = script.clone();
+    let clients = clients.clone();
+    thread::spawn(move || watch(build, script, clients));
+  }
+
+  for request in server.incoming_requests() {
+    let path = request.url().split('?').next().unwrap_or("/").to_string();
+    if path == HMR_PATH {
+      if let Some(socket) = upgrade(request) {
+        clients.lock().unwrap().push(socket);
+      }
+      continue;
+    }
+    let response = if path == BUNDLE_PATH {
+      respond_with(
+        script.lock().unwrap().clone().into_bytes(),
+        "application/javascript",
+      )
+    } else {
+      serve_file(&path)
+    };
+    // The page might be closed already.
+    request.respond(response).ok();
+  }
+  Ok(())
+}
+
+type FileResponse = Response<std::io::Cursor<Vec<u8>>>;
+
+fn respond_with(content: Vec<u8>, content_type: &str) -> FileResponse {
+  Response::from_data(content)
+    .with_header(Header::from_bytes("Content-Type", content_type).unwrap())
+}
+
+// Files are served from the current directory. `index.html` falls back to a page loading the bundle.
+fn serve_file(path: &str) -> FileResponse {
+  let relative = path.trim_start_matches('/');
+  let relative = if relative.is_empty() || relative.ends_with('/') {
+    format!("{}index.html", relative)
+  } else {
+    relative.to_string()
+  };
+  let file = PathBuf::from(&relative);
+  if file
+    .components()
+    .any(|component| matches!(component, std::path::Component::ParentDir))
+  {
+    return Response::from_string("Forbidden").with_status_code(StatusCode(403));
+  }
+  match std::fs::read(&file) {
+    Ok(content) => respond_with(content, content_type(&file)),
+    Err(_) if relative == "index.html" => {
+      respond_with(INDEX_HTML.as_bytes().to_vec(), "text/html; charset=utf-8")
+    }
+    Err(_) => Response::from_string("Not Found").with_status_code(StatusCode(404)),
+  }
+}
+
+fn content_type(file: &Path) -> &'static str {
+  match file.extension().and_then(|ext| ext.to_str()) {
+    Some("html") => "text/html; charset=utf-8",
+    Some("js" | "mjs") => "application/javascript",
+    Some("css") => "text/css",
+    Some("json" | "map") => "application/json",
+    Some("svg") => "image/svg+xml",
+    Some("png") => "image/png",
+    Some("jpg" | "jpeg") => "image/jpeg",
+    _ => "application/octet-stream",
+  }
+}
+
+fn upgrade(request: Request) -> Option<Socket> {
+  let key = request
+    .headers()
+    .iter()
+    .find(|header| header.field.equiv("Sec-WebSocket-Key"))
+    .map(|header| header.value.as_str().to_string());
+  let key = match key {
+    Some(key) => key,
+    None => {
+      request
+        .respond(
+          Response::from_string("Expected a WebSocket request").with_status_code(StatusCode(400)),
+        )
+        .ok();
+      return None;
+    }
+  };
+  let response = Response::empty(StatusCode(101)).with_header(
+    Header::from_bytes("Sec-WebSocket-Accept", derive_accept_key(key.as_bytes())).unwrap(),
+  );
+  let stream = request.upgrade("websocket", response);
+  Some(WebSocket::from_raw_socket(stream, Role::Server, None))
+}
+
+fn broadcast(clients: &Mutex<Vec<Socket>>, message: serde_json::Value) {
+  let message = message.to_string();
+  let mut clients = clients.lock().unwrap();
+  // Pages which are closed are dropped.
+  *clients = std::mem::take(&mut *clients)
+    .into_iter()
+    .filter_map(|mut socket| {
+      socket
+        .write_message(Message::Text(message.clone()))
+        .ok()
+        .map(|_| socket)
+    })
+    .collect();
+}
+
+fn watch(mut build: DevBuild, script: Arc<Mutex<String>>, clients: Arc<Mutex<Vec<Socket>>>) {
+  let (tx, rx) = mpsc::channel();
+  let mut watcher = match notify::watcher(tx, Duration::from_millis(50)) {
+    Ok(watcher) => watcher,
+    Err(err) => {
+      eprintln!("[!] Error: {}", err);
+      return;
+    }
+  };
+  let mut watched = HashSet::new();
+  loop {
+    let watch_files = build.watch_files().iter().cloned().collect::<HashSet<_>>();
+    if let Err(err) = update_watched(&mut watcher, &mut watched, watch_files) {
+      eprintln!("[!] Error: {}", err);
+    }
+
+    let mut changes = vec![];
+    match rx.recv() {
+      Ok(event) => collect_changes(event, &mut changes),
+      Err(_) => return,
+    }
+    while let Ok(event) = rx.try_recv() {
+      collect_changes(event, &mut changes);
+    }
+    if changes.is_empty() {
+      continue;
+    }
+    let changed_ids = changes
+      .iter()
+      .map(|(id, _)| id.as_str())
+      .collect::<Vec<_>>();
+    let entries = build.entries().to_vec();
+    match build.rebuild(&changed_ids) {
+      Ok(modules) => {
+        *script.lock().unwrap() = build.script();
+        // The runtime starts the entries once, so pages are reloaded to start new ones.
+        if build.entries() != entries {
+          eprintln!("reloading pages");
+          broadcast(&clients, json!({ "type": "full-reload" }));
+          continue;
+        }
+        if modules.is_empty() {
+          continue;
+        }
+        eprintln!(
+          "updated {}",
+          modules
+            .iter()
+            .map(|module| module.file_name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+        );
+        let modules = modules
+          .iter()
+          .map(|module| json!({ "file": module.file_name, "code": module.code }))
+          .collect::<Vec<_>>();
+        broadcast(&clients, json!({ "type": "update", "modules": modules }));
+      }
+      Err(err) => {
+        eprintln!("[!] Error: {}", err);
+        broadcast(
+          &clients,
+          json!({ "type": "error", "message": err.to_string() }),
+        );
+      }
+    }
+  }
+}
@@ -21,7 +21,7 @@ fn main() {
   //   GraphContainer::from_single_entry("./tests/fixtures/inter_module/index.js".to_owned());
   // let mut graph =
   //   GraphContainer::from_single_entry("../node_modules/lodash-es/lodash.js".to_owned());
-  let rolldown_build = RolldownBuild::new(NormalizedInputOptions {
+  let mut rolldown_build = RolldownBuild::new(NormalizedInputOptions {
     input: vec![
       // "./tests/fixtures/tree-shaking-advanced/index.js".to_owned(),
       "./tests/fixtures/re_export_default/index.js".to_owned(),
@@ -34,13 +34,16 @@ fn main() {
       // "../node_modules/lodash-es/array.default.js".to_owned()
     ],
     treeshake: false,
-  });
-  let output = rolldown_build.write(NormalizedOutputOptions {
-    // entry_file_names: "[name].js".to_string(),
-    file: Some("./output.js".to_string()),
-    // dir: Some("./output.js".to_string()),
     ..Default::default()
   });
+  let output = rolldown_build
+    .write(NormalizedOutputOptions {
+      // entry_file_names: "[name].js".to_string(),
+      file: Some("./output.js".to_string()),
+      // dir: Some("./output.js".to_string()),
+      ..Default::default()
+    })
+    .unwrap();
 
   log::info!("output:\n{:#?}", output);
   println!("{}", output[0].get_content());
@@ -1,8 +1,31 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use dashmap::DashSet;
+use linked_hash_map::LinkedHashMap;
+use petgraph::{graph::NodeIndex, visit::EdgeRef, EdgeDirection};
+use smol_str::SmolStr;
+use swc_atoms::JsWord;
+use swc_common::Mark;
+use swc_ecma_ast::Ident;
+use swc_ecma_visit::{noop_visit_type, Visit, VisitWith};
 
-use crate::{chunk::Chunk, graph, structs::OutputChunk, types::NormalizedOutputOptions};
+use crate::{
+  chunk::Chunk,
+  ext::SyntaxContextExt,
+  finalizers::{ChunkDependency, ImportBinding},
+  graph,
+  structs::{EmittedAsset, OutputAsset, OutputChunk, RenderedChunk, RolldownOutput},
+  types::{
+    ChunkContext, InternalModuleFormat, ManualChunksOption, NormalizedOutputOptions, SourceMapMode,
+  },
+  utils::{
+    file_name::{content_hash, render_file_name_pattern, DEFAULT_HASH_SIZE, HASH_PLACEHOLDER_RE},
+    lcp_of_array,
+    name_helpers::get_safe_name,
+    source_map::source_map_to_data_url,
+  },
+  worker::RolldownError,
+};
 
 #[non_exhaustive]
 pub struct Bundle {
@@ -18,52 +41,801 @@ impl Bundle {
     }
   }
 
-  fn generate_chunks(&self) -> Vec<Chunk> {
-    let entries = DashSet::new();
-    self.graph.entry_indexs.iter().for_each(|entry| {
-      let entry = self.graph.module_graph[*entry].to_owned();
-      entries.insert(entry);
+  // Assign modules to the chunks of `manualChunks`. Static dependencies of an assigned module
+  // follow it unless they are assigned to other chunks.
+  fn get_manual_chunk_aliases(&self) -> Result<LinkedHashMap<NodeIndex, String>, RolldownError> {
+    let graph = &self.graph;
+    let is_internal = |idx: &NodeIndex| graph.module_by_id.contains_key(&graph.module_graph[*idx]);
+    let mut alias_by_module: LinkedHashMap<NodeIndex, String> = Default::default();
+    match &self.output_options.manual_chunks {
+      None => return Ok(alias_by_module),
+      Some(ManualChunksOption::Map(map)) => {
+        let mut aliases = map.keys().collect::<Vec<_>>();
+        aliases.sort();
+        for alias in aliases {
+          for id in &map[alias] {
+            // Ids are resolved like entries, so plugins could resolve them.
+            let resolved = graph.plugin_driver.resolve(id, None, &[])?.id;
+            let node_idx = graph
+              .module_graph
+              .node_indices()
+              .find(|idx| graph.module_graph[*idx] == *id || graph.module_graph[*idx] == resolved)
+              .filter(is_internal);
+            if let Some(node_idx) = node_idx {
+              match alias_by_module.get(&node_idx) {
+                Some(existed) if existed != alias => {
+                  return Err(RolldownError::InvalidOption(format!(
+                    "Cannot assign \"{}\" to the \"{}\" chunk as it is already in the \"{}\" chunk.",
+                    id, alias, existed
+                  )));
+                }
+                _ => {
+                  alias_by_module.insert(node_idx, alias.clone());
+                }
+              }
+            }
+          }
+        }
+      }
+      Some(ManualChunksOption::Fn(get_manual_chunk)) => {
+        let ctx = ChunkContext { graph };
+        graph.ordered_modules.iter().for_each(|idx| {
+          if let Some(alias) = get_manual_chunk(&graph.module_graph[*idx], &ctx) {
+            alias_by_module.insert(*idx, alias);
+          }
+        });
+      }
+    }
+
+    let assigned = alias_by_module
+      .iter()
+      .map(|(idx, alias)| (*idx, alias.clone()))
+      .collect::<Vec<_>>();
+    assigned.into_iter().for_each(|(idx, alias)| {
+      let mut stack = graph
+        .module_graph
+        .edges_directed(idx, EdgeDirection::Outgoing)
+        .filter(|edge| edge.weight().is_static())
+        .map(|edge| edge.target())
+        .collect::<Vec<_>>();
+      while let Some(node_idx) = stack.pop() {
+        if alias_by_module.contains_key(&node_idx) || !is_internal(&node_idx) {
+          continue;
+        }
+        alias_by_module.insert(node_idx, alias.clone());
+        graph
+          .module_graph
+          .edges_directed(node_idx, EdgeDirection::Outgoing)
+          .filter(|edge| edge.weight().is_static())
+          .for_each(|edge| stack.push(edge.target()));
+      }
     });
+    Ok(alias_by_module)
+  }
 
-    let chunks = vec![Chunk {
-      id: Default::default(),
-      order_modules: self
-        .graph
-        .ordered_modules
+  // Modules assigned by `manualChunks` are put into their chunks first.
+  // The rest reached by the same set of entries are put into the same chunk.
+  // Modules imported dynamically are treated as entries too, unless they are inlined.
+  fn generate_chunks(&self) -> Result<Vec<Chunk>, RolldownError> {
+    let graph = &self.graph;
+    if self.output_options.preserve_modules {
+      if self.output_options.manual_chunks.is_some() {
+        log::warn!("\"output.manualChunks\" is ignored when \"output.preserveModules\" is true.");
+      }
+      return Ok(self.generate_preserved_module_chunks());
+    }
+    let alias_by_module = self.get_manual_chunk_aliases()?;
+    let inline_dynamic_imports = self.output_options.inline_dynamic_imports;
+    let entries = if inline_dynamic_imports {
+      graph.entry_indexs.iter().collect::<Vec<_>>()
+    } else {
+      graph
+        .entry_indexs
+        .iter()
+        .chain(graph.dynamic_entry_indexs.iter())
+        .collect::<Vec<_>>()
+    };
+    let mut dependent_entries: HashMap<NodeIndex, Vec<usize>> = Default::default();
+    entries.iter().enumerate().for_each(|(entry_index, entry)| {
+      let mut visited = HashSet::new();
+      let mut stack = vec![**entry];
+      while let Some(node_idx) = stack.pop() {
+        if !visited.insert(node_idx) || alias_by_module.contains_key(&node_idx) {
+          continue;
+        }
+        dependent_entries
+          .entry(node_idx)
+          .or_default()
+          .push(entry_index);
+        graph
+          .module_graph
+          .edges_directed(node_idx, EdgeDirection::Outgoing)
+          .filter(|edge| inline_dynamic_imports || edge.weight().is_static())
+          .for_each(|edge| stack.push(edge.target()));
+      }
+    });
+
+    let mut modules_by_alias: LinkedHashMap<String, Vec<SmolStr>> = alias_by_module
+      .values()
+      .map(|alias| (alias.clone(), vec![]))
+      .collect();
+    let mut modules_by_entries: LinkedHashMap<Vec<usize>, Vec<SmolStr>> = Default::default();
+    graph.ordered_modules.iter().for_each(|idx| {
+      if let Some(alias) = alias_by_module.get(idx) {
+        modules_by_alias
+          .get_mut(alias)
+          .unwrap()
+          .push(graph.module_graph[*idx].clone());
+        return;
+      }
+      modules_by_entries
+        .entry(dependent_entries.remove(idx).unwrap_or_default())
+        .or_insert_with(Default::default)
+        .push(graph.module_graph[*idx].clone());
+    });
+
+    let mut chunks = modules_by_alias
+      .into_iter()
+      .map(|(alias, modules)| {
+        let mut chunk = Chunk::new(modules, graph.symbol_box.clone(), DashSet::new());
+        chunk.name = Some(alias);
+        chunk
+      })
+      .chain(
+        modules_by_entries
+          .into_iter()
+          .map(|(_, modules)| Chunk::new(modules, graph.symbol_box.clone(), DashSet::new())),
+      )
+      .collect::<Vec<_>>();
+
+    entries.iter().for_each(|entry| {
+      let entry_id = &graph.module_graph[**entry];
+      let chunk = chunks
+        .iter()
+        .find(|chunk| chunk.order_modules.contains(entry_id))
+        .unwrap();
+      if chunk.entries.is_empty() {
+        chunk.entries.insert(entry_id.clone());
+      } else {
+        // The entry module is bundled into the chunk of another entry. Expose its exports via a facade chunk.
+        let entries = DashSet::new();
+        entries.insert(entry_id.clone());
+        chunks.push(Chunk::new(vec![], graph.symbol_box.clone(), entries));
+      }
+    });
+
+    Ok(chunks)
+  }
+
+  // Every module is put into its own chunk, named after its path relative to `preserveModulesRoot`
+  // or the common directory of all modules.
+  fn generate_preserved_module_chunks(&self) -> Vec<Chunk> {
+    let graph = &self.graph;
+    let ids = graph
+      .ordered_modules
+      .iter()
+      .map(|idx| graph.module_graph[*idx].clone())
+      .collect::<Vec<_>>();
+    if ids.is_empty() {
+      return vec![];
+    }
+    let common_prefix = lcp_of_array(&ids);
+    let common_dir = &common_prefix[..common_prefix.rfind('/').map_or(0, |slash| slash + 1)];
+    let preserve_modules_root = self
+      .output_options
+      .preserve_modules_root
+      .as_ref()
+      .map(|root| format!("{}/", nodejs_path::resolve!(root).trim_end_matches('/')));
+
+    graph
+      .ordered_modules
+      .iter()
+      .map(|idx| {
+        let id = &graph.module_graph[*idx];
+        let path = match &preserve_modules_root {
+          Some(root) if id.starts_with(root.as_str()) => &id[root.len()..],
+          _ => &id[common_dir.len()..],
+        };
+        let ext_len = nodejs_path::parse(path).ext.len();
+        let mut chunk = Chunk::new(vec![id.clone()], graph.symbol_box.clone(), DashSet::new());
+        chunk.name = Some(path[..path.len() - ext_len].to_string());
+        if graph.entry_indexs.contains(idx) || graph.dynamic_entry_indexs.contains(idx) {
+          chunk.entries.insert(id.clone());
+        }
+        chunk
+      })
+      .collect()
+  }
+
+  // Decide what each chunk imports from and exports to other chunks.
+  fn link_chunks(&self, chunks: &mut [Chunk]) {
+    let module_by_id = &self.graph.module_by_id;
+    let chunk_by_module = chunks
+      .iter()
+      .enumerate()
+      .flat_map(|(chunk_idx, chunk)| {
+        chunk
+          .order_modules
+          .iter()
+          .map(move |id| (id.clone(), chunk_idx))
+      })
+      .collect::<HashMap<_, _>>();
+    let node_by_module = self
+      .graph
+      .module_graph
+      .node_indices()
+      .map(|idx| (self.graph.module_graph[idx].clone(), idx))
+      .collect::<HashMap<_, _>>();
+
+    let mut symbol_box = self.graph.symbol_box.lock().unwrap();
+
+    // The chunk owning each symbol. Symbols imported from external modules are owned by the chunk importing them.
+    let mut owner_by_root: HashMap<Mark, (usize, JsWord)> = Default::default();
+    chunks.iter().enumerate().for_each(|(chunk_idx, chunk)| {
+      chunk
+        .order_modules
+        .iter()
+        .map(|id| module_by_id.get(id).unwrap())
+        .for_each(|module| {
+          module.declared_symbols.iter().for_each(|(name, mark)| {
+            owner_by_root
+              .entry(symbol_box.find_root(*mark))
+              .or_insert_with(|| (chunk_idx, name.clone()));
+          });
+        });
+    });
+    chunks.iter().enumerate().for_each(|(chunk_idx, chunk)| {
+      chunk
+        .order_modules
+        .iter()
+        .map(|id| module_by_id.get(id).unwrap())
+        .for_each(|module| {
+          module.imported_symbols.iter().for_each(|(name, mark)| {
+            owner_by_root
+              .entry(symbol_box.find_root(*mark))
+              .or_insert_with(|| (chunk_idx, name.clone()));
+          });
+        });
+    });
+
+    // importer -> owner -> root -> local name
+    let mut imports: Vec<LinkedHashMap<usize, LinkedHashMap<Mark, JsWord>>> =
+      vec![Default::default(); chunks.len()];
+    // facade -> owner -> [(root, exported)]
+    let mut facade_reexports: Vec<LinkedHashMap<usize, Vec<(Mark, JsWord)>>> =
+      vec![Default::default(); chunks.len()];
+
+    chunks.iter().enumerate().for_each(|(chunk_idx, chunk)| {
+      let mut collector = MarkCollector::default();
+      chunk
+        .order_modules
+        .iter()
+        .map(|id| module_by_id.get(id).unwrap())
+        .for_each(|module| {
+          module
+            .statements
+            .iter()
+            .filter(|stmt| stmt.included)
+            .for_each(|stmt| stmt.node.visit_with(&mut collector));
+
+          // Keep the execution order of modules in other chunks.
+          self
+            .graph
+            .module_graph
+            .edges_directed(node_by_module[&module.id], EdgeDirection::Outgoing)
+            .filter(|edge| edge.weight().is_static())
+            .filter_map(|edge| chunk_by_module.get(&self.graph.module_graph[edge.target()]))
+            .filter(|owner| **owner != chunk_idx)
+            .for_each(|owner| {
+              imports[chunk_idx]
+                .entry(*owner)
+                .or_insert_with(Default::default);
+            });
+        });
+
+      let is_facade = chunk.order_modules.is_empty();
+      chunk.entries.iter().for_each(|entry| {
+        let module = module_by_id.get(entry.key()).unwrap();
+        let mut exports = module
+          .exports
+          .iter()
+          .filter(|(exported, _)| *exported != "*")
+          .collect::<Vec<_>>();
+        exports.sort_by(|a, b| a.0.cmp(b.0));
+        exports.into_iter().for_each(|(exported, mark)| {
+          let root = symbol_box.find_root(*mark);
+          if is_facade {
+            if let Some((owner, _)) = owner_by_root.get(&root) {
+              facade_reexports[chunk_idx]
+                .entry(*owner)
+                .or_insert_with(Default::default)
+                .push((root, exported.clone()));
+            }
+          } else {
+            collector.marks.push(root);
+          }
+        });
+      });
+
+      collector.marks.iter().for_each(|mark| {
+        let root = symbol_box.find_root(*mark);
+        if let Some((owner, name)) = owner_by_root.get(&root) {
+          if *owner != chunk_idx {
+            imports[chunk_idx]
+              .entry(*owner)
+              .or_insert_with(Default::default)
+              .entry(root)
+              .or_insert_with(|| name.clone());
+          }
+        }
+      });
+    });
+
+    // Exports of entry modules keep their names. Other symbols are exported with their own names if possible.
+    let mut export_names = chunks
+      .iter()
+      .map(|chunk| {
+        let mut used_names = HashSet::new();
+        let mut names = HashMap::new();
+        chunk.entries.iter().for_each(|entry| {
+          let module = module_by_id.get(entry.key()).unwrap();
+          module.exports.iter().for_each(|(exported, mark)| {
+            used_names.insert(exported.to_string());
+            names.insert(symbol_box.find_root(*mark), exported.clone());
+          });
+        });
+        (used_names, names)
+      })
+      .collect::<Vec<_>>();
+    let mut get_export_name = |owner: usize, root: Mark, name: &JsWord| -> JsWord {
+      let (used_names, names) = &mut export_names[owner];
+      names
+        .entry(root)
+        .or_insert_with(|| get_safe_name(name, used_names).into())
         .clone()
-        .into_iter()
-        .map(|idx| self.graph.module_graph[idx].clone())
-        .collect(),
-      symbol_box: self.graph.symbol_box.clone(),
-      entries,
-    }];
+    };
+
+    let mut chunk_dependencies = vec![vec![]; chunks.len()];
+    imports
+      .into_iter()
+      .enumerate()
+      .for_each(|(importer, owners)| {
+        owners.into_iter().for_each(|(owner, roots)| {
+          let mut dep = ChunkDependency::new(
+            get_relative_import_path(&chunks[importer].id, &chunks[owner].id).into(),
+          );
+          roots.into_iter().for_each(|(root, name)| {
+            let exported = get_export_name(owner, root, &name);
+            chunks[owner]
+              .exports_for_chunks
+              .insert(root, exported.clone());
+            dep.imports.push(ImportBinding {
+              imported: exported,
+              mark: root,
+              local: name.to_string(),
+            });
+          });
+          chunk_dependencies[importer].push(dep);
+        });
+      });
+    facade_reexports
+      .into_iter()
+      .enumerate()
+      .for_each(|(facade, owners)| {
+        owners.into_iter().for_each(|(owner, reexports)| {
+          let mut dep = ChunkDependency::new(
+            get_relative_import_path(&chunks[facade].id, &chunks[owner].id).into(),
+          );
+          reexports.into_iter().for_each(|(root, exported)| {
+            let name = owner_by_root[&root].1.clone();
+            let imported = get_export_name(owner, root, &name);
+            chunks[owner]
+              .exports_for_chunks
+              .insert(root, imported.clone());
+            dep.add_reexport(imported, exported);
+          });
+          chunk_dependencies[facade].push(dep);
+        });
+      });
 
     chunks
+      .iter_mut()
+      .zip(chunk_dependencies)
+      .for_each(|(chunk, dependencies)| chunk.chunk_dependencies = dependencies);
+
+    // `import('./foo')` is rewritten to import the chunk exposing `foo.js`.
+    let chunk_by_entry = chunks
+      .iter()
+      .enumerate()
+      .flat_map(|(chunk_idx, chunk)| {
+        chunk
+          .entries
+          .iter()
+          .map(|entry| (entry.key().clone(), chunk_idx))
+          .collect::<Vec<_>>()
+      })
+      .collect::<HashMap<_, _>>();
+    let dynamic_imports = chunks
+      .iter()
+      .map(|chunk| {
+        chunk
+          .order_modules
+          .iter()
+          .flat_map(|id| {
+            self
+              .graph
+              .module_graph
+              .edges_directed(node_by_module[id], EdgeDirection::Outgoing)
+              .filter(|edge| !edge.weight().is_static())
+              .map(|edge| &self.graph.module_graph[edge.target()])
+          })
+          .filter_map(|imported| {
+            chunk_by_entry.get(imported).map(|imported_chunk| {
+              (
+                imported.clone(),
+                get_relative_import_path(&chunk.id, &chunks[*imported_chunk].id),
+              )
+            })
+          })
+          .collect::<HashMap<_, _>>()
+      })
+      .collect::<Vec<_>>();
+    chunks
+      .iter_mut()
+      .zip(dynamic_imports)
+      .for_each(|(chunk, dynamic_imports)| chunk.dynamic_imports = dynamic_imports);
   }
 
-  pub fn generate(&mut self) -> HashMap<String, OutputChunk> {
-    let mut chunks = self.generate_chunks();
+  // Dynamically imported modules are bundled into the importer's chunk and exposed as namespace objects.
+  fn inline_dynamic_imports(&mut self) -> Result<(), RolldownError> {
+    if self.graph.entry_indexs.len() > 1 {
+      return Err(RolldownError::InvalidOption(
+        "Multiple inputs are not supported when \"output.inlineDynamicImports\" is true."
+          .to_string(),
+      ));
+    }
+    let mark_to_stmt = self.graph.mark_to_stmt.clone();
+    self.graph.dynamic_entry_indexs.iter().for_each(|idx| {
+      let module = self
+        .graph
+        .module_by_id
+        .get_mut(&self.graph.module_graph[*idx])
+        .unwrap();
+      module.include_namespace(mark_to_stmt.clone());
+      // The namespace might be created before without being used.
+      if let Some(stmt_idx) = mark_to_stmt.get(&module.namespace.mark).map(|pair| pair.1) {
+        module.statements[stmt_idx].include();
+      }
+    });
+    Ok(())
+  }
+
+  pub fn generate(&mut self) -> Result<Vec<RolldownOutput>, RolldownError> {
+    self
+      .graph
+      .plugin_driver
+      .render_start(&self.output_options, &self.graph.input_options)?;
+    if self.output_options.inline_dynamic_imports {
+      if self.output_options.preserve_modules {
+        return Err(RolldownError::InvalidOption(
+          "The \"output.inlineDynamicImports\" option is not supported for \"output.preserveModules\"."
+            .to_string(),
+        ));
+      }
+      self.inline_dynamic_imports()?;
+    }
+    let mut chunks = self.generate_chunks()?;
+
+    if chunks.len() > 1 {
+      if matches!(
+        self.output_options.format,
+        InternalModuleFormat::UMD | InternalModuleFormat::IIFE
+      ) {
+        return Err(RolldownError::InvalidOption(format!(
+          "UMD and IIFE output formats are not supported for code-splitting builds, but {} chunks were generated.",
+          chunks.len()
+        )));
+      }
+      if self.output_options.file.is_some() {
+        return Err(RolldownError::InvalidOption(
+          "When building multiple chunks, the \"output.dir\" option must be used, not \"output.file\"."
+            .to_string(),
+        ));
+      }
+    }
 
-    chunks.iter_mut().for_each(|chunk| {
+    let user_entries = self
+      .graph
+      .entry_indexs
+      .iter()
+      .map(|idx| self.graph.module_graph[*idx].clone())
+      .collect::<HashSet<_>>();
+    let mut used_ids = HashSet::new();
+    for (index, chunk) in chunks.iter_mut().enumerate() {
       if let Some(file) = &self.output_options.file {
         chunk.id = nodejs_path::basename!(file).into();
       } else {
-        chunk.id = chunk.generate_id(&self.output_options);
+        // Chunks of dynamic imports and shared modules are named by `chunkFileNames`.
+        let is_entry = self.output_options.preserve_modules
+          || chunk
+            .entries
+            .iter()
+            .any(|id| user_entries.contains(id.key()));
+        let id = chunk.generate_id(index, is_entry, &self.output_options)?;
+        chunk.id = make_unique(&id, &mut used_ids).into();
       }
+    }
+
+    self.graph.module_by_id.values_mut().for_each(|module| {
+      module.trim_exports();
     });
+    self.link_chunks(&mut chunks);
 
-    chunks
+    let rendered_chunks = chunks
       .iter_mut()
       .map(|chunk| {
-        let chunk = chunk.render(&self.output_options, &mut self.graph.module_by_id);
-        (
-          chunk.file_name.clone(),
-          OutputChunk {
-            code: chunk.code,
-            file_name: chunk.file_name,
-          },
+        chunk.render(
+          &self.output_options,
+          &mut self.graph.module_by_id,
+          &self.graph.plugin_driver,
         )
       })
-      .collect()
+      .collect::<Result<Vec<_>, _>>()?;
+    let hashes = get_hashes(&rendered_chunks);
+    let replace_hashes = |text: &str| {
+      HASH_PLACEHOLDER_RE
+        .replace_all(text, |captures: &regex::Captures| {
+          hashes.get(&captures[0]).map_or_else(
+            || captures[0].to_string(),
+            |hash| hash[..captures[0].len()].to_string(),
+          )
+        })
+        .into_owned()
+    };
+
+    let mut output = rendered_chunks
+      .into_iter()
+      .map(|chunk| {
+        let file_name = replace_hashes(&chunk.file_name);
+        let mut code = replace_hashes(&chunk.code);
+        let map = chunk.map.map(|mut map| {
+          let file = map.get_file().map(replace_hashes);
+          map.set_file(file.as_deref());
+          map
+        });
+        if let Some(map) = &map {
+          match self.output_options.sourcemap {
+            SourceMapMode::File => code.push_str(&format!(
+              "//# sourceMappingURL={}.map\n",
+              nodejs_path::basename!(&file_name)
+            )),
+            SourceMapMode::Inline => code.push_str(&format!(
+              "//# sourceMappingURL={}\n",
+              source_map_to_data_url(map)
+            )),
+            _ => {}
+          }
+        }
+        RolldownOutput::Chunk(OutputChunk {
+          code,
+          file_name,
+          map,
+          is_entry: chunk.is_entry,
+          facade_module_id: chunk.facade_module_id,
+          module_ids: chunk.module_ids,
+        })
+      })
+      .collect::<Vec<_>>();
+
+    {
+      let mut file_names = self.graph.plugin_driver.file_names.write().unwrap();
+      for (reference_id, id) in self
+        .graph
+        .plugin_driver
+        .chunk_references
+        .lock()
+        .unwrap()
+        .iter()
+      {
+        let chunk = output.iter().find(|output| match output {
+          RolldownOutput::Chunk(chunk) => chunk.facade_module_id.as_deref() == Some(id.as_str()),
+          _ => false,
+        });
+        if let Some(chunk) = chunk {
+          file_names.insert(reference_id.clone(), chunk.get_file_name().to_string());
+        }
+      }
+    }
+
+    let mut used_file_names = output
+      .iter()
+      .map(|output| output.get_file_name().to_lowercase())
+      .collect::<HashSet<_>>();
+    for asset in &self.graph.emitted_assets {
+      let asset = self.generate_asset(asset, &mut used_file_names)?;
+      output.push(RolldownOutput::Asset(asset));
+    }
+    for (reference_id, asset) in &self.graph.plugin_assets {
+      let asset = self.generate_referenced_asset(reference_id, asset, &mut used_file_names)?;
+      output.push(RolldownOutput::Asset(asset));
+    }
+    self.generate_plugin_assets(&mut output)?;
+    Ok(output)
+  }
+
+  /// Add assets emitted by plugins since the last call, e.g. in the `generate_bundle` hook.
+  pub(crate) fn generate_plugin_assets(
+    &self,
+    output: &mut Vec<RolldownOutput>,
+  ) -> Result<(), RolldownError> {
+    let assets = std::mem::take(&mut *self.graph.plugin_driver.emitted_assets.lock().unwrap());
+    let mut used_file_names = output
+      .iter()
+      .map(|output| output.get_file_name().to_lowercase())
+      .collect::<HashSet<_>>();
+    for (reference_id, asset) in &assets {
+      let asset = self.generate_referenced_asset(reference_id, asset, &mut used_file_names)?;
+      output.push(RolldownOutput::Asset(asset));
+    }
+    Ok(())
+  }
+
+  // The file name is known to `PluginContext::get_file_name` afterwards.
+  fn generate_referenced_asset(
+    &self,
+    reference_id: &str,
+    asset: &EmittedAsset,
+    used_file_names: &mut HashSet<String>,
+  ) -> Result<OutputAsset, RolldownError> {
+    let asset = self.generate_asset(asset, used_file_names)?;
+    self
+      .graph
+      .plugin_driver
+      .file_names
+      .write()
+      .unwrap()
+      .insert(reference_id.to_string(), asset.file_name.clone());
+    Ok(asset)
+  }
+
+  fn generate_asset(
+    &self,
+    asset: &EmittedAsset,
+    used_file_names: &mut HashSet<String>,
+  ) -> Result<OutputAsset, RolldownError> {
+    let file_name = match &asset.file_name {
+      Some(file_name) => file_name.clone(),
+      None => {
+        let name = asset.name.as_deref().unwrap_or("asset");
+        let extname = nodejs_path::parse(name).ext;
+        let file_name = render_file_name_pattern(
+          &self.output_options.asset_file_names,
+          "assetFileNames",
+          |placeholder, size| {
+            Ok(match placeholder {
+              "name" => Some(name[..name.len() - extname.len()].to_string()),
+              "extname" => Some(extname.clone()),
+              "ext" => Some(extname.trim_start_matches('.').to_string()),
+              "hash" => {
+                let size = size.unwrap_or(DEFAULT_HASH_SIZE);
+                let hash = content_hash(&asset.source);
+                if size > hash.len() {
+                  return Err(RolldownError::InvalidOption(format!(
+                    "Hashes can't be longer than {} characters, received {}.",
+                    hash.len(),
+                    size
+                  )));
+                }
+                Some(hash[..size].to_string())
+              }
+              _ => None,
+            })
+          },
+        )?;
+        make_unique(&file_name, used_file_names)
+      }
+    };
+    Ok(OutputAsset {
+      file_name,
+      source: asset.source.clone(),
+      name: asset.name.clone(),
+    })
+  }
+}
+
+#[derive(Default)]
+struct MarkCollector {
+  marks: Vec<Mark>,
+}
+
+impl Visit for MarkCollector {
+  noop_visit_type!();
+
+  fn visit_ident(&mut self, ident: &Ident) {
+    self.marks.push(ident.span.ctxt.as_mark());
+  }
+}
+
+// The hash of a chunk covers its own code and the code of all chunks it references, so
+// changes of an imported chunk are reflected in the file names of its importers.
+fn get_hashes(chunks: &[RenderedChunk]) -> HashMap<String, String> {
+  let placeholders = chunks
+    .iter()
+    .map(|chunk| {
+      HASH_PLACEHOLDER_RE
+        .find(&chunk.file_name)
+        .map(|placeholder| placeholder.as_str().to_string())
+    })
+    .collect::<Vec<_>>();
+  let chunk_by_placeholder = placeholders
+    .iter()
+    .enumerate()
+    .filter_map(|(index, placeholder)| placeholder.as_ref().map(|p| (p.as_str(), index)))
+    .collect::<HashMap<_, _>>();
+  let content_hashes = chunks
+    .iter()
+    .map(|chunk| {
+      if chunk.hash_augmentation.is_empty() {
+        content_hash(chunk.code.as_bytes())
+      } else {
+        content_hash(format!("{}{}", chunk.code, chunk.hash_augmentation).as_bytes())
+      }
+    })
+    .collect::<Vec<_>>();
+
+  placeholders
+    .iter()
+    .enumerate()
+    .filter_map(|(index, placeholder)| {
+      let placeholder = placeholder.as_ref()?;
+      let mut referenced = HashSet::from([index]);
+      let mut stack = vec![index];
+      while let Some(index) = stack.pop() {
+        HASH_PLACEHOLDER_RE
+          .find_iter(&chunks[index].code)
+          .filter_map(|p| chunk_by_placeholder.get(p.as_str()))
+          .for_each(|index| {
+            if referenced.insert(*index) {
+              stack.push(*index);
+            }
+          });
+      }
+      let mut referenced = referenced.into_iter().collect::<Vec<_>>();
+      referenced.sort_unstable();
+      let hash_input = referenced
+        .into_iter()
+        .map(|index| content_hashes[index].as_str())
+        .collect::<String>();
+      Some((placeholder.clone(), content_hash(hash_input.as_bytes())))
+    })
+    .collect()
+}
+
+// `chunks/a.js` imports `b.js` via `../b.js`.
+fn get_relative_import_path(importer: &str, imported: &str) -> String {
+  let path = nodejs_path::relative(
+    &nodejs_path::dirname(&format!("/{}", importer)),
+    &format!("/{}", imported),
+  );
+  if path.starts_with('.') {
+    path
+  } else {
+    format!("./{}", path)
+  }
+}
+
+// `index.js` becomes `index2.js` if it's already used. File names are compared case-insensitively.
+fn make_unique(id: &str, used_ids: &mut HashSet<String>) -> String {
+  let (stem, ext) = match id.rfind('.') {
+    Some(dot) if dot > id.rfind('/').map_or(0, |slash| slash + 1) => id.split_at(dot),
+    _ => (id, ""),
+  };
+  let mut unique_id = id.to_string();
+  let mut count = 1;
+  while used_ids.contains(&unique_id.to_lowercase()) {
+    count += 1;
+    unique_id = format!("{}{}{}", stem, count, ext);
   }
+  used_ids.insert(unique_id.to_lowercase());
+  unique_id
 }
@@ -1,34 +1,70 @@
 use crate::utils::lcp_of_array;
 use dashmap::DashSet;
+use linked_hash_map::LinkedHashMap;
 use smol_str::SmolStr;
 use std::{
   collections::{HashMap, HashSet},
   path::Path,
   sync::{Arc, Mutex},
 };
+use swc_atoms::JsWord;
 
 use crate::{
   compiler::SOURCE_MAP,
+  ext::SyntaxContextExt,
+  finalizers::{finalize, ChunkDependency, ChunkExport, FinalizerArgs, ImportBinding},
   module::Module,
+  plugin_driver::{PluginDriver, SourceDescription},
   renamer::Renamer,
-  structs::{OutputChunk, RenderedChunk},
+  scanner::rel::get_sym_from_module_export,
+  structs::{ChunkInfo, OutputChunk, RenderedChunk},
   symbol_box::SymbolBox,
-  types::NormalizedOutputOptions,
-  utils::lcp,
+  types::{Addon, InternalModuleFormat, NormalizedOutputOptions},
+  utils::{
+    ast_sugar,
+    file_name::{hash_placeholder, render_file_name_pattern, DEFAULT_HASH_SIZE},
+    lcp,
+    minify::minify,
+    name_helpers::{get_safe_name, make_legal},
+    source_map::{build_source_map, get_output_file_path},
+  },
+  worker::RolldownError,
 };
 
 use rayon::prelude::*;
 
-use swc_common::comments::{Comment, Comments, SingleThreadedComments};
-use swc_ecma_ast::EsVersion;
+use swc_common::{
+  comments::{Comment, Comments, SingleThreadedComments},
+  util::take::Take,
+  Mark, DUMMY_SP,
+};
+use swc_ecma_ast::{
+  self as ast, AssignExpr, AssignPatProp, BinExpr, BinaryOp, BindingIdent, Callee, EsVersion,
+  ExportSpecifier, Expr, ExprStmt, Ident, ImportSpecifier, Lit, ModuleDecl, ModuleItem,
+  NamedExport, Number, ParenExpr, Pat, PatOrExpr, SeqExpr, Stmt, UnaryExpr, UnaryOp, UpdateOp,
+};
 use swc_ecma_codegen::text_writer::JsWriter;
-use swc_ecma_visit::VisitMutWith;
+use swc_ecma_visit::{
+  noop_visit_mut_type, noop_visit_type, Visit, VisitMut, VisitMutWith, VisitWith,
+};
+
+// Stands for the rendered modules while the chunk is finalized. Generated code never contains a NUL character.
+const BODY_PLACEHOLDER: &str = "\0rolldown:chunk-body\0";
 
 pub struct Chunk {
   pub id: SmolStr,
   pub order_modules: Vec<SmolStr>,
   pub symbol_box: Arc<Mutex<SymbolBox>>,
+  // Entry modules whose exports are exposed by the chunk
   pub entries: DashSet<SmolStr>,
+  // Imports of symbols from other chunks. They are decided in `Bundle::link_chunks`.
+  pub chunk_dependencies: Vec<ChunkDependency>,
+  // Symbols used by other chunks and the names they are exported as.
+  pub exports_for_chunks: LinkedHashMap<Mark, JsWord>,
+  // Modules imported dynamically and the relative paths of the chunks exposing them.
+  pub dynamic_imports: HashMap<SmolStr, String>,
+  // Name given by `manualChunks` or `preserveModules`
+  pub name: Option<String>,
 }
 
 impl Chunk {
@@ -42,71 +78,371 @@ impl Chunk {
       order_modules,
       symbol_box,
       entries,
+      chunk_dependencies: Default::default(),
+      exports_for_chunks: Default::default(),
+      dynamic_imports: Default::default(),
+      name: None,
     }
   }
 
-  pub fn de_conflict(&mut self, modules: &mut HashMap<SmolStr, Box<Module>>) {
+  pub fn de_conflict(
+    &mut self,
+    modules: &mut HashMap<SmolStr, Box<Module>>,
+    dependencies: &mut [ChunkDependency],
+    format: InternalModuleFormat,
+  ) -> HashMap<Mark, String> {
     let mut used_names = HashSet::new();
     let mut mark_to_name = HashMap::new();
+    if format != InternalModuleFormat::ES {
+      // Avoid shadowing variables that the wrapper of the format relies on.
+      used_names.extend(["exports", "module", "require"].map(String::from));
+    }
 
-    // De-conflict from the entry module to keep namings as simple as possible
-    self
-      .order_modules
-      .iter()
-      .map(|id| modules.get(id).unwrap())
-      .rev()
-      .for_each(|module| {
-        module.declared_symbols.iter().for_each(|(name, mark)| {
-          let root_mark = self.symbol_box.lock().unwrap().find_root(*mark);
-          if let std::collections::hash_map::Entry::Vacant(e) = mark_to_name.entry(root_mark) {
-            let original_name = name.to_string();
-            let mut name = name.to_string();
-            let mut count = 0;
-            while used_names.contains(&name) {
-              name = format!("{}${}", original_name, count);
-              count += 1;
+    {
+      let mut symbol_box = self.symbol_box.lock().unwrap();
+      // De-conflict from the entry module to keep namings as simple as possible
+      self
+        .order_modules
+        .iter()
+        .map(|id| modules.get(id).unwrap())
+        .rev()
+        .for_each(|module| {
+          module.declared_symbols.iter().for_each(|(name, mark)| {
+            let root_mark = symbol_box.find_root(*mark);
+            if let std::collections::hash_map::Entry::Vacant(e) = mark_to_name.entry(root_mark) {
+              e.insert(get_safe_name(name, &mut used_names));
             }
-            e.insert(name.clone());
-            used_names.insert(name);
-          } else {
-          }
+          });
+        });
+
+      dependencies.iter_mut().for_each(|dep| {
+        // System format keeps the imported bindings as local variables, which are updated by setters.
+        if matches!(
+          format,
+          InternalModuleFormat::ES | InternalModuleFormat::System
+        ) {
+          dep.imports.iter_mut().for_each(|binding| {
+            binding.local = get_safe_name(&binding.local, &mut used_names);
+          });
+        } else {
+          // For non-ES formats, named imports are accessed via the variable holding the dependency to keep live bindings.
+          dep.name = get_safe_name(&make_legal(get_alias_name(&dep.id)), &mut used_names);
+          dep.imports.iter_mut().for_each(|binding| {
+            binding.local = match &*binding.imported {
+              "default" => get_safe_name(&format!("{}__default", dep.name), &mut used_names),
+              "*" => get_safe_name(&format!("{}__namespace", dep.name), &mut used_names),
+              imported => format!("{}.{}", dep.name, imported),
+            };
+          });
+        }
+        dep.imports.iter().for_each(|binding| {
+          mark_to_name.insert(symbol_box.find_root(binding.mark), binding.local.clone());
         });
       });
+    }
 
-    modules.par_iter_mut().for_each(|(_, module)| {
-      module.statements.iter_mut().for_each(|stmt| {
-        let mut renamer = Renamer {
-          mark_to_names: &mark_to_name,
-          symbol_box: self.symbol_box.clone(),
-        };
-        stmt.node.visit_mut_with(&mut renamer);
+    let chunk_module_ids = self.order_modules.iter().collect::<HashSet<_>>();
+    modules
+      .par_iter_mut()
+      .filter(|(id, _)| chunk_module_ids.contains(id))
+      .for_each(|(_, module)| {
+        module.statements.iter_mut().for_each(|stmt| {
+          let mut renamer = Renamer {
+            mark_to_names: &mark_to_name,
+            symbol_box: self.symbol_box.clone(),
+          };
+          stmt.node.visit_mut_with(&mut renamer);
+        });
       });
-    });
 
     log::debug!("mark_to_name {:#?}", mark_to_name);
+    mark_to_name
+  }
+
+  // Collect imports and re-exports of external modules and remove them from the rendered modules.
+  // They will be rendered by the finalizer of the output format.
+  fn collect_dependencies(
+    &self,
+    modules: &mut HashMap<SmolStr, Box<Module>>,
+  ) -> Result<Vec<ChunkDependency>, RolldownError> {
+    let mut dependencies: LinkedHashMap<SmolStr, ChunkDependency> = Default::default();
+    let mut symbol_box = self.symbol_box.lock().unwrap();
+
+    self.order_modules.iter().try_for_each(|id| {
+      if let Some(module) = modules.get_mut(id) {
+        let is_entry = self.entries.contains(id);
+        let mut module_dependencies = vec![];
+        module.statements.iter_mut().for_each(|stmt| {
+          if !stmt.included {
+            return;
+          }
+          if let ModuleItem::ModuleDecl(module_decl) = &stmt.node {
+            let src = match module_decl {
+              ModuleDecl::Import(import_decl) => &import_decl.src.value,
+              ModuleDecl::ExportAll(export_all) => &export_all.src.value,
+              ModuleDecl::ExportNamed(NamedExport { src: Some(src), .. }) => &src.value,
+              _ => return,
+            };
+            log::debug!("[chunk]: collect dependency {:?} in {}", src, id);
+            module_dependencies.push(module_decl.clone());
+            stmt.included = false;
+          }
+        });
+
+        module_dependencies
+          .into_iter()
+          .try_for_each(|module_decl| match module_decl {
+            ModuleDecl::Import(import_decl) => {
+              let dep_id = module.resolve_id(&import_decl.src.value).id;
+              let dep = dependencies
+                .entry(dep_id.clone())
+                .or_insert_with(|| ChunkDependency::new(dep_id));
+              import_decl
+                .specifiers
+                .into_iter()
+                .try_for_each(|specifier| {
+                  let (imported, local) = match specifier {
+                    ImportSpecifier::Default(s) => ("default".into(), s.local),
+                    ImportSpecifier::Named(s) => (
+                      s.imported
+                        .as_ref()
+                        .map_or_else(|| Ok(s.local.sym.clone()), get_sym_from_module_export)?,
+                      s.local,
+                    ),
+                    ImportSpecifier::Namespace(s) => ("*".into(), s.local),
+                  };
+                  let mark = local.span.ctxt.as_mark();
+                  if let Some(existed) = dep.find_import(&imported) {
+                    // Importing the same binding from the same module in multiple modules. Treat them as one.
+                    symbol_box.union(existed.mark, mark);
+                  } else {
+                    dep.imports.push(ImportBinding {
+                      imported,
+                      mark,
+                      local: local.sym.to_string(),
+                    });
+                  }
+                  Ok::<(), RolldownError>(())
+                })
+            }
+            ModuleDecl::ExportAll(export_all) => {
+              let dep_id = module.resolve_id(&export_all.src.value).id;
+              let dep = dependencies
+                .entry(dep_id.clone())
+                .or_insert_with(|| ChunkDependency::new(dep_id));
+              if is_entry {
+                dep.add_reexport("*".into(), "*".into());
+              }
+              Ok(())
+            }
+            ModuleDecl::ExportNamed(named_export) => {
+              let dep_id = module.resolve_id(&named_export.src.unwrap().value).id;
+              let dep = dependencies
+                .entry(dep_id.clone())
+                .or_insert_with(|| ChunkDependency::new(dep_id));
+              if is_entry {
+                named_export.specifiers.iter().try_for_each(|specifier| {
+                  match specifier {
+                    ExportSpecifier::Named(s) => {
+                      let imported = get_sym_from_module_export(&s.orig)?;
+                      let exported = s
+                        .exported
+                        .as_ref()
+                        .map_or_else(|| Ok(imported.clone()), get_sym_from_module_export)?;
+                      dep.add_reexport(imported, exported);
+                    }
+                    ExportSpecifier::Namespace(s) => {
+                      dep.add_reexport("*".into(), get_sym_from_module_export(&s.name)?);
+                    }
+                    // `export v from 'mod'` is not supported.
+                    ExportSpecifier::Default(_) => {}
+                  }
+                  Ok::<(), RolldownError>(())
+                })?;
+              }
+              Ok(())
+            }
+            _ => Ok::<(), RolldownError>(()),
+          })?;
+      }
+      Ok::<(), RolldownError>(())
+    })?;
+
+    Ok(dependencies.into_iter().map(|(_, dep)| dep).collect())
+  }
+
+  // Point `import('./foo')` to the chunk of `foo`, and transform it for formats without native dynamic imports.
+  // With `inlineDynamicImports`, `foo` is in this chunk and the import resolves to its namespace object.
+  fn rewrite_dynamic_imports(
+    &self,
+    modules: &mut HashMap<SmolStr, Box<Module>>,
+    mark_to_name: &HashMap<Mark, String>,
+    options: &NormalizedOutputOptions,
+  ) {
+    let mut symbol_box = self.symbol_box.lock().unwrap();
+    let namespaces = if options.inline_dynamic_imports {
+      self
+        .order_modules
+        .iter()
+        .filter_map(|id| modules.get(id))
+        .filter(|module| module.namespace.included)
+        .filter_map(|module| {
+          mark_to_name
+            .get(&symbol_box.find_root(module.namespace.mark))
+            .map(|name| (module.id.clone(), name.clone()))
+        })
+        .collect::<HashMap<_, _>>()
+    } else {
+      Default::default()
+    };
+
+    self.order_modules.iter().for_each(|id| {
+      if let Some(module) = modules.get_mut(id) {
+        let sources = module
+          .resolved_ids
+          .iter()
+          .filter_map(|resolved| {
+            let target = match self.dynamic_imports.get(&resolved.id) {
+              Some(path) => DynamicImportTarget::Chunk(path.clone()),
+              None => DynamicImportTarget::Namespace(namespaces.get(&resolved.id)?.clone()),
+            };
+            Some((resolved.key().clone(), target))
+          })
+          .collect::<HashMap<_, _>>();
+        let mut rewriter = DynamicImportRewriter {
+          sources: &sources,
+          format: options.format,
+        };
+        module
+          .statements
+          .iter_mut()
+          .filter(|stmt| stmt.included)
+          .for_each(|stmt| stmt.node.visit_mut_with(&mut rewriter));
+      }
+    });
+  }
+
+  // Exports of System chunks are live bindings, so importers are notified whenever an exported binding is reassigned.
+  fn export_reassignments(
+    &self,
+    modules: &mut HashMap<SmolStr, Box<Module>>,
+    exports: &[ChunkExport],
+  ) {
+    let mut exported_names: HashMap<Mark, Vec<JsWord>> = HashMap::new();
+    exports.iter().for_each(|export| {
+      exported_names
+        .entry(export.mark)
+        .or_default()
+        .push(export.exported.clone())
+    });
+    let mut symbol_box = self.symbol_box.lock().unwrap();
+    let mut rewriter = LiveExportRewriter {
+      exported_names: &exported_names,
+      symbol_box: &mut symbol_box,
+    };
+    self.order_modules.iter().for_each(|id| {
+      if let Some(module) = modules.get_mut(id) {
+        module
+          .statements
+          .iter_mut()
+          .filter(|stmt| stmt.included)
+          .for_each(|stmt| stmt.node.visit_mut_with(&mut rewriter));
+      }
+    });
+  }
+
+  fn collect_exports(
+    &self,
+    modules: &HashMap<SmolStr, Box<Module>>,
+    mark_to_name: &HashMap<Mark, String>,
+  ) -> Vec<ChunkExport> {
+    let mut symbol_box = self.symbol_box.lock().unwrap();
+    let mut exports = self
+      .order_modules
+      .iter()
+      .filter(|id| self.entries.contains(*id))
+      .filter_map(|id| modules.get(id))
+      .flat_map(|module| {
+        module
+          .exports
+          .iter()
+          // `*` is the namespace of the module, which is not an export.
+          .filter(|(exported, _)| *exported != "*")
+          // Re-exports of external modules are rendered along with their dependency.
+          .filter(|(exported, _)| {
+            !module
+              .re_exports
+              .get(*exported)
+              .map_or(false, |desc| module.is_external(&desc.source))
+          })
+          .map(|(exported, mark)| {
+            let mark = symbol_box.find_root(*mark);
+            ChunkExport {
+              exported: exported.clone(),
+              local: mark_to_name
+                .get(&mark)
+                .cloned()
+                .unwrap_or_else(|| exported.to_string()),
+              mark,
+            }
+          })
+          .collect::<Vec<_>>()
+      })
+      .collect::<Vec<_>>();
+    // Symbols used by other chunks may be exported by the entry module already.
+    let exported_names = exports
+      .iter()
+      .map(|export| export.exported.clone())
+      .collect::<HashSet<_>>();
+    let exports_for_chunks = self
+      .exports_for_chunks
+      .iter()
+      .filter(|(_, exported)| !exported_names.contains(*exported))
+      .map(|(mark, exported)| {
+        let mark = symbol_box.find_root(*mark);
+        ChunkExport {
+          exported: exported.clone(),
+          local: mark_to_name
+            .get(&mark)
+            .cloned()
+            .unwrap_or_else(|| exported.to_string()),
+          mark,
+        }
+      })
+      .collect::<Vec<_>>();
+    exports.extend(exports_for_chunks);
+    exports.sort_by(|a, b| a.exported.cmp(&b.exported));
+    exports
   }
 
   pub fn render(
     &mut self,
     options: &NormalizedOutputOptions,
     modules: &mut HashMap<SmolStr, Box<Module>>,
-  ) -> RenderedChunk {
+    plugin_driver: &PluginDriver,
+  ) -> Result<RenderedChunk, RolldownError> {
     assert!(!self.id.is_empty());
-    modules.par_iter_mut().for_each(|(_key, module)| {
-      module.trim_exports();
-      if module.is_user_defined_entry_point {
-        module.generate_exports();
-      }
-    });
 
-    self.de_conflict(modules);
+    // Chunks are imported before external modules.
+    let mut dependencies = self.chunk_dependencies.clone();
+    dependencies.extend(self.collect_dependencies(modules)?);
+    let mark_to_name = self.de_conflict(modules, &mut dependencies, options.format);
+    let exports = self.collect_exports(modules, &mark_to_name);
+    self.rewrite_dynamic_imports(modules, &mark_to_name, options);
+    if options.format == InternalModuleFormat::System {
+      self.export_reassignments(modules, &exports);
+    }
 
-    let common_prefix = lcp_of_array(&self.order_modules);
-    let common_prefix_len = if let Ok(p) = std::env::current_dir().map(|p| p.display().to_string())
-    {
-      lcp(&p, &common_prefix).len()
+    // Facade chunks have no modules.
+    let common_prefix_len = if self.order_modules.is_empty() {
+      0
     } else {
-      common_prefix.len()
+      let common_prefix = lcp_of_array(&self.order_modules);
+      if let Ok(p) = std::env::current_dir().map(|p| p.display().to_string()) {
+        lcp(&p, &common_prefix).len()
+      } else {
+        common_prefix.len()
+      }
     };
     let mut output = Vec::new();
     let comments = SingleThreadedComments::default();
@@ -127,9 +463,10 @@ impl Chunk {
       }
     });
 
+    let mut src_map_buf = vec![];
     let mut emitter = swc_ecma_codegen::Emitter {
       cfg: swc_ecma_codegen::Config {
-        minify: options.minify,
+        minify: options.minify.is_some(),
       },
       cm: SOURCE_MAP.clone(),
       comments: Some(&comments),
@@ -137,20 +474,154 @@ impl Chunk {
         SOURCE_MAP.clone(),
         "\n",
         &mut output,
-        None,
+        if options.sourcemap.is_enabled() {
+          Some(&mut src_map_buf)
+        } else {
+          None
+        },
         EsVersion::latest(),
       )),
     };
 
-    self.order_modules.iter().for_each(|idx| {
-      if let Some(module) = modules.get(idx) {
-        module.render(&mut emitter);
-      }
-    });
+    if let Some(minify_options) = &options.minify {
+      // Modules are minified as a whole, so names could be mangled across modules.
+      let body = self
+        .order_modules
+        .iter()
+        .filter_map(|id| modules.get(id))
+        .flat_map(|module| {
+          module
+            .statements
+            .iter()
+            .filter(|stmt| stmt.included)
+            .map(|stmt| stmt.node.clone())
+        })
+        .collect();
+      let reserved = exports
+        .iter()
+        .map(|export| export.local.as_str().into())
+        .collect();
+      let program = minify(
+        ast::Module {
+          span: DUMMY_SP,
+          body,
+          shebang: None,
+        },
+        minify_options,
+        reserved,
+      );
+      emitter.emit_module(&program).unwrap();
+    } else {
+      self.order_modules.iter().for_each(|idx| {
+        if let Some(module) = modules.get(idx) {
+          module.render(&mut emitter);
+        }
+      });
+    }
 
-    RenderedChunk {
-      code: String::from_utf8(output).unwrap(),
+    let entry_id = self
+      .entries
+      .iter()
+      .next()
+      .map_or_else(|| self.id.to_string(), |id| id.to_string());
+
+    let body = String::from_utf8(output).unwrap();
+    let chunk_info = self.get_chunk_info(modules, &dependencies, &exports);
+    let render_addon = |addon: &Option<Addon>| {
+      addon
+        .as_ref()
+        .map(|addon| addon.render(&chunk_info))
+        .filter(|content| !content.is_empty())
+    };
+    // `intro` and `outro` are placed inside the wrapper of the format, while `banner` and `footer` are outside.
+    // The body is rendered as a placeholder first, so the lines before it are known for the source map.
+    let mut wrapped_body = BODY_PLACEHOLDER.to_string();
+    if let Some(intro) = render_addon(&options.intro) {
+      wrapped_body = format!("{}\n\n{}", intro, wrapped_body);
+    }
+    if let Some(outro) = render_addon(&options.outro) {
+      wrapped_body = format!("{}\n{}\n", wrapped_body, outro);
+    }
+    let mut code = finalize(
+      wrapped_body,
+      &FinalizerArgs {
+        id: &entry_id,
+        file_name: &self.id,
+        dependencies: &dependencies,
+        exports: &exports,
+      },
+      options,
+    )?;
+    // Finalizers place the body verbatim at the start of a line.
+    let body_start = code
+      .find(BODY_PLACEHOLDER)
+      .expect("finalizers render the body");
+    let mut line_offset = code[..body_start].matches('\n').count();
+    code.replace_range(body_start..body_start + BODY_PLACEHOLDER.len(), &body);
+    if let Some(banner) = render_addon(&options.banner) {
+      line_offset += banner.matches('\n').count() + 1;
+      code = format!("{}\n{}", banner, code);
+    }
+    if let Some(footer) = render_addon(&options.footer) {
+      code = format!("{}{}\n", code, footer);
+    }
+
+    let map = if options.sourcemap.is_enabled() {
+      // Only lines need to be shifted, as the body starts at the start of a line.
+      src_map_buf
+        .iter_mut()
+        .for_each(|(_, line_col)| line_col.line += line_offset as u32);
+      let file = get_output_file_path(&self.id, options);
+      // The `sourceMappingURL` comment is added once hashes in file names are resolved.
+      Some(build_source_map(&mut src_map_buf, &file, options, |id| {
+        modules.get(id).and_then(|module| module.input_map.as_ref())
+      }))
+    } else {
+      None
+    };
+
+    let SourceDescription { code, map } =
+      plugin_driver.render_chunk(SourceDescription { code, map }, &chunk_info, options)?;
+    let hash_augmentation = plugin_driver.augment_chunk_hash(&chunk_info);
+
+    Ok(RenderedChunk {
+      code,
       file_name: self.id.clone().into(),
+      map,
+      hash_augmentation,
+      is_entry: chunk_info.is_entry,
+      facade_module_id: chunk_info.facade_module_id,
+      module_ids: chunk_info.module_ids,
+    })
+  }
+
+  fn get_chunk_info(
+    &self,
+    modules: &HashMap<SmolStr, Box<Module>>,
+    dependencies: &[ChunkDependency],
+    exports: &[ChunkExport],
+  ) -> ChunkInfo {
+    let (user_entries, dynamic_entries): (Vec<_>, Vec<_>) = self
+      .entries
+      .iter()
+      .map(|id| id.key().clone())
+      .partition(|id| {
+        modules
+          .get(id)
+          .map_or(false, |m| m.is_user_defined_entry_point)
+      });
+    ChunkInfo {
+      file_name: self.id.to_string(),
+      name: self.get_chunk_name(),
+      is_entry: !user_entries.is_empty(),
+      is_dynamic_entry: !dynamic_entries.is_empty(),
+      facade_module_id: user_entries
+        .first()
+        .or_else(|| dynamic_entries.first())
+        .map(|id| id.to_string()),
+      module_ids: self.order_modules.iter().map(|id| id.to_string()).collect(),
+      exports: exports.iter().map(|e| e.exported.to_string()).collect(),
+      imports: dependencies.iter().map(|dep| dep.id.to_string()).collect(),
     }
   }
 
@@ -158,6 +629,10 @@ impl Chunk {
     OutputChunk {
       code: "".to_string(),
       file_name: self.id.clone().into(),
+      map: None,
+      is_entry: false,
+      facade_module_id: None,
+      module_ids: self.order_modules.iter().map(|id| id.to_string()).collect(),
     }
   }
 
@@ -167,13 +642,36 @@ impl Chunk {
   }
 
   #[inline]
-  pub fn get_chunk_name(&self) -> &str {
-    self.get_fallback_chunk_name()
+  pub fn get_chunk_name(&self) -> String {
+    if let Some(name) = &self.name {
+      return name.clone();
+    }
+    self.entries.iter().next().map_or_else(
+      || self.get_fallback_chunk_name().to_string(),
+      |id| get_alias_name(&id).to_string(),
+    )
   }
 
-  pub fn generate_id(&self, options: &NormalizedOutputOptions) -> SmolStr {
-    let pattern = &options.entry_file_names;
-    pattern.replace("[name]", self.get_chunk_name()).into()
+  // `[hash]` is rendered as a placeholder, which is replaced by the content hash after rendering.
+  pub fn generate_id(
+    &self,
+    index: usize,
+    is_entry: bool,
+    options: &NormalizedOutputOptions,
+  ) -> Result<String, RolldownError> {
+    let (pattern, pattern_name) = if is_entry {
+      (&options.entry_file_names, "entryFileNames")
+    } else {
+      (&options.chunk_file_names, "chunkFileNames")
+    };
+    render_file_name_pattern(pattern, pattern_name, |placeholder, size| {
+      Ok(match placeholder {
+        "name" => Some(self.get_chunk_name()),
+        "format" => Some(options.format.as_str().to_string()),
+        "hash" => Some(hash_placeholder(index, size.unwrap_or(DEFAULT_HASH_SIZE))?),
+        _ => None,
+      })
+    })
   }
 }
 
@@ -185,3 +683,189 @@ fn get_alias_name(id: &str) -> &str {
   let file_name = p.file_name().unwrap().to_str().unwrap();
   &file_name[0..file_name.len() - ext_len]
 }
+
+enum DynamicImportTarget {
+  // Relative path of the chunk
+  Chunk(String),
+  // Name of the namespace object of the inlined module
+  Namespace(String),
+}
+
+struct DynamicImportRewriter<'me> {
+  sources: &'me HashMap<JsWord, DynamicImportTarget>,
+  format: InternalModuleFormat,
+}
+
+impl<'me> VisitMut for DynamicImportRewriter<'me> {
+  noop_visit_mut_type!();
+
+  fn visit_mut_expr(&mut self, node: &mut Expr) {
+    node.visit_mut_children_with(self);
+    if let Expr::Call(call) = node {
+      if !matches!(call.callee, Callee::Import(_)) {
+        return;
+      }
+      // Only `import('./foo')` with a string literal could be resolved.
+      let source = match call.args.first().map(|arg| arg.expr.as_ref()) {
+        Some(Expr::Lit(Lit::Str(source))) => source,
+        _ => return,
+      };
+      let source = match self.sources.get(&source.value) {
+        Some(DynamicImportTarget::Chunk(path)) => path.clone(),
+        Some(DynamicImportTarget::Namespace(name)) => {
+          *node = *ast_sugar::resolved_promise(ast_sugar::expr_ident(name));
+          return;
+        }
+        // External modules keep their specifiers.
+        None => source.value.to_string(),
+      };
+      match ast_sugar::dynamic_import(self.format, &source) {
+        Some(expr) => *node = *expr,
+        None => call.args[0].expr = Box::new(Expr::Lit(Lit::Str(ast_sugar::str(&source)))),
+      }
+    }
+  }
+}
+
+// `x = 1` becomes `exports('x', x = 1)`, which returns the assigned value.
+struct LiveExportRewriter<'me> {
+  // Exported names by the root mark of the binding.
+  exported_names: &'me HashMap<Mark, Vec<JsWord>>,
+  symbol_box: &'me mut SymbolBox,
+}
+
+impl<'me> LiveExportRewriter<'me> {
+  fn exported_names_of(&mut self, ident: &Ident) -> &'me [JsWord] {
+    let mark = self.symbol_box.find_root(ident.span.ctxt.as_mark());
+    let exported_names = self.exported_names;
+    exported_names
+      .get(&mark)
+      .map(Vec::as_slice)
+      .unwrap_or_default()
+  }
+
+  fn export(names: &[JsWord], value: Box<Expr>) -> Box<Expr> {
+    names
+      .iter()
+      .fold(value, |value, name| ast_sugar::system_export(name, value))
+  }
+}
+
+impl<'me> VisitMut for LiveExportRewriter<'me> {
+  noop_visit_mut_type!();
+
+  fn visit_mut_stmt(&mut self, node: &mut Stmt) {
+    node.visit_mut_children_with(self);
+    // The value is unused, so destructuring could be exported afterwards.
+    if let Stmt::Expr(ExprStmt { expr, .. }) = node {
+      if let Expr::Assign(AssignExpr {
+        left: PatOrExpr::Pat(pat),
+        ..
+      }) = &**expr
+      {
+        let mut idents = PatIdents::default();
+        pat.visit_with(&mut idents);
+        let mut exports = vec![];
+        for ident in idents.0 {
+          for name in self.exported_names_of(&ident) {
+            let value = Box::new(Expr::Ident(ident.clone()));
+            exports.push(ast_sugar::system_export(name, value));
+          }
+        }
+        if !exports.is_empty() {
+          let mut exprs = vec![Box::new((**expr).take())];
+          exprs.extend(exports);
+          *expr = Box::new(Expr::Seq(SeqExpr {
+            span: DUMMY_SP,
+            exprs,
+          }));
+        }
+      }
+    }
+  }
+
+  fn visit_mut_expr(&mut self, node: &mut Expr) {
+    node.visit_mut_children_with(self);
+    match node {
+      Expr::Assign(assign) => {
+        let ident = match &assign.left {
+          PatOrExpr::Pat(pat) => match &**pat {
+            Pat::Ident(binding) => &binding.id,
+            _ => return,
+          },
+          PatOrExpr::Expr(expr) => match &**expr {
+            Expr::Ident(ident) => ident,
+            _ => return,
+          },
+        };
+        let names = self.exported_names_of(ident);
+        if !names.is_empty() {
+          *node = *Self::export(names, Box::new(node.take()));
+        }
+      }
+      Expr::Update(update) => {
+        let ident = match &*update.arg {
+          Expr::Ident(ident) => ident.clone(),
+          _ => return,
+        };
+        let names = self.exported_names_of(&ident);
+        if names.is_empty() {
+          return;
+        }
+        if update.prefix {
+          *node = *Self::export(names, Box::new(node.take()));
+        } else {
+          // `x++` becomes `(exports('x', +x + 1), x++)`, so its value is still the previous one.
+          let next = Box::new(Expr::Bin(BinExpr {
+            span: DUMMY_SP,
+            op: match update.op {
+              UpdateOp::PlusPlus => BinaryOp::Add,
+              UpdateOp::MinusMinus => BinaryOp::Sub,
+            },
+            left: Box::new(Expr::Unary(UnaryExpr {
+              span: DUMMY_SP,
+              op: UnaryOp::Plus,
+              arg: Box::new(Expr::Ident(ident)),
+            })),
+            right: Box::new(Expr::Lit(Lit::Num(Number {
+              span: DUMMY_SP,
+              value: 1.0,
+            }))),
+          }));
+          let mut exprs = names
+            .iter()
+            .map(|name| ast_sugar::system_export(name, next.clone()))
+            .collect::<Vec<_>>();
+          exprs.push(Box::new(node.take()));
+          *node = Expr::Paren(ParenExpr {
+            span: DUMMY_SP,
+            expr: Box::new(Expr::Seq(SeqExpr {
+              span: DUMMY_SP,
+              exprs,
+            })),
+          });
+        }
+      }
+      _ => {}
+    }
+  }
+}
+
+// Bindings assigned by a destructuring pattern.
+#[derive(Default)]
+struct PatIdents(Vec<Ident>);
+
+impl Visit for PatIdents {
+  noop_visit_type!();
+
+  fn visit_binding_ident(&mut self, node: &BindingIdent) {
+    self.0.push(node.id.clone());
+  }
+
+  fn visit_assign_pat_prop(&mut self, node: &AssignPatProp) {
+    self.0.push(node.key.clone());
+  }
+
+  // Default values and computed keys are not assigned.
+  fn visit_expr(&mut self, _: &Expr) {}
+}
@@ -0,0 +1,622 @@
+use std::{
+  collections::{HashMap, HashSet},
+  fs,
+  path::PathBuf,
+  sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use smol_str::SmolStr;
+use swc_atoms::JsWord;
+use swc_common::{BytePos, FileName, Mark, Span, SyntaxContext};
+use swc_ecma_ast::ModuleItem;
+use swc_ecma_visit::{Visit, VisitMut, VisitMutWith, VisitWith};
+
+use crate::{
+  compiler::SOURCE_MAP,
+  ext::{MarkExt, SyntaxContextExt},
+  graph::{CachedModule, Rel},
+  module::Module,
+  scanner::rel::{ExportDesc, ReExportDesc, RelationInfo, Specifier},
+  statement::Statement,
+  symbol_box::SymbolBox,
+  types::{NormalizedInputOptions, ResolvedId},
+  utils::{file_name::content_hash, side_effect::SideEffect},
+};
+
+// Bump it when the format of entries changes.
+const FORMAT_VERSION: u32 = 1;
+
+/// Modules stored in a directory across processes. Entries are keyed by the id and the code of the
+/// module, the version of Rolldown, the options affecting modules and the names and versions of plugins.
+/// Marks are process-local, so they are stored as indexes into the marks of the entry, and replaced by
+/// new marks when the entry is read.
+pub struct DiskCache {
+  dir: PathBuf,
+  // Part of the key shared by every module of the build.
+  build_key: String,
+}
+
+impl DiskCache {
+  pub fn new(dir: impl Into<PathBuf>, options: &NormalizedInputOptions) -> Self {
+    // `external` is a function, so it's not part of the key. Ids resolved by an entry are resolved again instead.
+    let plugins = options
+      .plugins
+      .iter()
+      .map(|plugin| {
+        format!(
+          "{}@{}",
+          plugin.get_name(),
+          plugin.get_version().unwrap_or("")
+        )
+      })
+      .collect::<Vec<_>>();
+    Self {
+      dir: dir.into(),
+      build_key: format!(
+        "{}\0{}\0treeshake={}\0{}",
+        env!("CARGO_PKG_VERSION"),
+        FORMAT_VERSION,
+        options.treeshake,
+        plugins.join(",")
+      ),
+    }
+  }
+
+  pub fn key(&self, id: &str, code: &str) -> String {
+    content_hash(format!("{}\0{}\0{}", self.build_key, id, code).as_bytes())
+  }
+
+  fn entry_path(&self, id: &str) -> PathBuf {
+    self
+      .dir
+      .join(format!("{}.json", &content_hash(id.as_bytes())[..16]))
+  }
+
+  pub fn get(&self, id: &str, key: &str) -> Option<CacheEntry> {
+    let content = fs::read(self.entry_path(id)).ok()?;
+    // A corrupted entry is a miss, like an outdated one.
+    serde_json::from_slice::<CacheEntry>(&content)
+      .ok()
+      .filter(|entry| entry.key == key && entry.is_valid())
+  }
+
+  // The cache is an optimization, so failing to write it is not an error of the build.
+  pub fn set(&self, key: &str, cached: &CachedModule, symbol_box: &Arc<Mutex<SymbolBox>>) {
+    let entry = CacheEntry::new(key.to_string(), cached, &mut symbol_box.lock().unwrap());
+    let path = self.entry_path(&cached.module.id);
+    // Write to a temporary file first, so concurrent builds never read a partial entry.
+    let tmp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+    let result = fs::create_dir_all(&self.dir)
+      .and_then(|_| fs::write(&tmp_path, serde_json::to_vec(&entry).unwrap()))
+      .and_then(|_| fs::rename(&tmp_path, &path));
+    if let Err(err) = result {
+      log::warn!(
+        "[disk_cache]: failed to write the entry of {}: {}",
+        cached.module.id,
+        err
+      );
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CacheEntry {
+  key: String,
+  mark_count: u32,
+  // (mark, root) of marks unioned while the module is scanned
+  unions: Vec<(u32, u32)>,
+  // (source, resolved id, external)
+  resolved_ids: Vec<(String, String, bool)>,
+  statements: Vec<StatementEntry>,
+  definitions: Vec<(String, usize)>,
+  local_exports: Vec<(String, ExportEntry)>,
+  re_exports: Vec<(String, ReExportEntry)>,
+  re_export_all_sources: Vec<String>,
+  exports: Vec<(String, u32)>,
+  declared_symbols: Vec<(String, u32)>,
+  imported_symbols: Vec<(String, u32)>,
+  suggested_names: Vec<(String, String)>,
+  namespace: (bool, u32),
+  module_span: (u32, u32),
+  dependencies: Vec<(String, RelEntry)>,
+  marks: Vec<(u32, usize)>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StatementEntry {
+  // Spans are relative to the start of the file, and contexts are indexes of marks.
+  node: ModuleItem,
+  included: bool,
+  declared: Vec<(String, u32)>,
+  reads: Vec<u32>,
+  writes: Vec<u32>,
+  side_effect: Option<SideEffect>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExportEntry {
+  identifier: Option<String>,
+  local_name: String,
+  mark: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReExportEntry {
+  original: String,
+  local_name: String,
+  source: String,
+  mark: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+enum RelEntry {
+  Import(RelationEntry),
+  ReExport(RelationEntry),
+  ReExportAll(usize),
+  DynImport,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RelationEntry {
+  source: String,
+  // (original, used, mark)
+  names: Vec<(String, String, u32)>,
+  order: usize,
+}
+
+// Maps marks to indexes starting from 1. 0 stands for no mark.
+struct MarkTable {
+  indexes: HashMap<Mark, u32>,
+  marks: Vec<Mark>,
+}
+
+impl MarkTable {
+  fn index(&mut self, mark: Mark) -> u32 {
+    let marks = &mut self.marks;
+    *self.indexes.entry(mark).or_insert_with(|| {
+      marks.push(mark);
+      marks.len() as u32
+    })
+  }
+
+  fn word_marks<'a>(
+    &mut self,
+    iter: impl Iterator<Item = (&'a JsWord, &'a Mark)>,
+  ) -> Vec<(String, u32)> {
+    iter
+      .map(|(name, mark)| (name.to_string(), self.index(*mark)))
+      .collect()
+  }
+}
+
+// Whether the contexts of spans are indexes of marks of the entry.
+struct ContextChecker {
+  mark_count: u32,
+  is_valid: bool,
+}
+
+impl Visit for ContextChecker {
+  fn visit_span(&mut self, span: &Span) {
+    self.is_valid &= span.ctxt.as_u32() <= self.mark_count;
+  }
+}
+
+pub(crate) struct SpanMapper<F: FnMut(Span) -> Span>(pub F);
+
+impl<F: FnMut(Span) -> Span> VisitMut for SpanMapper<F> {
+  fn visit_mut_span(&mut self, span: &mut Span) {
+    *span = (self.0)(*span);
+  }
+}
+
+impl CacheEntry {
+  fn new(key: String, cached: &CachedModule, symbol_box: &mut SymbolBox) -> Self {
+    let module = &cached.module;
+    let base = SOURCE_MAP
+      .lookup_byte_offset(module.module_span.lo)
+      .sf
+      .start_pos;
+    let relative = |pos: BytePos| {
+      if pos == BytePos(0) {
+        0
+      } else {
+        pos.0 - base.0 + 1
+      }
+    };
+    let mut table = MarkTable {
+      indexes: Default::default(),
+      marks: Default::default(),
+    };
+
+    let statements = module
+      .statements
+      .iter()
+      .map(|stmt| {
+        let mut node = stmt.node.clone();
+        node.visit_mut_with(&mut SpanMapper(|span: Span| {
+          let ctxt = if span.ctxt == SyntaxContext::empty() {
+            0
+          } else {
+            table.index(span.ctxt.as_mark())
+          };
+          Span::new(
+            BytePos(relative(span.lo)),
+            BytePos(relative(span.hi)),
+            SyntaxContext::from_u32(ctxt),
+          )
+        }));
+        StatementEntry {
+          node,
+          included: stmt.included,
+          declared: table.word_marks(stmt.declared.iter()),
+          reads: stmt.reads.iter().map(|mark| table.index(*mark)).collect(),
+          writes: stmt.writes.iter().map(|mark| table.index(*mark)).collect(),
+          side_effect: stmt.side_effect,
+        }
+      })
+      .collect();
+    let local_exports = module
+      .local_exports
+      .iter()
+      .map(|(name, desc)| {
+        (
+          name.to_string(),
+          ExportEntry {
+            identifier: desc.identifier.as_ref().map(|name| name.to_string()),
+            local_name: desc.local_name.to_string(),
+            mark: table.index(desc.mark),
+          },
+        )
+      })
+      .collect();
+    let re_exports = module
+      .re_exports
+      .iter()
+      .map(|(name, desc)| {
+        (
+          name.to_string(),
+          ReExportEntry {
+            original: desc.original.to_string(),
+            local_name: desc.local_name.to_string(),
+            source: desc.source.to_string(),
+            mark: table.index(desc.mark),
+          },
+        )
+      })
+      .collect();
+    let mut relation = |info: &RelationInfo| RelationEntry {
+      source: info.source.to_string(),
+      names: info
+        .names
+        .iter()
+        .map(|specifier| {
+          (
+            specifier.original.to_string(),
+            specifier.used.to_string(),
+            table.index(specifier.mark),
+          )
+        })
+        .collect(),
+      order: info.order,
+    };
+    let dependencies = cached
+      .dependencies
+      .iter()
+      .map(|(id, rel)| {
+        let rel = match rel {
+          Rel::Import(info) => RelEntry::Import(relation(info)),
+          Rel::ReExport(info) => RelEntry::ReExport(relation(info)),
+          Rel::ReExportAll(order) => RelEntry::ReExportAll(*order),
+          Rel::DynImport => RelEntry::DynImport,
+        };
+        (id.to_string(), rel)
+      })
+      .collect();
+    let exports = table.word_marks(module.exports.iter());
+    let declared_symbols = table.word_marks(module.declared_symbols.iter());
+    let imported_symbols = table.word_marks(module.imported_symbols.iter());
+    let namespace = (
+      module.namespace.included,
+      table.index(module.namespace.mark),
+    );
+    let marks = cached
+      .marks
+      .iter()
+      .map(|(mark, idx)| (table.index(*mark), *idx))
+      .collect();
+
+    // Roots are added to the table while iterating, so they are unioned as well.
+    let mut unions = vec![];
+    let mut cursor = 0;
+    while cursor < table.marks.len() {
+      let mark = table.marks[cursor];
+      let root = symbol_box.find_root(mark);
+      if root != mark {
+        unions.push((cursor as u32 + 1, table.index(root)));
+      }
+      cursor += 1;
+    }
+
+    Self {
+      key,
+      mark_count: table.marks.len() as u32,
+      unions,
+      resolved_ids: module
+        .resolved_ids
+        .iter()
+        .map(|pair| {
+          (
+            pair.key().to_string(),
+            pair.value().id.to_string(),
+            pair.value().external,
+          )
+        })
+        .collect(),
+      statements,
+      definitions: module
+        .definitions
+        .iter()
+        .map(|(name, idx)| (name.to_string(), *idx))
+        .collect(),
+      local_exports,
+      re_exports,
+      re_export_all_sources: module
+        .re_export_all_sources
+        .iter()
+        .map(|source| source.to_string())
+        .collect(),
+      exports,
+      declared_symbols,
+      imported_symbols,
+      suggested_names: module
+        .suggested_names
+        .iter()
+        .map(|(name, suggested)| (name.to_string(), suggested.to_string()))
+        .collect(),
+      namespace,
+      module_span: (
+        relative(module.module_span.lo),
+        relative(module.module_span.hi),
+      ),
+      dependencies,
+      marks,
+    }
+  }
+
+  // Mark indexes are between 1 and `mark_count`, and statement indexes point to statements, so the entry
+  // could be read without panicking.
+  fn is_valid(&self) -> bool {
+    let is_mark = |index: &u32| (1..=self.mark_count).contains(index);
+    let are_marks = |entries: &[(String, u32)]| entries.iter().all(|(_, index)| is_mark(index));
+    let is_relation_valid =
+      |entry: &RelationEntry| entry.names.iter().all(|(_, _, index)| is_mark(index));
+    let is_statement = |idx: &usize| *idx < self.statements.len();
+    let mut checker = ContextChecker {
+      mark_count: self.mark_count,
+      is_valid: true,
+    };
+    self
+      .statements
+      .iter()
+      .for_each(|stmt| stmt.node.visit_with(&mut checker));
+    checker.is_valid
+      && self
+        .unions
+        .iter()
+        .all(|(mark, root)| is_mark(mark) && is_mark(root))
+      && self.statements.iter().all(|stmt| {
+        are_marks(&stmt.declared)
+          && stmt.reads.iter().all(is_mark)
+          && stmt.writes.iter().all(is_mark)
+      })
+      && self.definitions.iter().all(|(_, idx)| is_statement(idx))
+      && self
+        .local_exports
+        .iter()
+        .all(|(_, entry)| is_mark(&entry.mark))
+      && self
+        .re_exports
+        .iter()
+        .all(|(_, entry)| is_mark(&entry.mark))
+      && are_marks(&self.exports)
+      && are_marks(&self.declared_symbols)
+      && are_marks(&self.imported_symbols)
+      && is_mark(&self.namespace.1)
+      && self.dependencies.iter().all(|(_, rel)| match rel {
+        RelEntry::Import(entry) | RelEntry::ReExport(entry) => is_relation_valid(entry),
+        RelEntry::ReExportAll(_) | RelEntry::DynImport => true,
+      })
+      && self
+        .marks
+        .iter()
+        .all(|(index, idx)| is_mark(index) && is_statement(idx))
+  }
+
+  /// (source, resolved id, whether the source is only imported by `import()`). Sources of `import()` are resolved
+  /// by the `resolve_dynamic_import` hook first.
+  pub fn resolved_ids(&self) -> impl Iterator<Item = (String, ResolvedId, bool)> + '_ {
+    let static_sources = self
+      .dependencies
+      .iter()
+      .filter_map(|(_, rel)| match rel {
+        RelEntry::Import(entry) | RelEntry::ReExport(entry) => Some(entry.source.as_str()),
+        _ => None,
+      })
+      .chain(
+        self
+          .re_export_all_sources
+          .iter()
+          .map(|source| source.as_str()),
+      )
+      .collect::<HashSet<_>>();
+    self.resolved_ids.iter().map(move |(source, id, external)| {
+      (
+        source.clone(),
+        ResolvedId::new(SmolStr::new(id), *external),
+        !static_sources.contains(source.as_str()),
+      )
+    })
+  }
+
+  /// Build the module with new marks. The code is added to the source map, so spans of the module point
+  /// to it as if it's parsed.
+  pub fn into_cached_module(
+    self,
+    id: &str,
+    code: String,
+    symbol_box: &Arc<Mutex<SymbolBox>>,
+  ) -> CachedModule {
+    let base = SOURCE_MAP
+      .new_source_file(FileName::Custom(id.to_owned()), code)
+      .start_pos;
+    let absolute = |pos: u32| {
+      if pos == 0 {
+        BytePos(0)
+      } else {
+        BytePos(base.0 + pos - 1)
+      }
+    };
+    let fresh_marks = {
+      let mut symbol_box = symbol_box.lock().unwrap();
+      let marks = (0..self.mark_count)
+        .map(|_| symbol_box.new_mark())
+        .collect::<Vec<_>>();
+      self.unions.iter().for_each(|(mark, root)| {
+        symbol_box.union(marks[*mark as usize - 1], marks[*root as usize - 1]);
+      });
+      marks
+    };
+    let mark = |index: u32| fresh_marks[index as usize - 1];
+    let word_marks = |entries: Vec<(String, u32)>| {
+      entries
+        .into_iter()
+        .map(|(name, index)| (JsWord::from(name), mark(index)))
+        .collect::<HashMap<_, _>>()
+    };
+
+    let mut module = Module::new(id.into());
+    self
+      .resolved_ids
+      .into_iter()
+      .for_each(|(source, id, external)| {
+        module
+          .resolved_ids
+          .insert(source.into(), ResolvedId::new(id.into(), external));
+      });
+    module.statements = self
+      .statements
+      .into_iter()
+      .map(|entry| {
+        let mut node = entry.node;
+        node.visit_mut_with(&mut SpanMapper(|span: Span| {
+          let ctxt = match span.ctxt.as_u32() {
+            0 => SyntaxContext::empty(),
+            index => mark(index).as_ctxt(),
+          };
+          Span::new(absolute(span.lo.0), absolute(span.hi.0), ctxt)
+        }));
+        let mut stmt = Statement::new(node);
+        stmt.included = entry.included;
+        stmt.declared = word_marks(entry.declared);
+        stmt.reads = entry.reads.into_iter().map(mark).collect();
+        stmt.writes = entry.writes.into_iter().map(mark).collect();
+        stmt.side_effect = entry.side_effect;
+        stmt
+      })
+      .collect();
+    module.definitions = self
+      .definitions
+      .into_iter()
+      .map(|(name, idx)| (name.into(), idx))
+      .collect();
+    module.local_exports = self
+      .local_exports
+      .into_iter()
+      .map(|(name, entry)| {
+        (
+          name.into(),
+          ExportDesc {
+            identifier: entry.identifier.map(JsWord::from),
+            local_name: entry.local_name.into(),
+            mark: mark(entry.mark),
+          },
+        )
+      })
+      .collect();
+    module.re_exports = self
+      .re_exports
+      .into_iter()
+      .map(|(name, entry)| {
+        (
+          name.into(),
+          ReExportDesc {
+            original: entry.original.into(),
+            local_name: entry.local_name.into(),
+            source: entry.source.into(),
+            mark: mark(entry.mark),
+          },
+        )
+      })
+      .collect();
+    module.re_export_all_sources = self
+      .re_export_all_sources
+      .into_iter()
+      .map(JsWord::from)
+      .collect();
+    module.exports = word_marks(self.exports);
+    module.declared_symbols = word_marks(self.declared_symbols);
+    module.imported_symbols = word_marks(self.imported_symbols);
+    module.suggested_names = self
+      .suggested_names
+      .into_iter()
+      .map(|(name, suggested)| (name.into(), suggested.into()))
+      .collect();
+    module.namespace.included = self.namespace.0;
+    module.namespace.mark = mark(self.namespace.1);
+    module.module_span = Span::new(
+      absolute(self.module_span.0),
+      absolute(self.module_span.1),
+      SyntaxContext::empty(),
+    );
+
+    let relation = |entry: RelationEntry| RelationInfo {
+      source: entry.source.into(),
+      names: entry
+        .names
+        .into_iter()
+        .map(|(original, used, index)| Specifier {
+          original: original.into(),
+          used: used.into(),
+          mark: mark(index),
+        })
+        .collect(),
+      order: entry.order,
+    };
+    let dependencies = self
+      .dependencies
+      .into_iter()
+      .map(|(id, rel)| {
+        let rel = match rel {
+          RelEntry::Import(entry) => Rel::Import(relation(entry)),
+          RelEntry::ReExport(entry) => Rel::ReExport(relation(entry)),
+          RelEntry::ReExportAll(order) => Rel::ReExportAll(order),
+          RelEntry::DynImport => Rel::DynImport,
+        };
+        (SmolStr::new(id), rel)
+      })
+      .collect();
+    let marks = self
+      .marks
+      .into_iter()
+      .map(|(index, idx)| (mark(index), idx))
+      .collect();
+
+    CachedModule {
+      module,
+      dependencies,
+      marks,
+      unions: Default::default(),
+    }
+  }
+}
@@ -0,0 +1,78 @@
+use super::{
+  quote,
+  shared::{render_es_module_marker, render_export_block, render_interop_block},
+  FinalizerArgs,
+};
+use crate::{
+  types::{ExportMode, NormalizedOutputOptions},
+  worker::RolldownError,
+};
+
+pub fn finalize(
+  body: String,
+  args: &FinalizerArgs,
+  export_mode: ExportMode,
+  options: &NormalizedOutputOptions,
+) -> Result<String, RolldownError> {
+  let amd_id = match (&options.amd.id, options.amd.auto_id) {
+    (Some(_), true) => {
+      return Err(RolldownError::InvalidOption(
+        "\"output.amd.autoId\" and \"output.amd.id\" cannot be used together.".to_string(),
+      ))
+    }
+    (Some(id), false) => Some(id.clone()),
+    (None, true) => Some(
+      args
+        .file_name
+        .strip_suffix(".js")
+        .unwrap_or(args.file_name)
+        .to_string(),
+    ),
+    (None, false) => None,
+  };
+
+  let mut deps = vec![];
+  let mut params = vec![];
+  if export_mode == ExportMode::Named {
+    deps.push(quote("exports"));
+    params.push("exports".to_string());
+  }
+  args.dependencies.iter().for_each(|dep| {
+    deps.push(quote(&dep.id));
+    params.push(dep.name.clone());
+  });
+
+  let mut define_args = vec![];
+  if let Some(amd_id) = amd_id {
+    define_args.push(quote(&amd_id));
+  }
+  if !deps.is_empty() {
+    define_args.push(format!("[{}]", deps.join(", ")));
+  }
+
+  let mut blocks = vec![];
+  if let Some(marker) = render_es_module_marker(args, export_mode, options) {
+    blocks.push(marker.to_string());
+  }
+  let interop_block = render_interop_block(args.dependencies);
+  if !interop_block.is_empty() {
+    blocks.push(interop_block);
+  }
+  blocks.push(body);
+  let export_block = render_export_block(args, export_mode, "return ");
+  if !export_block.is_empty() {
+    blocks.push(export_block);
+  }
+
+  define_args.push(format!(
+    "(function ({}) {{ 'use strict';\n\n{}\n\n}})",
+    params.join(", "),
+    blocks.join("\n\n")
+  ));
+
+  Ok(format!(
+    "{}({});\n",
+    options.amd.define,
+    define_args.join(", ")
+  ))
+}
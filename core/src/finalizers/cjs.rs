@@ -0,0 +1,50 @@
+use super::{
+  quote,
+  shared::{render_es_module_marker, render_export_block, render_interop_block},
+  FinalizerArgs,
+};
+use crate::types::{ExportMode, NormalizedOutputOptions};
+
+pub fn finalize(
+  body: String,
+  args: &FinalizerArgs,
+  export_mode: ExportMode,
+  options: &NormalizedOutputOptions,
+) -> String {
+  let mut blocks = vec!["'use strict';".to_string()];
+
+  if let Some(marker) = render_es_module_marker(args, export_mode, options) {
+    blocks.push(marker.to_string());
+  }
+
+  let requires = args
+    .dependencies
+    .iter()
+    .map(|dep| {
+      if dep.is_side_effect_only() {
+        format!("require({});", quote(&dep.id))
+      } else {
+        format!("var {} = require({});", dep.name, quote(&dep.id))
+      }
+    })
+    .collect::<Vec<_>>();
+  if !requires.is_empty() {
+    blocks.push(requires.join("\n"));
+  }
+
+  let interop_block = render_interop_block(args.dependencies);
+  if !interop_block.is_empty() {
+    blocks.push(interop_block);
+  }
+
+  blocks.push(body);
+
+  let export_block = render_export_block(args, export_mode, "module.exports = ");
+  if !export_block.is_empty() {
+    blocks.push(export_block);
+  }
+
+  let mut output = blocks.join("\n\n");
+  output.push('\n');
+  output
+}
@@ -0,0 +1,116 @@
+use super::{quote, ChunkDependency, ChunkExport, FinalizerArgs};
+
+pub fn finalize(body: String, args: &FinalizerArgs) -> String {
+  let mut output = String::with_capacity(body.len());
+
+  let import_block = args
+    .dependencies
+    .iter()
+    .flat_map(render_import_statements)
+    .collect::<Vec<_>>();
+  if !import_block.is_empty() {
+    output.push_str(&import_block.join("\n"));
+    output.push_str("\n\n");
+  }
+
+  output.push_str(&body);
+
+  let export_block = args
+    .dependencies
+    .iter()
+    .flat_map(render_reexport_statements)
+    .chain(render_export_statement(args.exports))
+    .collect::<Vec<_>>();
+  if !export_block.is_empty() {
+    output.push('\n');
+    output.push_str(&export_block.join("\n"));
+    output.push('\n');
+  }
+
+  output
+}
+
+fn render_import_statements(dep: &ChunkDependency) -> Vec<String> {
+  let source = quote(&dep.id);
+  if dep.is_side_effect_only() {
+    return vec![format!("import {};", source)];
+  }
+
+  let mut default_local = None;
+  let mut namespace_local = None;
+  let mut named = vec![];
+  dep
+    .imports
+    .iter()
+    .for_each(|binding| match &*binding.imported {
+      "default" => default_local = Some(binding.local.as_str()),
+      "*" => namespace_local = Some(binding.local.as_str()),
+      imported => named.push(render_specifier(imported, &binding.local)),
+    });
+
+  let mut statements = vec![];
+  if let Some(namespace_local) = namespace_local {
+    let default_part = default_local
+      .take()
+      .map(|local| format!("{}, ", local))
+      .unwrap_or_default();
+    statements.push(format!(
+      "import {}* as {} from {};",
+      default_part, namespace_local, source
+    ));
+  }
+  if default_local.is_some() || !named.is_empty() {
+    let mut parts = vec![];
+    if let Some(default_local) = default_local {
+      parts.push(default_local.to_string());
+    }
+    if !named.is_empty() {
+      parts.push(format!("{{ {} }}", named.join(", ")));
+    }
+    statements.push(format!("import {} from {};", parts.join(", "), source));
+  }
+  statements
+}
+
+fn render_reexport_statements(dep: &ChunkDependency) -> Vec<String> {
+  let source = quote(&dep.id);
+  let mut statements = vec![];
+  let mut named = vec![];
+  dep
+    .reexports
+    .iter()
+    .for_each(|(imported, exported)| match (&**imported, &**exported) {
+      ("*", "*") => statements.push(format!("export * from {};", source)),
+      ("*", exported) => statements.push(format!("export * as {} from {};", exported, source)),
+      (imported, exported) => named.push(render_specifier(imported, exported)),
+    });
+  if !named.is_empty() {
+    statements.push(format!(
+      "export {{ {} }} from {};",
+      named.join(", "),
+      source
+    ));
+  }
+  statements
+}
+
+fn render_export_statement(exports: &[ChunkExport]) -> Option<String> {
+  if exports.is_empty() {
+    None
+  } else {
+    let specifiers = exports
+      .iter()
+      .map(|export| render_specifier(&export.local, &export.exported))
+      .collect::<Vec<_>>();
+    Some(format!("export {{ {} }};", specifiers.join(", ")))
+  }
+}
+
+#[inline]
+fn render_specifier(original: &str, alias: &str) -> String {
+  if original == alias {
+    original.to_string()
+  } else {
+    format!("{} as {}", original, alias)
+  }
+}
@@ -0,0 +1,87 @@
+use super::{
+  shared::{
+    get_global_name, namespace_path, render_es_module_marker, render_export_block,
+    render_interop_block, setup_namespace, validate_name,
+  },
+  FinalizerArgs,
+};
+use crate::{
+  types::{ExportMode, NormalizedOutputOptions},
+  worker::RolldownError,
+};
+
+pub fn finalize(
+  body: String,
+  args: &FinalizerArgs,
+  export_mode: ExportMode,
+  options: &NormalizedOutputOptions,
+) -> Result<String, RolldownError> {
+  let is_named_export_mode = export_mode == ExportMode::Named;
+  let name = options
+    .name
+    .as_ref()
+    .filter(|_| export_mode != ExportMode::None);
+  if name.is_none() && export_mode != ExportMode::None {
+    log::warn!("If you do not supply \"output.name\", you may not be able to access the exports of an IIFE bundle.");
+  }
+  if let Some(name) = name {
+    validate_name(name)?;
+  }
+
+  let mut params = vec![];
+  let mut factory_args = vec![];
+  if is_named_export_mode {
+    params.push("exports".to_string());
+    factory_args.push(match name {
+      Some(name) if options.extend => {
+        format!(
+          "{path} = {path} || {{}}",
+          path = namespace_path("this", name)
+        )
+      }
+      _ => "{}".to_string(),
+    });
+  }
+  args.dependencies.iter().for_each(|dep| {
+    params.push(dep.name.clone());
+    factory_args.push(get_global_name(dep, options));
+  });
+
+  let mut blocks = vec!["'use strict';".to_string()];
+  if let Some(marker) = render_es_module_marker(args, export_mode, options) {
+    blocks.push(marker.to_string());
+  }
+  let interop_block = render_interop_block(args.dependencies);
+  if !interop_block.is_empty() {
+    blocks.push(interop_block);
+  }
+  blocks.push(body);
+  let export_block = render_export_block(args, export_mode, "return ");
+  if !export_block.is_empty() {
+    blocks.push(export_block);
+  }
+  if is_named_export_mode && !options.extend {
+    blocks.push("return exports;".to_string());
+  }
+
+  let wrapper = format!(
+    "(function ({}) {{\n{}\n\n}})({})",
+    params.join(", "),
+    blocks.join("\n\n"),
+    factory_args.join(", ")
+  );
+
+  // Namespaces of a dotted name are created first, like `this.a = this.a || {};` for `a.b`.
+  let mut output = name.map_or_else(Default::default, |name| setup_namespace("this", name));
+  match name {
+    // The exports object is assigned in the arguments.
+    Some(_) if is_named_export_mode && options.extend => output.push_str(&wrapper),
+    Some(name) if name.contains('.') => {
+      output.push_str(&format!("{} = {}", namespace_path("this", name), wrapper))
+    }
+    Some(name) => output.push_str(&format!("var {} = {}", name, wrapper)),
+    None => output.push_str(&wrapper),
+  }
+  output.push_str(";\n");
+  Ok(output)
+}
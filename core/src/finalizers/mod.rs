@@ -0,0 +1,160 @@
+use smol_str::SmolStr;
+use swc_atoms::JsWord;
+use swc_common::Mark;
+
+use crate::{
+  types::{ExportMode, InternalModuleFormat, NormalizedOutputOptions},
+  worker::RolldownError,
+};
+
+mod amd;
+mod cjs;
+mod es;
+mod iife;
+mod shared;
+mod system;
+mod umd;
+
+// Align to https://github.com/rollup/rollup/tree/master/src/finalisers
+
+#[derive(Debug, Clone)]
+pub struct ImportBinding {
+  // `default` for `import foo from 'foo'` and `*` for `import * as foo from 'foo'`
+  pub imported: JsWord,
+  pub mark: Mark,
+  // The name used inside the chunk. It's decided in `Chunk::de_conflict`.
+  pub local: String,
+}
+
+/// A module that the chunk imports at runtime but doesn't contain, such as an external module.
+#[derive(Debug, Clone)]
+pub struct ChunkDependency {
+  pub id: SmolStr,
+  // The variable holding the dependency in non-ES formats. Such as `react` in `var react = require('react')`.
+  pub name: String,
+  pub imports: Vec<ImportBinding>,
+  // (imported, exported). Both are `*` for `export * from 'foo'`.
+  pub reexports: Vec<(JsWord, JsWord)>,
+}
+
+impl ChunkDependency {
+  pub fn new(id: SmolStr) -> Self {
+    Self {
+      id,
+      name: Default::default(),
+      imports: Default::default(),
+      reexports: Default::default(),
+    }
+  }
+
+  #[inline]
+  pub fn find_import(&self, imported: &JsWord) -> Option<&ImportBinding> {
+    self
+      .imports
+      .iter()
+      .find(|binding| &binding.imported == imported)
+  }
+
+  #[inline]
+  pub fn add_reexport(&mut self, imported: JsWord, exported: JsWord) {
+    if !self
+      .reexports
+      .iter()
+      .any(|(_, existed)| existed == &exported)
+    {
+      self.reexports.push((imported, exported));
+    }
+  }
+
+  #[inline]
+  pub fn is_side_effect_only(&self) -> bool {
+    self.imports.is_empty() && self.reexports.is_empty()
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChunkExport {
+  pub exported: JsWord,
+  pub local: String,
+  // Root mark of the exported binding.
+  pub mark: Mark,
+}
+
+pub struct FinalizerArgs<'a> {
+  // Id of the entry module. Used for error messages.
+  pub id: &'a str,
+  pub file_name: &'a str,
+  pub dependencies: &'a [ChunkDependency],
+  pub exports: &'a [ChunkExport],
+}
+
+impl<'a> FinalizerArgs<'a> {
+  pub fn get_export_names(&self) -> Vec<&str> {
+    let mut names = self
+      .exports
+      .iter()
+      .map(|export| &*export.exported)
+      .chain(
+        self
+          .dependencies
+          .iter()
+          .flat_map(|dep| dep.reexports.iter().map(|(_, exported)| &**exported)),
+      )
+      .collect::<Vec<_>>();
+    names.sort_unstable();
+    names
+  }
+}
+
+pub fn finalize(
+  body: String,
+  args: &FinalizerArgs,
+  options: &NormalizedOutputOptions,
+) -> Result<String, RolldownError> {
+  match options.format {
+    InternalModuleFormat::ES => Ok(es::finalize(body, args)),
+    InternalModuleFormat::CJS => {
+      let export_mode = get_export_mode(args, options.exports)?;
+      Ok(cjs::finalize(body, args, export_mode, options))
+    }
+    InternalModuleFormat::UMD => {
+      let export_mode = get_export_mode(args, options.exports)?;
+      umd::finalize(body, args, export_mode, options)
+    }
+    InternalModuleFormat::IIFE => {
+      let export_mode = get_export_mode(args, options.exports)?;
+      iife::finalize(body, args, export_mode, options)
+    }
+    InternalModuleFormat::AMD => {
+      let export_mode = get_export_mode(args, options.exports)?;
+      amd::finalize(body, args, export_mode, options)
+    }
+    InternalModuleFormat::System => Ok(system::finalize(body, args, options)),
+  }
+}
+
+pub fn get_export_mode(
+  args: &FinalizerArgs,
+  export_mode: ExportMode,
+) -> Result<ExportMode, RolldownError> {
+  let export_names = args.get_export_names();
+  let is_default_only = export_names.len() == 1 && export_names[0] == "default";
+  let invalid_export_option = || RolldownError::InvalidExportOption {
+    option: export_mode.as_str(),
+    id: args.id.to_string(),
+    exports: export_names.join(", "),
+  };
+  match export_mode {
+    ExportMode::Default if !is_default_only => Err(invalid_export_option()),
+    ExportMode::None if !export_names.is_empty() => Err(invalid_export_option()),
+    ExportMode::Auto if export_names.is_empty() => Ok(ExportMode::None),
+    ExportMode::Auto if is_default_only => Ok(ExportMode::Default),
+    ExportMode::Auto => Ok(ExportMode::Named),
+    _ => Ok(export_mode),
+  }
+}
+
+#[inline]
+pub fn quote(s: &str) -> String {
+  format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
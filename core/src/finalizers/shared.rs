@@ -0,0 +1,234 @@
+use super::{quote, ChunkDependency, FinalizerArgs};
+use crate::{
+  types::{ExportMode, NormalizedOutputOptions},
+  utils::name_helpers::is_legal,
+  worker::RolldownError,
+};
+
+const INTEROP_DEFAULT_HELPER: &str =
+  "function _interopDefault (e) { return e && e.__esModule ? e['default'] : e; }";
+
+const INTEROP_NAMESPACE_HELPER: &str = r#"function _interopNamespace (e) {
+  if (e && e.__esModule) return e;
+  var n = Object.create(null);
+  if (e) {
+    Object.keys(e).forEach(function (k) {
+      if (k !== 'default') {
+        var d = Object.getOwnPropertyDescriptor(e, k);
+        Object.defineProperty(n, k, d.get ? d : {
+          enumerable: true,
+          get: function () { return e[k]; }
+        });
+      }
+    });
+  }
+  n['default'] = e;
+  return Object.freeze(n);
+}"#;
+
+/// Render the interop helpers and variables for default and namespace imports of non-ES formats.
+/// Named imports don't need them, since they are accessed as `foo.bar` directly.
+pub fn render_interop_block(dependencies: &[ChunkDependency]) -> String {
+  let mut needs_interop_default = false;
+  let mut needs_interop_namespace = false;
+  let mut variables = vec![];
+  dependencies.iter().for_each(|dep| {
+    dep
+      .imports
+      .iter()
+      .for_each(|binding| match &*binding.imported {
+        "default" => {
+          needs_interop_default = true;
+          variables.push(format!(
+            "var {} = _interopDefault({});",
+            binding.local, dep.name
+          ));
+        }
+        "*" => {
+          needs_interop_namespace = true;
+          variables.push(format!(
+            "var {} = _interopNamespace({});",
+            binding.local, dep.name
+          ));
+        }
+        _ => {}
+      });
+    if dep
+      .reexports
+      .iter()
+      .any(|(imported, exported)| imported == "*" && exported != "*")
+    {
+      needs_interop_namespace = true;
+    }
+  });
+
+  let mut lines = vec![];
+  if needs_interop_default {
+    lines.push(INTEROP_DEFAULT_HELPER.to_string());
+  }
+  if needs_interop_namespace {
+    lines.push(INTEROP_NAMESPACE_HELPER.to_string());
+  }
+  if !variables.is_empty() {
+    lines.push(variables.join("\n"));
+  }
+  lines.join("\n\n")
+}
+
+pub fn render_es_module_marker(
+  args: &FinalizerArgs,
+  export_mode: ExportMode,
+  options: &NormalizedOutputOptions,
+) -> Option<&'static str> {
+  if options.es_module && export_mode == ExportMode::Named && !args.get_export_names().is_empty() {
+    Some("Object.defineProperty(exports, '__esModule', { value: true });")
+  } else {
+    None
+  }
+}
+
+/// Render exports of non-ES formats. `default_mechanism` is how the value of default export mode is exposed,
+/// such as `module.exports = ` for CommonJS or `return ` for formats using a factory function.
+pub fn render_export_block(
+  args: &FinalizerArgs,
+  export_mode: ExportMode,
+  default_mechanism: &str,
+) -> String {
+  match export_mode {
+    ExportMode::None | ExportMode::Auto => Default::default(),
+    ExportMode::Default => {
+      let local = args
+        .exports
+        .iter()
+        .find(|export| &export.exported == "default")
+        .map(|export| export.local.clone())
+        .or_else(|| {
+          args.dependencies.iter().find_map(|dep| {
+            dep
+              .reexports
+              .iter()
+              .find(|(_, exported)| exported == "default")
+              .map(|(imported, _)| format!("{}.{}", dep.name, imported))
+          })
+        })
+        .unwrap();
+      format!("{}{};", default_mechanism, local)
+    }
+    ExportMode::Named => {
+      let mut lines = vec![];
+      args.dependencies.iter().for_each(|dep| {
+        dep
+          .reexports
+          .iter()
+          .for_each(|(imported, exported)| match (&**imported, &**exported) {
+            ("*", "*") => {}
+            ("*", exported) => lines.push(format!(
+              "exports.{} = _interopNamespace({});",
+              exported, dep.name
+            )),
+            (imported, exported) => lines.push(format!(
+              "Object.defineProperty(exports, {}, {{\n  enumerable: true,\n  get: function () {{ return {}.{}; }}\n}});",
+              quote(exported),
+              dep.name,
+              imported
+            )),
+          });
+      });
+      args.exports.iter().for_each(|export| {
+        lines.push(format!("exports.{} = {};", export.exported, export.local));
+      });
+      // `export * from 'foo'` should not override exports of the chunk itself.
+      args.dependencies.iter().for_each(|dep| {
+        if dep.reexports.iter().any(|(imported, exported)| imported == "*" && exported == "*") {
+          lines.push(format!(
+            "Object.keys({name}).forEach(function (k) {{\n  if (k !== 'default' && !exports.hasOwnProperty(k)) Object.defineProperty(exports, k, {{\n    enumerable: true,\n    get: function () {{ return {name}[k]; }}\n  }});\n}});",
+            name = dep.name
+          ));
+        }
+      });
+      lines.join("\n")
+    }
+  }
+}
+
+/// `foo` renders `.foo` and `foo-bar` renders `['foo-bar']`.
+#[inline]
+pub fn property_access(name: &str) -> String {
+  if is_legal(name) {
+    format!(".{}", name)
+  } else {
+    format!("[{}]", quote(name))
+  }
+}
+
+/// `output.name` of IIFE and UMD bundles is a variable name, or a dotted path like `a.b.c` of identifiers.
+pub fn validate_name(name: &str) -> Result<(), RolldownError> {
+  if name
+    .split('.')
+    .all(|segment| !segment.is_empty() && is_legal(segment))
+  {
+    Ok(())
+  } else {
+    Err(RolldownError::InvalidOption(format!(
+      "Given name \"{}\" is not a legal JS identifier or a dotted path of them.",
+      name
+    )))
+  }
+}
+
+/// Statements creating the namespaces of the dotted `name` on `root`, which end with a new line.
+/// `("this", "a.b.c")` renders `this.a = this.a || {};` and `this.a.b = this.a.b || {};`.
+pub fn setup_namespace(root: &str, name: &str) -> String {
+  let segments = name.split('.').collect::<Vec<_>>();
+  let mut path = root.to_string();
+  segments[..segments.len() - 1]
+    .iter()
+    .map(|segment| {
+      path.push_str(&property_access(segment));
+      format!("{path} = {path} || {{}};\n", path = path)
+    })
+    .collect()
+}
+
+/// `("this", "a.b")` renders `this.a.b`.
+pub fn namespace_path(root: &str, name: &str) -> String {
+  std::iter::once(root.to_string())
+    .chain(name.split('.').map(property_access))
+    .collect()
+}
+
+/// Assign `value` to the dotted `name` on `root`, creating intermediate namespaces if needed.
+/// `("this", "a.b", "{}", false)` renders `this.a = this.a || {}, this.a.b = {}`.
+/// With `extend`, an existing value is kept. Such as `this.a.b = this.a.b || {}`.
+pub fn assign_to_namespace(root: &str, name: &str, value: &str, extend: bool) -> String {
+  let segments = name.split('.').collect::<Vec<_>>();
+  let mut path = root.to_string();
+  let mut assignments = vec![];
+  segments[..segments.len() - 1].iter().for_each(|segment| {
+    path.push_str(&property_access(segment));
+    assignments.push(format!("{path} = {path} || {{}}", path = path));
+  });
+  path.push_str(&property_access(segments[segments.len() - 1]));
+  if extend {
+    assignments.push(format!("{path} = {path} || {}", value, path = path));
+  } else {
+    assignments.push(format!("{} = {}", path, value));
+  }
+  assignments.join(", ")
+}
+
+/// Global variable name of an external module for UMD and IIFE, such as `React` for `react`.
+pub fn get_global_name(dep: &ChunkDependency, options: &NormalizedOutputOptions) -> String {
+  options
+    .globals
+    .get(dep.id.as_str())
+    .cloned()
+    .unwrap_or_else(|| {
+      log::warn!(
+        "No name was provided for external module '{}' in output.globals – guessing '{}'",
+        dep.id,
+        dep.name
+      );
+      dep.name.clone()
+    })
+}
@@ -0,0 +1,119 @@
+use super::{quote, shared::property_access, ChunkDependency, FinalizerArgs};
+use crate::types::NormalizedOutputOptions;
+
+pub fn finalize(body: String, args: &FinalizerArgs, options: &NormalizedOutputOptions) -> String {
+  let export_names = args.get_export_names();
+  let has_star_reexport = args.dependencies.iter().any(|dep| {
+    dep
+      .reexports
+      .iter()
+      .any(|(imported, exported)| imported == "*" && exported == "*")
+  });
+
+  let deps = args
+    .dependencies
+    .iter()
+    .map(|dep| quote(&dep.id))
+    .collect::<Vec<_>>();
+  let setters = args
+    .dependencies
+    .iter()
+    .map(render_setter)
+    .collect::<Vec<_>>();
+
+  let mut variables = vec![];
+  let imported_locals = args
+    .dependencies
+    .iter()
+    .flat_map(|dep| dep.imports.iter().map(|binding| binding.local.as_str()))
+    .collect::<Vec<_>>();
+  if !imported_locals.is_empty() {
+    variables.push(format!("var {};", imported_locals.join(", ")));
+  }
+  if has_star_reexport {
+    // `export * from 'foo'` should not override exports of the chunk itself.
+    let excludes = std::iter::once("default")
+      .chain(export_names.iter().copied())
+      .map(|name| format!("{}: 1", quote(name)))
+      .collect::<Vec<_>>();
+    variables.push(format!(
+      "var _starExcludes = {{ {} }};",
+      excludes.join(", ")
+    ));
+  }
+
+  let mut execute = body;
+  if !args.exports.is_empty() {
+    // Initial values are exported once the chunk is executed. Reassignments are exported where they happen.
+    let exports = args
+      .exports
+      .iter()
+      .map(|export| format!("{}: {}", quote(&export.exported), export.local))
+      .collect::<Vec<_>>();
+    execute.push_str(&format!("\nexports({{ {} }});\n", exports.join(", ")));
+  }
+
+  let mut register_args = vec![];
+  if let Some(name) = &options.name {
+    register_args.push(quote(name));
+  }
+  register_args.push(format!("[{}]", deps.join(", ")));
+
+  let mut output = format!(
+    "System.register({}, (function (exports, module) {{\n  'use strict';\n",
+    register_args.join(", ")
+  );
+  variables.iter().for_each(|line| {
+    output.push_str("  ");
+    output.push_str(line);
+    output.push('\n');
+  });
+  output.push_str(&format!(
+    "  return {{\n    setters: [{}],\n    execute: (function () {{\n\n{}\n    }})\n  }};\n}}));\n",
+    setters.join(", "),
+    execute
+  ));
+  output
+}
+
+fn render_setter(dep: &ChunkDependency) -> String {
+  if dep.is_side_effect_only() {
+    return "null".to_string();
+  }
+  let mut lines = dep
+    .imports
+    .iter()
+    .map(|binding| match &*binding.imported {
+      "*" => format!("{} = module;", binding.local),
+      imported => format!("{} = module{};", binding.local, property_access(imported)),
+    })
+    .collect::<Vec<_>>();
+
+  let mut reexports = vec![];
+  dep
+    .reexports
+    .iter()
+    .for_each(|(imported, exported)| match (&**imported, &**exported) {
+      ("*", "*") => lines.push(
+        "var setter = {};\nfor (var name in module) {\n  if (!_starExcludes[name]) setter[name] = module[name];\n}\nexports(setter);"
+          .to_string(),
+      ),
+      ("*", exported) => reexports.push(format!("{}: module", quote(exported))),
+      (imported, exported) => reexports.push(format!(
+        "{}: module{}",
+        quote(exported),
+        property_access(imported)
+      )),
+    });
+  if !reexports.is_empty() {
+    lines.push(format!("exports({{ {} }});", reexports.join(", ")));
+  }
+
+  let body = lines
+    .join("\n")
+    .lines()
+    .map(|line| format!("      {}", line))
+    .collect::<Vec<_>>()
+    .join("\n");
+  format!("function (module) {{\n{}\n    }}", body)
+}
@@ -0,0 +1,132 @@
+use super::{
+  quote,
+  shared::{
+    assign_to_namespace, get_global_name, property_access, render_es_module_marker,
+    render_export_block, render_interop_block, validate_name,
+  },
+  FinalizerArgs,
+};
+use crate::{
+  types::{ExportMode, NormalizedOutputOptions},
+  worker::RolldownError,
+};
+
+pub fn finalize(
+  body: String,
+  args: &FinalizerArgs,
+  export_mode: ExportMode,
+  options: &NormalizedOutputOptions,
+) -> Result<String, RolldownError> {
+  let is_named_export_mode = export_mode == ExportMode::Named;
+  let name = match (&options.name, export_mode) {
+    (_, ExportMode::None) => None,
+    (Some(name), _) => Some(name),
+    (None, _) => {
+      return Err(RolldownError::InvalidOption(
+        "You must supply \"output.name\" for UMD bundles that have exports so that the exports are accessible in environments without a module loader.".to_string(),
+      ))
+    }
+  };
+  if let Some(name) = name {
+    validate_name(name)?;
+  }
+
+  let mut params = vec![];
+  let mut cjs_args = vec![];
+  let mut amd_deps = vec![];
+  let mut global_args = vec![];
+  if is_named_export_mode {
+    params.push("exports".to_string());
+    cjs_args.push("exports".to_string());
+    amd_deps.push(quote("exports"));
+  }
+  args.dependencies.iter().for_each(|dep| {
+    params.push(dep.name.clone());
+    cjs_args.push(format!("require({})", quote(&dep.id)));
+    amd_deps.push(quote(&dep.id));
+    let global_name = get_global_name(dep, options);
+    global_args.push(format!(
+      "global{}",
+      global_name
+        .split('.')
+        .map(property_access)
+        .collect::<String>()
+    ));
+  });
+
+  let cjs_factory = format!("factory({})", cjs_args.join(", "));
+  let cjs_export = if export_mode == ExportMode::Default {
+    format!("module.exports = {}", cjs_factory)
+  } else {
+    cjs_factory
+  };
+
+  let amd_export = if amd_deps.is_empty() {
+    "define(factory)".to_string()
+  } else {
+    format!("define([{}], factory)", amd_deps.join(", "))
+  };
+
+  let global_export = match name {
+    Some(name) if is_named_export_mode => {
+      let exports_arg = assign_to_namespace("global", name, "{}", options.extend);
+      let exports_arg = if exports_arg.contains(", ") {
+        format!("({})", exports_arg)
+      } else {
+        exports_arg
+      };
+      format!(
+        "factory({})",
+        std::iter::once(exports_arg)
+          .chain(global_args)
+          .collect::<Vec<_>>()
+          .join(", ")
+      )
+    }
+    Some(name) => {
+      let assignment = assign_to_namespace(
+        "global",
+        name,
+        &format!("factory({})", global_args.join(", ")),
+        false,
+      );
+      if assignment.contains(", ") {
+        format!("({})", assignment)
+      } else {
+        assignment
+      }
+    }
+    None => format!("factory({})", global_args.join(", ")),
+  };
+
+  let mut blocks = vec!["'use strict';".to_string()];
+  if let Some(marker) = render_es_module_marker(args, export_mode, options) {
+    blocks.push(marker.to_string());
+  }
+  let interop_block = render_interop_block(args.dependencies);
+  if !interop_block.is_empty() {
+    blocks.push(interop_block);
+  }
+  blocks.push(body);
+  let export_block = render_export_block(args, export_mode, "return ");
+  if !export_block.is_empty() {
+    blocks.push(export_block);
+  }
+
+  Ok(format!(
+    "(function (global, factory) {{
+  typeof exports === 'object' && typeof module !== 'undefined' ? {} :
+  typeof define === 'function' && define.amd ? {} :
+  (global = typeof globalThis !== 'undefined' ? globalThis : global || self, {});
+}})(this, (function ({}) {{
+{}
+
+}}));
+",
+    cjs_export,
+    amd_export,
+    global_export,
+    params.join(", "),
+    blocks.join("\n\n")
+  ))
+}
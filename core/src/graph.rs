@@ -15,16 +15,21 @@ use petgraph::{graph::NodeIndex, visit::EdgeRef, EdgeDirection};
 use rayon::prelude::*;
 use smol_str::SmolStr;
 
-use swc_common::Mark;
+use swc_common::{Mark, Span, SyntaxContext};
+use swc_ecma_visit::VisitMutWith;
 
 use crate::{
+  disk_cache::{DiskCache, SpanMapper},
+  ext::{MarkExt, SyntaxContextExt},
   external_module::ExternalModule,
   module::Module,
+  plugin_driver::{Loader, PluginDriver},
   scanner::rel::RelationInfo,
+  structs::EmittedAsset,
   symbol_box::SymbolBox,
-  types::{NormalizedInputOptions, ResolvedId},
-  utils::{is_decl_or_stmt, resolve_id},
-  worker::Worker,
+  types::{ModuleInfo, NormalizedInputOptions, ResolvedId},
+  utils::is_decl_or_stmt,
+  worker::{RolldownError, Worker},
 };
 
 type ModulePetGraph = petgraph::graph::DiGraph<SmolStr, Rel>;
@@ -34,18 +39,163 @@ pub struct Graph {
   resolved_entries: Vec<ResolvedId>,
   pub module_graph: ModulePetGraph,
   pub entry_indexs: Vec<NodeIndex>,
+  // Modules imported via `import()`, which are bundled as implicit entries.
+  pub dynamic_entry_indexs: Vec<NodeIndex>,
   pub ordered_modules: Vec<NodeIndex>,
   pub symbol_box: Arc<Mutex<SymbolBox>>,
   pub module_by_id: HashMap<SmolStr, Box<Module>>,
+  pub external_module_by_id: HashMap<SmolStr, ExternalModule>,
   pub mark_to_stmt: Arc<DashMap<Mark, (SmolStr, usize)>>,
+  pub emitted_assets: Vec<EmittedAsset>,
+  // Assets emitted by plugins while building by reference id, which are part of every output.
+  pub plugin_assets: Vec<(String, EmittedAsset)>,
+  // Files other than modules that the build depends on.
+  pub watch_files: Vec<String>,
+  // Modules of the previous build to reuse, and the modules of this build after `build`.
+  pub cache: Option<GraphCache>,
+  // Shared with workers to run the hooks of plugins.
+  pub plugin_driver: Arc<PluginDriver>,
+}
+
+// A module as emitted by the worker, before linking mutates it.
+#[derive(Clone)]
+pub struct CachedModule {
+  pub module: Module,
+  // (dependency id, relation) in the order they were found
+  pub dependencies: Vec<(SmolStr, Rel)>,
+  // Entries of `Graph::mark_to_stmt` belonging to the module
+  pub marks: Vec<(Mark, usize)>,
+  // (mark, root) of marks unioned while the module is scanned. Recorded once the module is cached.
+  pub unions: Vec<(Mark, Mark)>,
+}
+
+impl CachedModule {
+  /// The module with new marks of `symbol_box`, unioned as they were when the module was scanned.
+  pub fn remark(&self, symbol_box: &mut SymbolBox) -> Self {
+    let mut cached = self.clone();
+    let mut fresh_marks = HashMap::new();
+    cached.map_marks(&mut |mark| {
+      *fresh_marks
+        .entry(mark)
+        .or_insert_with(|| symbol_box.new_mark())
+    });
+    cached
+      .unions
+      .iter()
+      .for_each(|(mark, root)| symbol_box.union(*mark, *root));
+    cached
+  }
+
+  // Modules are linked after they are cached, so roots found here are unions made while scanning.
+  fn record_unions(&mut self, symbol_box: &mut SymbolBox) {
+    let mut unions = HashSet::new();
+    self.map_marks(&mut |mark| {
+      let root = symbol_box.find_root(mark);
+      if root != mark {
+        unions.insert((mark, root));
+      }
+      mark
+    });
+    self.unions = unions.into_iter().collect();
+  }
+
+  fn map_marks(&mut self, f: &mut impl FnMut(Mark) -> Mark) {
+    let module = &mut self.module;
+    module.statements.iter_mut().for_each(|stmt| {
+      stmt.node.visit_mut_with(&mut SpanMapper(|span: Span| {
+        if span.ctxt == SyntaxContext::empty() {
+          span
+        } else {
+          span.with_ctxt(f(span.ctxt.as_mark()).as_ctxt())
+        }
+      }));
+      stmt.declared.values_mut().for_each(|mark| *mark = f(*mark));
+      stmt.reads = stmt.reads.iter().map(|mark| f(*mark)).collect();
+      stmt.writes = stmt.writes.iter().map(|mark| f(*mark)).collect();
+    });
+    module
+      .local_exports
+      .values_mut()
+      .for_each(|desc| desc.mark = f(desc.mark));
+    module
+      .re_exports
+      .values_mut()
+      .for_each(|desc| desc.mark = f(desc.mark));
+    module
+      .exports
+      .values_mut()
+      .chain(module.declared_symbols.values_mut())
+      .chain(module.imported_symbols.values_mut())
+      .for_each(|mark| *mark = f(*mark));
+    module.namespace.mark = f(module.namespace.mark);
+    self.dependencies.iter_mut().for_each(|(_, rel)| {
+      if let Rel::Import(info) | Rel::ReExport(info) = rel {
+        info.names = info
+          .names
+          .drain()
+          .map(|mut specifier| {
+            specifier.mark = f(specifier.mark);
+            specifier
+          })
+          .collect();
+      }
+    });
+    self.marks.iter_mut().for_each(|(mark, _)| *mark = f(*mark));
+    self.unions.iter_mut().for_each(|(mark, root)| {
+      *mark = f(*mark);
+      *root = f(*root);
+    });
+  }
+}
+
+/// Modules of a finished build, which could be reused by the next build of the same input. Every build has
+/// its own `SymbolBox`, so cached modules are given new marks when they are reused.
+#[derive(Clone, Default)]
+pub struct GraphCache {
+  pub modules: HashMap<SmolStr, CachedModule>,
+  // Modules dropped by `invalidate`, which are still modules of the input until a build succeeds.
+  dropped: HashSet<SmolStr>,
+}
+
+impl GraphCache {
+  /// Drop `changed_ids`. Importers are linked again with the new exports, since cached modules don't keep
+  /// unions made by linking. Ids which are not modules, such as files added by `Graph::add_watch_file`, might
+  /// affect any module, so everything is dropped. Returns whether any cached module was dropped.
+  pub fn invalidate(&mut self, changed_ids: &[&str]) -> bool {
+    if changed_ids
+      .iter()
+      .any(|id| !self.contains(id) && !self.dropped.contains(*id))
+    {
+      let is_empty = self.modules.is_empty();
+      *self = Default::default();
+      return !is_empty;
+    }
+    let count = self.modules.len();
+    changed_ids.iter().for_each(|id| {
+      if self.modules.remove(*id).is_some() {
+        self.dropped.insert(SmolStr::new(id));
+      }
+    });
+    count != self.modules.len()
+  }
+
+  pub fn contains(&self, id: &str) -> bool {
+    self.modules.contains_key(id)
+  }
+
+  pub fn module_ids(&self) -> impl Iterator<Item = &SmolStr> {
+    self.modules.keys()
+  }
 }
 
 // Relation between modules
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Rel {
   Import(RelationInfo),
   ReExport(RelationInfo),
   ReExportAll(usize),
+  // `import('./foo')`
+  DynImport,
 }
 
 impl Rel {
@@ -55,8 +205,14 @@ impl Rel {
       Self::Import(info) => info.order,
       Self::ReExport(info) => info.order,
       Self::ReExportAll(order) => *order,
+      Self::DynImport => usize::MAX,
     }
   }
+
+  #[inline]
+  pub fn is_static(&self) -> bool {
+    !matches!(self, Self::DynImport)
+  }
 }
 
 pub enum Msg {
@@ -68,14 +224,51 @@ pub enum Msg {
 impl Graph {
   pub fn new(input_options: NormalizedInputOptions) -> Self {
     Self {
-      input_options,
       resolved_entries: Default::default(),
       entry_indexs: Default::default(),
+      dynamic_entry_indexs: Default::default(),
       ordered_modules: Default::default(),
       module_by_id: Default::default(),
+      external_module_by_id: Default::default(),
       module_graph: ModulePetGraph::new(),
       symbol_box: Arc::new(Mutex::new(SymbolBox::new())),
       mark_to_stmt: Default::default(),
+      emitted_assets: Default::default(),
+      plugin_assets: Default::default(),
+      watch_files: Default::default(),
+      cache: None,
+      plugin_driver: Arc::new(PluginDriver::new(input_options.plugins.clone())),
+      input_options,
+    }
+  }
+
+  /// A graph reusing the modules in `cache`. The modules of this build are cached again after `build`.
+  pub fn with_cache(input_options: NormalizedInputOptions, cache: GraphCache) -> Self {
+    Self {
+      cache: Some(cache),
+      ..Self::new(input_options)
+    }
+  }
+
+  /// A copy of the linked graph to bundle, so the graph could be bundled again for another output. Bundling
+  /// mutates modules and unions marks, so they are copied, while plugins are shared.
+  pub fn fork(&self) -> Self {
+    Self {
+      input_options: self.input_options.clone(),
+      resolved_entries: self.resolved_entries.clone(),
+      module_graph: self.module_graph.clone(),
+      entry_indexs: self.entry_indexs.clone(),
+      dynamic_entry_indexs: self.dynamic_entry_indexs.clone(),
+      ordered_modules: self.ordered_modules.clone(),
+      symbol_box: Arc::new(Mutex::new(self.symbol_box.lock().unwrap().clone())),
+      module_by_id: self.module_by_id.clone(),
+      external_module_by_id: self.external_module_by_id.clone(),
+      mark_to_stmt: Arc::new((*self.mark_to_stmt).clone()),
+      emitted_assets: self.emitted_assets.clone(),
+      plugin_assets: self.plugin_assets.clone(),
+      watch_files: self.watch_files.clone(),
+      cache: None,
+      plugin_driver: self.plugin_driver.clone(),
     }
   }
 
@@ -87,7 +280,7 @@ impl Graph {
     })
   }
   // build dependency graph via entry modules.
-  fn generate_module_graph(&mut self) {
+  fn generate_module_graph(&mut self) -> Result<(), RolldownError> {
     let nums_of_thread = num_cpus::get();
     let idle_thread_count: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(nums_of_thread));
     let job_queue: Arc<SegQueue<ResolvedId>> = Default::default();
@@ -95,8 +288,8 @@ impl Graph {
       .input_options
       .input
       .iter()
-      .map(|entry| resolve_id(entry, None, false))
-      .collect();
+      .map(|entry| self.plugin_driver.resolve(entry, None, &[]))
+      .collect::<Result<_, _>>()?;
 
     let mut path_to_node_idx: HashMap<SmolStr, NodeIndex> = Default::default();
 
@@ -110,56 +303,151 @@ impl Graph {
     let processed_id: Arc<DashSet<SmolStr>> = Default::default();
 
     let (tx, rx) = channel::unbounded::<Msg>();
+    let errors: Arc<Mutex<Vec<RolldownError>>> = Default::default();
+    let is_caching = self.cache.is_some();
+    let (cache, dropped) = self
+      .cache
+      .take()
+      .map(|cache| (cache.modules, cache.dropped))
+      .unwrap_or_default();
+    let cache = Arc::new(cache);
+    let disk_cache = self
+      .input_options
+      .cache_dir
+      .as_ref()
+      .map(|dir| Arc::new(DiskCache::new(dir, &self.input_options)));
+    let mut cached_modules: HashMap<SmolStr, CachedModule> = Default::default();
+    let mut dependencies: HashMap<SmolStr, Vec<(SmolStr, Rel)>> = Default::default();
+    let worker = Worker {
+      tx,
+      job_queue: job_queue.clone(),
+      processed_id,
+      symbol_box: self.symbol_box.clone(),
+      mark_to_stmt: self.mark_to_stmt.clone(),
+      cache: cache.clone(),
+      disk_cache,
+      plugin_driver: self.plugin_driver.clone(),
+    };
+    *self.plugin_driver.loader.lock().unwrap() = Some(Loader {
+      worker: worker.clone(),
+      errors: errors.clone(),
+      thread: std::thread::current().id(),
+    });
 
-    for _ in 0..nums_of_thread {
-      let idle_thread_count = idle_thread_count.clone();
-      let mut worker = Worker {
-        tx: tx.clone(),
-        job_queue: job_queue.clone(),
-        processed_id: processed_id.clone(),
-        symbol_box: self.symbol_box.clone(),
-        mark_to_stmt: self.mark_to_stmt.clone(),
-      };
-      std::thread::spawn(move || loop {
-        idle_thread_count.fetch_sub(1, Ordering::SeqCst);
-        if let Err(e) = worker.run() {
-          eprintln!("{}", e);
-        }
-        idle_thread_count.fetch_add(1, Ordering::SeqCst);
-        loop {
-          if !worker.job_queue.is_empty() {
-            break;
-            // need to work again
-          } else if idle_thread_count.load(Ordering::SeqCst) == nums_of_thread {
-            // All threads are idle now. There's no more work to do.
-            return;
-          }
-        }
-      });
-    }
+    // Chunks emitted by plugins are loaded like entries. Workers exit once there's no job left, so chunks emitted
+    // while modules are loaded are loaded in another round.
+    let mut emitted_chunk_count = 0;
+    loop {
+      {
+        let emitted_chunks = self.plugin_driver.emitted_chunks.lock().unwrap();
+        emitted_chunks[emitted_chunk_count..]
+          .iter()
+          .for_each(|resolved_id| job_queue.push(resolved_id.clone()));
+        emitted_chunk_count = emitted_chunks.len();
+      }
+      if job_queue.is_empty() || !errors.lock().unwrap().is_empty() {
+        break;
+      }
 
-    while idle_thread_count.load(Ordering::SeqCst) != nums_of_thread
-      || job_queue.len() > 0
-      || !rx.is_empty()
-    {
-      if let Ok(job) = rx.try_recv() {
-        match job {
-          Msg::NewMod(module) => {
-            self.module_by_id.insert(module.id.clone(), module);
+      for _ in 0..nums_of_thread {
+        let idle_thread_count = idle_thread_count.clone();
+        let mut worker = worker.clone();
+        let errors = errors.clone();
+        std::thread::spawn(move || loop {
+          idle_thread_count.fetch_sub(1, Ordering::SeqCst);
+          if let Err(e) = worker.run() {
+            errors.lock().unwrap().push(e);
           }
-          Msg::DependencyReference(from, to, rel) => {
-            let from_id = *path_to_node_idx
-              .entry(from)
-              .or_insert_with_key(|key| self.module_graph.add_node(key.clone()));
-            let to_id = *path_to_node_idx
-              .entry(to)
-              .or_insert_with_key(|key| self.module_graph.add_node(key.clone()));
-            self.module_graph.add_edge(from_id, to_id, rel);
+          idle_thread_count.fetch_add(1, Ordering::SeqCst);
+          loop {
+            if !worker.job_queue.is_empty() {
+              break;
+              // need to work again
+            } else if idle_thread_count.load(Ordering::SeqCst) == nums_of_thread {
+              // All threads are idle now. There's no more work to do.
+              return;
+            }
+          }
+        });
+      }
+
+      while idle_thread_count.load(Ordering::SeqCst) != nums_of_thread
+        || job_queue.len() > 0
+        || !rx.is_empty()
+      {
+        if let Ok(job) = rx.try_recv() {
+          match job {
+            Msg::NewMod(module) => {
+              if is_caching {
+                cached_modules.insert(
+                  module.id.clone(),
+                  CachedModule {
+                    module: (*module).clone(),
+                    dependencies: Default::default(),
+                    marks: Default::default(),
+                    unions: Default::default(),
+                  },
+                );
+              }
+              let id = module.id.clone();
+              self.module_by_id.insert(id.clone(), module);
+              if !self.plugin_driver.plugins.is_empty() {
+                // Importers might be sent after the module.
+                let node_idx = *path_to_node_idx
+                  .entry(id.clone())
+                  .or_insert_with_key(|key| self.module_graph.add_node(key.clone()));
+                let info = self.module_info_of(node_idx);
+                self
+                  .plugin_driver
+                  .module_infos
+                  .write()
+                  .unwrap()
+                  .insert(id, info.clone());
+                if let Err(err) = self.plugin_driver.module_parsed(&info) {
+                  errors.lock().unwrap().push(err);
+                }
+              }
+            }
+            Msg::NewExtMod(module) => {
+              self
+                .external_module_by_id
+                .insert(module.id.clone().into(), module);
+            }
+            Msg::DependencyReference(from, to, rel) => {
+              if is_caching {
+                dependencies
+                  .entry(from.clone())
+                  .or_default()
+                  .push((to.clone(), rel.clone()));
+              }
+              let from_id = *path_to_node_idx
+                .entry(from)
+                .or_insert_with_key(|key| self.module_graph.add_node(key.clone()));
+              let to_id = *path_to_node_idx
+                .entry(to)
+                .or_insert_with_key(|key| self.module_graph.add_node(key.clone()));
+              self.module_graph.add_edge(from_id, to_id, rel);
+            }
           }
-          _ => {}
         }
       }
     }
+    // The loader holds the driver, and workers hold the cache, which is handed back if the build fails.
+    self.plugin_driver.loader.lock().unwrap().take();
+    drop(worker);
+    self
+      .plugin_driver
+      .is_module_loaded
+      .store(true, Ordering::SeqCst);
+    let emitted_chunks = self.plugin_driver.emitted_chunks.lock().unwrap().clone();
+    emitted_chunks.into_iter().for_each(|resolved_id| {
+      let idx = *path_to_node_idx
+        .entry(resolved_id.id)
+        .or_insert_with_key(|key| self.module_graph.add_node(key.clone()));
+      if !self.entry_indexs.contains(&idx) {
+        self.entry_indexs.push(idx);
+      }
+    });
 
     let entries_id = self
       .entry_indexs
@@ -169,46 +457,206 @@ impl Graph {
     self.module_by_id.par_iter_mut().for_each(|(_key, module)| {
       module.is_user_defined_entry_point = entries_id.contains(&module.id);
     });
+
+    if let Some(err) = errors.lock().unwrap().drain(..).next() {
+      // The cache is handed back, so the build after the error still reuses the modules.
+      if is_caching {
+        self.cache = Some(GraphCache {
+          modules: Arc::try_unwrap(cache).unwrap_or_else(|cache| (*cache).clone()),
+          dropped,
+        });
+      }
+      return Err(err);
+    }
+
+    if is_caching {
+      dependencies.into_iter().for_each(|(id, dependencies)| {
+        if let Some(cached) = cached_modules.get_mut(&id) {
+          cached.dependencies = dependencies;
+        }
+      });
+      self.mark_to_stmt.iter().for_each(|pair| {
+        let (id, idx) = pair.value();
+        if let Some(cached) = cached_modules.get_mut(id) {
+          cached.marks.push((*pair.key(), *idx));
+        }
+      });
+      {
+        let mut symbol_box = self.symbol_box.lock().unwrap();
+        cached_modules
+          .values_mut()
+          .for_each(|cached| cached.record_unions(&mut symbol_box));
+      }
+      self.cache = Some(GraphCache {
+        modules: cached_modules,
+        dropped: Default::default(),
+      });
+    }
+    Ok(())
   }
 
   fn sort_modules(&mut self) {
     let mut ordered_modules = vec![];
-    let entry = self.entry_indexs[0];
     let mut visited = HashSet::new();
-    let mut stack = vec![entry];
-    while let Some(node_idx) = stack.pop() {
-      if !visited.contains(&node_idx) {
-        stack.push(node_idx);
-        visited.insert(node_idx);
-        let edges = self
-          .module_graph
-          .edges_directed(node_idx, EdgeDirection::Outgoing);
-        let mut rels = edges.collect::<Vec<_>>();
-        rels.sort_by_key(|a| a.weight().get_order());
-        rels
-          .into_iter()
-          .rev()
-          .filter(|edge| !visited.contains(&edge.target()))
-          .for_each(|edge| stack.push(edge.target()));
-      } else {
-        ordered_modules.push(node_idx);
+    let mut executed = HashSet::new();
+    let mut dynamic_entries = vec![];
+    // Walk entries one by one, so modules are executed in the order of entries.
+    // Modules imported dynamically are walked after all static dependencies as implicit entries.
+    let mut entries = self.entry_indexs.clone();
+    let mut entry_cursor = 0;
+    while entry_cursor < entries.len() {
+      let mut stack = vec![entries[entry_cursor]];
+      entry_cursor += 1;
+      while let Some(node_idx) = stack.pop() {
+        if !visited.contains(&node_idx) {
+          stack.push(node_idx);
+          visited.insert(node_idx);
+          let edges = self
+            .module_graph
+            .edges_directed(node_idx, EdgeDirection::Outgoing);
+          let mut rels = edges.collect::<Vec<_>>();
+          rels.sort_by_key(|a| a.weight().get_order());
+          rels
+            .iter()
+            .filter(|edge| !edge.weight().is_static())
+            .for_each(|edge| dynamic_entries.push(edge.target()));
+          rels
+            .into_iter()
+            .rev()
+            .filter(|edge| edge.weight().is_static())
+            .filter(|edge| !visited.contains(&edge.target()))
+            .for_each(|edge| stack.push(edge.target()));
+        } else if executed.insert(node_idx) {
+          // A module might be pushed to the stack by multiple importers.
+          ordered_modules.push(node_idx);
+        }
       }
-      // ordered_modules = ordered_modules.into_iter().rev().collect()
+      dynamic_entries.drain(..).for_each(|idx| {
+        if !entries.contains(&idx)
+          && !self
+            .external_module_by_id
+            .contains_key(&self.module_graph[idx])
+        {
+          entries.push(idx);
+          self.dynamic_entry_indexs.push(idx);
+        }
+      });
     }
 
-    // while let Some(node) = dfs.next(&self.graph) {
-    //   ordered_modules.push(node);
-    // }
+    // External modules are not part of the bundle. They are rendered as imports of the chunk.
+    ordered_modules.retain(|idx| {
+      !self
+        .external_module_by_id
+        .contains_key(&self.module_graph[*idx])
+    });
     self.ordered_modules = ordered_modules;
-    // println!("self.ordered_modules {:#?}", ordered_modules.iter().map(|idx| &self.graph[*idx]).collect::<Vec<_>>());
+  }
+
+  pub fn get_module_info(&self, id: &str) -> Option<ModuleInfo> {
+    let node_idx = self
+      .module_graph
+      .node_indices()
+      .find(|idx| self.module_graph[*idx] == id)?;
+    Some(self.module_info_of(node_idx))
+  }
+
+  fn module_info_of(&self, node_idx: NodeIndex) -> ModuleInfo {
+    let id = &self.module_graph[node_idx];
+    let module = self.module_by_id.get(id);
+    let ids = |direction: EdgeDirection, is_static: bool| {
+      let mut ids = self
+        .module_graph
+        .edges_directed(node_idx, direction)
+        .filter(|edge| edge.weight().is_static() == is_static)
+        .map(|edge| {
+          let idx = match direction {
+            EdgeDirection::Outgoing => edge.target(),
+            EdgeDirection::Incoming => edge.source(),
+          };
+          self.module_graph[idx].clone()
+        })
+        .collect::<Vec<_>>();
+      // Edges are iterated from the latest one.
+      ids.reverse();
+      let mut seen = HashSet::new();
+      ids.retain(|id| seen.insert(id.clone()));
+      ids
+    };
+    let mut exports = module
+      .map(|module| {
+        module
+          .exports
+          .keys()
+          .filter(|name| &**name != "*")
+          .map(|name| name.to_string())
+          .collect::<Vec<_>>()
+      })
+      .unwrap_or_default();
+    exports.sort();
+    ModuleInfo {
+      id: id.clone(),
+      is_entry: self.entry_indexs.contains(&node_idx),
+      is_external: self.external_module_by_id.contains_key(id),
+      importers: ids(EdgeDirection::Incoming, true),
+      dynamic_importers: ids(EdgeDirection::Incoming, false),
+      imported_ids: ids(EdgeDirection::Outgoing, true),
+      dynamically_imported_ids: ids(EdgeDirection::Outgoing, false),
+      exports,
+      meta: module.map(|module| module.meta.clone()).unwrap_or_default(),
+    }
+  }
+
+  // Module infos of the plugin context, which are refreshed once the module graph is linked.
+  fn update_module_infos(&self) {
+    let infos = self
+      .module_graph
+      .node_indices()
+      .map(|idx| (self.module_graph[idx].clone(), self.module_info_of(idx)))
+      .collect();
+    *self.plugin_driver.module_infos.write().unwrap() = infos;
+  }
+
+  pub fn emit_asset(&mut self, asset: EmittedAsset) {
+    self.emitted_assets.push(asset);
+  }
+
+  pub fn add_watch_file(&mut self, id: String) {
+    if !self.watch_files.contains(&id) {
+      self.watch_files.push(id);
+    }
   }
 
   pub fn build(&mut self) {
-    self.generate_module_graph();
-    self.sort_modules();
-    self.link_module_exports();
-    self.link_module();
-    self.include();
+    if let Err(err) = self.try_build() {
+      panic!("{}", err);
+    }
+  }
+
+  pub fn try_build(&mut self) -> Result<(), RolldownError> {
+    self.plugin_driver.start_build();
+    self.plugin_driver.options(&mut self.input_options)?;
+    *self.plugin_driver.external.write().unwrap() = Some(self.input_options.external.clone());
+    *self.plugin_driver.on_warn.write().unwrap() = Some(self.input_options.on_warn.clone());
+    let result = self
+      .plugin_driver
+      .build_start(&self.input_options)
+      .and_then(|_| self.generate_module_graph());
+    if result.is_ok() {
+      self.sort_modules();
+      self.link_module_exports();
+      self.link_module();
+      self.include();
+      self.update_module_infos();
+    }
+    self.plugin_driver.build_end(result.as_ref().err())?;
+    // Files added by plugins after the build ended are not watched.
+    let watch_files = std::mem::take(&mut *self.plugin_driver.watch_files.lock().unwrap());
+    watch_files
+      .into_iter()
+      .for_each(|id| self.add_watch_file(id));
+    // Assets emitted later, such as in the `generate_bundle` hook, belong to the output being generated.
+    self.plugin_assets = std::mem::take(&mut *self.plugin_driver.emitted_assets.lock().unwrap());
+    result
   }
 
   pub fn include(&mut self) {
@@ -220,16 +668,25 @@ impl Graph {
         treeshake,
         id
       );
-      module.include(treeshake);
+      // Unused statements of modules without side effects are removed as well.
+      if !treeshake || module.module_side_effects {
+        module.include(treeshake);
+      }
     });
 
     if treeshake {
-      self.resolved_entries.iter().for_each(|resolved_id| {
+      let entry_ids = self
+        .entry_indexs
+        .iter()
+        .chain(self.dynamic_entry_indexs.iter())
+        .map(|idx| self.module_graph[*idx].clone())
+        .collect::<Vec<_>>();
+      entry_ids.iter().for_each(|id| {
         log::debug!(
           "[treeshake]: include entry module's local exports for {:?}",
-          resolved_id.id
+          id
         );
-        let module = self.module_by_id.get_mut(&resolved_id.id.clone()).unwrap();
+        let module = self.module_by_id.get_mut(id).unwrap();
         module
           .local_exports
           .values()
@@ -285,7 +742,10 @@ impl Graph {
       let dep_ids = module
         .re_export_all_sources
         .iter()
-        .map(|dep_src| module.resolved_ids.get(dep_src).unwrap().clone().id)
+        .map(|dep_src| module.resolved_ids.get(dep_src).unwrap().clone())
+        // `export * from 'external'` is kept as it is and rendered by the finalizer.
+        .filter(|resolved_id| !resolved_id.external)
+        .map(|resolved_id| resolved_id.id)
         .collect::<Vec<_>>();
       let dep_exports = dep_ids
         .into_par_iter()
@@ -315,6 +775,13 @@ impl Graph {
         .module_graph
         .edges_directed(*idx, EdgeDirection::Outgoing);
       edges.for_each(|edge| {
+        if self
+          .external_module_by_id
+          .contains_key(&self.module_graph[edge.target()])
+        {
+          // Symbols imported from external modules are never unioned. They keep their own marks.
+          return;
+        }
         log::debug!(
           "[graph]: link module from {:?} to {:?}",
           &self.module_graph[*idx],
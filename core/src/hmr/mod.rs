@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+
+use swc_ecma_ast::{Expr, MemberExpr, MemberProp, MetaPropExpr};
+use swc_ecma_visit::{noop_visit_mut_type, VisitMut, VisitMutWith};
+
+use crate::{
+  structs::RolldownOutput,
+  types::{ExportMode, InternalModuleFormat, NormalizedInputOptions, NormalizedOutputOptions},
+  utils::ast_sugar,
+  worker::RolldownError,
+  RolldownBuild,
+};
+
+const RUNTIME: &str = include_str!("./runtime.js");
+
+/// Path of the script served by the dev server, which contains the runtime and every module.
+pub const BUNDLE_PATH: &str = "/__rolldown/bundle.js";
+/// Path of the WebSocket the runtime connects to for updates.
+pub const HMR_PATH: &str = "/__rolldown/hmr";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HmrModule {
+  pub file_name: String,
+  // A script defining the module in the runtime.
+  pub code: String,
+}
+
+/// An in-memory build for the dev server. Every module is rendered as a CommonJS chunk, so a changed module
+/// could be sent to the runtime on its own.
+pub struct DevBuild {
+  build: RolldownBuild,
+  // Modules of the latest build by file name.
+  modules: HashMap<String, HmrModule>,
+  entries: Vec<String>,
+  watch_files: Vec<String>,
+}
+
+impl DevBuild {
+  pub fn new(input_options: NormalizedInputOptions) -> Result<Self, RolldownError> {
+    let mut dev_build = Self {
+      build: RolldownBuild::try_new(input_options)?,
+      modules: Default::default(),
+      entries: Default::default(),
+      watch_files: Default::default(),
+    };
+    dev_build.generate()?;
+    Ok(dev_build)
+  }
+
+  /// Build again after `changed_ids` are modified, and return the modules whose output changed.
+  pub fn rebuild(&mut self, changed_ids: &[&str]) -> Result<Vec<HmrModule>, RolldownError> {
+    self.build.rebuild(changed_ids)?;
+    let prev = self.generate()?;
+    let mut changed = self
+      .modules
+      .values()
+      .filter(|module| prev.get(&module.file_name) != Some(module))
+      .cloned()
+      .collect::<Vec<_>>();
+    changed.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    Ok(changed)
+  }
+
+  /// The script loaded by the page, which defines every module and executes the entries.
+  pub fn script(&self) -> String {
+    let mut file_names = self.modules.keys().collect::<Vec<_>>();
+    file_names.sort();
+    let mut script = RUNTIME.to_string();
+    file_names
+      .into_iter()
+      .for_each(|file_name| script.push_str(&self.modules[file_name].code));
+    script.push_str(&format!(
+      "__rolldown_hmr__.start({});\n",
+      serde_json::to_string(&self.entries).unwrap()
+    ));
+    script
+  }
+
+  /// File names of the entry chunks of the latest build.
+  pub fn entries(&self) -> &[String] {
+    &self.entries
+  }
+
+  /// Modules and files added by `Graph::add_watch_file` in the latest build.
+  pub fn watch_files(&self) -> &[String] {
+    &self.watch_files
+  }
+
+  // Returns modules of the previous build.
+  fn generate(&mut self) -> Result<HashMap<String, HmrModule>, RolldownError> {
+    self
+      .build
+      .graph
+      .module_by_id
+      .values_mut()
+      .for_each(|module| {
+        module
+          .statements
+          .iter_mut()
+          .for_each(|stmt| stmt.node.visit_mut_with(&mut HotRewriter))
+      });
+    let output = self.build.generate(NormalizedOutputOptions {
+      format: InternalModuleFormat::CJS,
+      // Importers keep the exports objects, so bindings of updated modules could be swapped.
+      exports: ExportMode::Named,
+      preserve_modules: true,
+      ..Default::default()
+    })?;
+
+    let mut modules = HashMap::new();
+    let mut entries = vec![];
+    let mut watch_files = self.build.graph.watch_files.clone();
+    output.into_iter().for_each(|output| {
+      if let RolldownOutput::Chunk(chunk) = output {
+        if chunk.is_entry {
+          entries.push(chunk.file_name.clone());
+        }
+        watch_files.extend(chunk.module_ids);
+        let code = format!(
+          "__rolldown_hmr__.define({}, function (require, module, exports) {{\n{}}});\n",
+          serde_json::to_string(&chunk.file_name).unwrap(),
+          chunk.code
+        );
+        modules.insert(
+          chunk.file_name.clone(),
+          HmrModule {
+            file_name: chunk.file_name,
+            code,
+          },
+        );
+      }
+    });
+    entries.sort();
+    self.entries = entries;
+    self.watch_files = watch_files;
+    Ok(std::mem::replace(&mut self.modules, modules))
+  }
+}
+
+// `import.meta.hot` -> `module.hot`
+struct HotRewriter;
+
+impl VisitMut for HotRewriter {
+  noop_visit_mut_type!();
+
+  fn visit_mut_expr(&mut self, node: &mut Expr) {
+    node.visit_mut_children_with(self);
+    if let Expr::Member(MemberExpr {
+      obj,
+      prop: MemberProp::Ident(prop),
+      ..
+    }) = node
+    {
+      let is_import_meta = matches!(
+        obj.as_ref(),
+        Expr::MetaProp(MetaPropExpr { meta, prop }) if &meta.sym == "import" && &prop.sym == "meta"
+      );
+      if is_import_meta && &prop.sym == "hot" {
+        *node = *ast_sugar::member(ast_sugar::expr_ident("module"), "hot");
+      }
+    }
+  }
+}
@@ -0,0 +1,197 @@
+// Runtime of the dev server. Modules are defined by their output file names and executed on demand.
+var __rolldown_hmr__ = (function () {
+  'use strict';
+
+  var factories = Object.create(null);
+  // Records of executed modules by file name.
+  var cache = Object.create(null);
+  // Records of modules being updated, whose exports objects are kept to swap the bindings.
+  var outdated = Object.create(null);
+  var entries = [];
+
+  function define(file, factory) {
+    factories[file] = factory;
+  }
+
+  function resolve(from, request) {
+    var parts = from.split('/');
+    parts.pop();
+    request.split('/').forEach(function (part) {
+      if (part === '..') {
+        parts.pop();
+      } else if (part !== '.') {
+        parts.push(part);
+      }
+    });
+    return parts.join('/');
+  }
+
+  function createHot(record, data) {
+    return {
+      data: data,
+      accept: function (deps, callback) {
+        if (deps === undefined || typeof deps === 'function') {
+          record.selfAccepted = true;
+          if (deps) record.selfCallbacks.push(deps);
+          return;
+        }
+        var files = (Array.isArray(deps) ? deps : [deps]).map(function (dep) {
+          return resolve(record.file, dep);
+        });
+        record.acceptHandlers.push({
+          files: files,
+          callback: function () {
+            if (!callback) return;
+            var exports = files.map(function (file) {
+              return cache[file] && cache[file].exports;
+            });
+            callback(Array.isArray(deps) ? exports : exports[0]);
+          },
+        });
+      },
+      dispose: function (callback) {
+        record.disposers.push(callback);
+      },
+      invalidate: function () {
+        setTimeout(function () {
+          apply([record.file], record.file);
+        });
+      },
+    };
+  }
+
+  // Keep the exports object seen by importers, and move the new bindings into it.
+  function swap(prev, next) {
+    if (!prev || prev === next || typeof next !== 'object' || next === null) return next;
+    Object.getOwnPropertyNames(prev).forEach(function (key) {
+      if (Object.getOwnPropertyDescriptor(prev, key).configurable) delete prev[key];
+    });
+    Object.getOwnPropertyNames(next).forEach(function (key) {
+      var descriptor = Object.getOwnPropertyDescriptor(prev, key);
+      if (!descriptor || descriptor.configurable) {
+        Object.defineProperty(prev, key, Object.getOwnPropertyDescriptor(next, key));
+      }
+    });
+    return prev;
+  }
+
+  function load(file, importer) {
+    var record = cache[file];
+    if (!record) {
+      var factory = factories[file];
+      if (!factory) throw new Error('Cannot find module "' + file + '"');
+      var prev = outdated[file];
+      delete outdated[file];
+      record = cache[file] = {
+        file: file,
+        exports: {},
+        importers: prev ? prev.importers : [],
+        selfAccepted: false,
+        selfCallbacks: [],
+        acceptHandlers: [],
+        disposers: [],
+      };
+      var module = { exports: record.exports, hot: createHot(record, prev ? prev.data : {}) };
+      factory(
+        function (request) {
+          return load(resolve(file, request), file);
+        },
+        module,
+        module.exports
+      );
+      record.exports = swap(prev && prev.exports, module.exports);
+      if (prev) {
+        prev.selfCallbacks.forEach(function (callback) {
+          callback(record.exports);
+        });
+      }
+    }
+    if (importer && record.importers.indexOf(importer) < 0) record.importers.push(importer);
+    return record.exports;
+  }
+
+  function reload() {
+    location.reload();
+  }
+
+  // Re-execute the changed modules and their importers up to the modules accepting the update.
+  // `invalidated` is a module which declined the update it accepted before.
+  function apply(files, invalidated) {
+    var stale = [];
+    var handlers = [];
+    var queue = files.slice();
+    while (queue.length) {
+      var file = queue.shift();
+      var record = cache[file];
+      // Modules not executed yet use the new factories when they are required.
+      if (!record || stale.indexOf(file) >= 0) continue;
+      stale.push(file);
+      if (record.selfAccepted && file !== invalidated) continue;
+      if (entries.indexOf(file) >= 0 || !record.importers.length) return reload();
+      record.importers.forEach(function (importer) {
+        var accepted = cache[importer].acceptHandlers.filter(function (handler) {
+          return handler.files.indexOf(file) >= 0;
+        });
+        if (accepted.length) {
+          accepted.forEach(function (handler) {
+            if (handlers.indexOf(handler) < 0) handlers.push(handler);
+          });
+        } else {
+          queue.push(importer);
+        }
+      });
+    }
+    try {
+      stale.forEach(function (file) {
+        var record = cache[file];
+        record.data = {};
+        record.disposers.forEach(function (dispose) {
+          dispose(record.data);
+        });
+        outdated[file] = record;
+        delete cache[file];
+      });
+      stale.forEach(function (file) {
+        load(file);
+      });
+      handlers.forEach(function (handler) {
+        handler.callback();
+      });
+    } catch (err) {
+      console.error('[rolldown] Failed to apply the update.', err);
+      reload();
+    }
+  }
+
+  function connect() {
+    var protocol = location.protocol === 'https:' ? 'wss://' : 'ws://';
+    var socket = new WebSocket(protocol + location.host + '/__rolldown/hmr');
+    socket.onmessage = function (event) {
+      var message = JSON.parse(event.data);
+      if (message.type === 'update') {
+        message.modules.forEach(function (module) {
+          (0, eval)(module.code);
+        });
+        apply(
+          message.modules.map(function (module) {
+            return module.file;
+          })
+        );
+      } else if (message.type === 'full-reload') {
+        reload();
+      } else if (message.type === 'error') {
+        console.error('[rolldown] ' + message.message);
+      }
+    };
+  }
+
+  function start(files) {
+    entries = files;
+    files.forEach(function (file) {
+      load(file);
+    });
+    connect();
+  }
+
+  return { define: define, start: start };
+})();
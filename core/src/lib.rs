@@ -2,8 +2,11 @@
 
 pub mod bundle;
 pub mod chunk;
+pub mod disk_cache;
 pub mod external_module;
+pub mod finalizers;
 pub mod graph;
+pub mod hmr;
 // pub mod linker;
 pub mod module;
 pub mod scanner;
@@ -11,12 +14,15 @@ pub mod scanner;
 pub mod renamer;
 pub mod types;
 pub mod utils;
+pub mod watcher;
 pub mod worker;
 
-use graph::Graph;
-use structs::RolldownOutput;
+use graph::{Graph, GraphCache};
+use structs::{OutputAsset, RolldownOutput};
 pub use swc_ecma_ast as ast;
-use types::{NormalizedInputOptions, NormalizedOutputOptions};
+use types::{NormalizedInputOptions, NormalizedOutputOptions, SourceMapMode};
+use utils::source_map::source_map_to_string;
+use worker::RolldownError;
 
 use crate::bundle::Bundle;
 
@@ -30,22 +36,58 @@ pub mod symbol_box;
 
 pub struct RolldownBuild {
   pub graph: Graph,
-  // pub input_options: NormalizedInputOptions,
+  // Modules of the latest build before linking, which are reused by `rebuild`.
+  cache: GraphCache,
 }
 
 impl RolldownBuild {
   pub fn new(options: NormalizedInputOptions) -> Self {
-    let mut graph = Graph::new(options);
-    graph.build();
-    Self { graph }
+    match Self::try_new(options) {
+      Ok(build) => build,
+      Err(err) => panic!("{}", err),
+    }
   }
 
-  pub fn generate(self, options: NormalizedOutputOptions) -> Vec<RolldownOutput> {
-    handle_generate_write(false, self.graph, options)
+  /// Like `new`, but errors of the build, such as parse errors, are returned instead of panicking.
+  pub fn try_new(options: NormalizedInputOptions) -> Result<Self, RolldownError> {
+    let mut graph = Graph::with_cache(options, Default::default());
+    graph.try_build()?;
+    let cache = graph.cache.take().unwrap_or_default();
+    Ok(Self { graph, cache })
   }
 
-  pub fn write(self, options: NormalizedOutputOptions) -> Vec<RolldownOutput> {
-    handle_generate_write(true, self.graph, options)
+  /// Build again after `changed_ids` are modified. Unchanged modules of the previous build are reused, so
+  /// only the changed modules and new dependencies are loaded and parsed. If the build fails, the previous
+  /// graph is kept, and the cache is kept for the next rebuild.
+  pub fn rebuild(&mut self, changed_ids: &[&str]) -> Result<(), RolldownError> {
+    self.cache.invalidate(changed_ids);
+    let mut graph = Graph::with_cache(
+      self.graph.input_options.clone(),
+      std::mem::take(&mut self.cache),
+    );
+    graph.emitted_assets = self.graph.emitted_assets.clone();
+    graph.watch_files = self.graph.watch_files.clone();
+    // A failed build hands the cache back.
+    let result = graph.try_build();
+    self.cache = graph.cache.take().unwrap_or_default();
+    result?;
+    self.graph = graph;
+    Ok(())
+  }
+
+  pub fn generate(
+    &self,
+    options: NormalizedOutputOptions,
+  ) -> Result<Vec<RolldownOutput>, RolldownError> {
+    // `graph` stays linked, so build hooks run once however many outputs are generated.
+    handle_generate_write(false, self.graph.fork(), options)
+  }
+
+  pub fn write(
+    &self,
+    options: NormalizedOutputOptions,
+  ) -> Result<Vec<RolldownOutput>, RolldownError> {
+    handle_generate_write(true, self.graph.fork(), options)
   }
 }
 
@@ -54,25 +96,57 @@ fn handle_generate_write(
   is_write: bool,
   graph: Graph,
   output_options: NormalizedOutputOptions,
-) -> Vec<RolldownOutput> {
+) -> Result<Vec<RolldownOutput>, RolldownError> {
+  let mut bundle = Bundle::new(graph, output_options);
+  generate_bundle(is_write, &mut bundle)
+}
+
+pub(crate) fn generate_bundle(
+  is_write: bool,
+  bundle: &mut Bundle,
+) -> Result<Vec<RolldownOutput>, RolldownError> {
   if is_write {
-    assert!(output_options.dir.is_some() || output_options.file.is_some());
+    assert!(bundle.output_options.dir.is_some() || bundle.output_options.file.is_some());
+  }
+  let mut output = bundle.generate()?;
+  let plugin_driver = &bundle.graph.plugin_driver;
+  plugin_driver.generate_bundle(&bundle.output_options, &mut output, is_write)?;
+  bundle.generate_plugin_assets(&mut output)?;
+
+  if matches!(
+    bundle.output_options.sourcemap,
+    SourceMapMode::File | SourceMapMode::Hidden
+  ) {
+    let map_files = output
+      .iter()
+      .filter_map(|output| match output {
+        RolldownOutput::Chunk(chunk) => chunk.map.as_ref().map(|map| {
+          RolldownOutput::Asset(OutputAsset {
+            file_name: format!("{}.map", chunk.file_name),
+            source: source_map_to_string(map).into_bytes(),
+            name: None,
+          })
+        }),
+        _ => None,
+      })
+      .collect::<Vec<_>>();
+    output.extend(map_files);
   }
-  let mut bundle = Bundle::new(graph, output_options);
-  let output = bundle.generate();
-  let output = output
-    .into_iter()
-    .map(|(_, output_chunk)| RolldownOutput::Chunk(output_chunk))
-    .collect::<Vec<_>>();
 
-  output.iter().for_each(|output| {
-    write_output_file(output, &bundle.output_options);
-  });
+  if is_write {
+    output
+      .iter()
+      .try_for_each(|output| write_output_file(output, &bundle.output_options))?;
+    plugin_driver.write_bundle(&bundle.output_options, &output)?;
+  }
 
-  output
+  Ok(output)
 }
 
-fn write_output_file(output_file: &RolldownOutput, output_options: &NormalizedOutputOptions) {
+fn write_output_file(
+  output_file: &RolldownOutput,
+  output_options: &NormalizedOutputOptions,
+) -> Result<(), RolldownError> {
   let file_name = nodejs_path::resolve!(
     &output_options
       .dir
@@ -81,7 +155,7 @@ fn write_output_file(output_file: &RolldownOutput, output_options: &NormalizedOu
     output_file.get_file_name()
   );
 
-  std::fs::create_dir_all(nodejs_path::dirname(&file_name)).unwrap();
+  std::fs::create_dir_all(nodejs_path::dirname(&file_name)).map_err(RolldownError::IO)?;
   log::info!("file_name {}", file_name);
-  std::fs::write(file_name, output_file.get_content()).unwrap();
+  std::fs::write(file_name, output_file.get_source()).map_err(RolldownError::IO)
 }
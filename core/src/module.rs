@@ -53,6 +53,12 @@ pub struct Module {
   pub namespace: Namespace,
   pub is_user_defined_entry_point: bool,
   pub module_span: Span,
+  // Maps the loaded code back to the original code, such as the map referenced by a `sourceMappingURL` comment.
+  pub input_map: Option<sourcemap::SourceMap>,
+  // Whether statements with side effects are kept when they are unused. Set by the `transform` hook.
+  pub module_side_effects: bool,
+  // Custom data of plugins by plugin name.
+  pub meta: HashMap<String, serde_json::Value>,
   // pub module_item_infos: Vec<ModuleItemInfo>,
 }
 
@@ -73,6 +79,9 @@ impl Module {
       namespace: Default::default(),
       is_user_defined_entry_point: false,
       module_span: Take::dummy(),
+      input_map: None,
+      module_side_effects: true,
+      meta: Default::default(),
     }
   }
 
@@ -105,12 +114,14 @@ impl Module {
       });
   }
 
+  // Returns the entries of `mark_to_stmt` added for the module.
   pub fn set_statements(
     &mut self,
     ast: ast::Module,
     module_item_infos: Vec<ModuleItemInfo>,
     mark_to_stmt: Arc<DashMap<Mark, (SmolStr, usize)>>,
-  ) {
+  ) -> Vec<(Mark, usize)> {
+    let mut marks = vec![];
     self.module_span = ast.span;
     self.statements = ast
       .body
@@ -121,18 +132,20 @@ impl Module {
         let is_decl_or_stmt = is_decl_or_stmt(&node);
         let mut stmt = Statement::new(node);
         if let Some(export_mark) = info.export_mark {
-          mark_to_stmt
-            .entry(export_mark)
-            .or_insert_with(|| (self.id.clone(), idx));
+          mark_to_stmt.entry(export_mark).or_insert_with(|| {
+            marks.push((export_mark, idx));
+            (self.id.clone(), idx)
+          });
         }
         info.declared.iter().for_each(|(name, mark)| {
           self.definitions.insert(name.clone(), idx);
 
           // Skip declarations brought by `import`
           if is_decl_or_stmt {
-            mark_to_stmt
-              .entry(*mark)
-              .or_insert_with(|| (self.id.clone(), idx));
+            mark_to_stmt.entry(*mark).or_insert_with(|| {
+              marks.push((*mark, idx));
+              (self.id.clone(), idx)
+            });
           }
         });
         stmt.writes = info.writes;
@@ -154,6 +167,7 @@ impl Module {
         stmt
       })
       .collect();
+    marks
   }
 
   pub fn include_mark(&mut self, name: &JsWord, mark: &Mark) {
@@ -196,6 +210,11 @@ impl Module {
       .clone()
   }
 
+  #[inline]
+  pub fn is_external(&self, dep_src: &JsWord) -> bool {
+    self.resolved_ids.get(dep_src).unwrap().external
+  }
+
   pub fn resolve_mark(&self, name: &JsWord) -> Mark {
     *self.declared_symbols.get(name).unwrap_or_else(|| {
       self
@@ -373,18 +392,33 @@ pub fn fold_export_decl_to_decl(
       ModuleDecl::ExportAll(export_all) => {
         // keep external module as it (we may use it later on code-gen) and internal modules removed.
         // export * from 'react'
-        if module
-          .resolved_ids
-          .get(&export_all.src.value)
-          .unwrap()
-          .external
-        {
+        if module.is_external(&export_all.src.value) {
           ModuleItem::ModuleDecl(ModuleDecl::ExportAll(export_all))
         } else {
           // remove `export * from './foo'`
           ModuleItem::dummy()
         }
       }
+      // keep `import ... from 'external'` for the finalizer and remove imports of internal modules.
+      ModuleDecl::Import(import_decl) => {
+        if module.is_external(&import_decl.src.value) {
+          ModuleItem::ModuleDecl(ModuleDecl::Import(import_decl))
+        } else {
+          ModuleItem::dummy()
+        }
+      }
+      // keep `export { foo } from 'external'` for the finalizer and remove `export { foo }`.
+      ModuleDecl::ExportNamed(named_export) => {
+        let is_external = named_export
+          .src
+          .as_ref()
+          .map_or(false, |src| module.is_external(&src.value));
+        if is_external {
+          ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(named_export))
+        } else {
+          ModuleItem::dummy()
+        }
+      }
       ModuleDecl::ExportDefaultExpr(export_decl) => {
         // ignore `export default foo`
         if let Expr::Ident(_) = export_decl.expr.as_ref() {
@@ -407,7 +441,6 @@ pub fn fold_export_decl_to_decl(
           })))
         }
       }
-      // remove `export { foo, baz }`
       _ => ModuleItem::dummy(),
     }
   } else {
@@ -0,0 +1,278 @@
+use std::fmt;
+
+use smol_str::SmolStr;
+
+use super::PluginDriver;
+use crate::{
+  structs::EmittedAsset,
+  types::{ModuleInfo, ResolvedId},
+  worker::RolldownError,
+};
+
+pub enum EmittedFile {
+  Asset(EmittedAsset),
+  // The module is bundled as an additional entry. Chunks could only be emitted until modules are loaded.
+  Chunk { id: String },
+}
+
+pub struct ResolveOptions {
+  // Skip the `resolve_id` hook of the plugin calling `resolve`, which prevents infinite loops. Plugins skipped by
+  // the `resolve` call this one is nested in are skipped as well.
+  pub skip_self: bool,
+}
+
+impl Default for ResolveOptions {
+  fn default() -> Self {
+    Self { skip_self: true }
+  }
+}
+
+// Both are 1-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+  pub line: usize,
+  pub column: usize,
+}
+
+impl Position {
+  /// Position of the byte `offset` in `code`, such as the code passed to `transform`.
+  pub fn from_offset(code: &str, offset: usize) -> Self {
+    let mut offset = offset.min(code.len());
+    while !code.is_char_boundary(offset) {
+      offset -= 1;
+    }
+    let before = &code[..offset];
+    let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+    Self {
+      line: before.matches('\n').count() + 1,
+      column: before[line_start..].chars().count() + 1,
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct PluginWarning {
+  pub plugin: String,
+  pub message: String,
+  pub id: Option<String>,
+  pub pos: Option<Position>,
+}
+
+impl fmt::Display for PluginWarning {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "[plugin {}] {}{}",
+      self.plugin,
+      location(&self.id, &self.pos),
+      self.message
+    )
+  }
+}
+
+// `id:line:column: `
+pub(crate) fn location(id: &Option<String>, pos: &Option<Position>) -> String {
+  match (id, pos) {
+    (Some(id), Some(pos)) => format!("{}:{}:{}: ", id, pos.line, pos.column),
+    (Some(id), None) => format!("{}: ", id),
+    (None, Some(pos)) => format!("{}:{}: ", pos.line, pos.column),
+    (None, None) => Default::default(),
+  }
+}
+
+/// Passed to every hook to call back into the build.
+pub struct PluginContext<'a> {
+  pub(crate) driver: &'a PluginDriver,
+  // Index of the plugin whose hook is called.
+  pub(crate) index: usize,
+  // Plugins skipped by the `resolve` call running the `resolve_id` hook.
+  pub(crate) skipped: &'a [usize],
+}
+
+impl<'a> PluginContext<'a> {
+  fn plugin_name(&self) -> &'static str {
+    self.driver.plugins[self.index].get_name()
+  }
+
+  /// Resolve `source` like an import of `importer`, with the `resolve_id` hooks of plugins and the `external` option.
+  pub fn resolve(
+    &self,
+    source: &str,
+    importer: Option<&str>,
+    options: ResolveOptions,
+  ) -> Result<ResolvedId, RolldownError> {
+    let mut skipped = self.skipped.to_vec();
+    if options.skip_self && !skipped.contains(&self.index) {
+      skipped.push(self.index);
+    }
+    self.driver.resolve(source, importer, &skipped)
+  }
+
+  /// Load `resolved_id` with the `load` and `transform` hooks, parse it and add it to the build, like `this.load` of
+  /// Rollup. The module is only bundled if it's imported. Its dependencies are loaded as well, so they might be
+  /// missing from `ModuleInfo::importers` until the build ends. Modules could only be loaded until they are all
+  /// loaded, and not by `module_parsed`.
+  pub fn load(&self, resolved_id: ResolvedId) -> Result<ModuleInfo, RolldownError> {
+    if resolved_id.external {
+      return Err(self.error(
+        format!(
+          "Module \"{}\" could not be loaded as it's external",
+          resolved_id.id
+        ),
+        None,
+        None,
+      ));
+    }
+    let (mut worker, errors) = match self.driver.loader.lock().unwrap().as_ref() {
+      Some(loader) if loader.thread != std::thread::current().id() => {
+        (loader.worker.clone(), loader.errors.clone())
+      }
+      Some(_) => {
+        return Err(self.error(
+          format!(
+            "Module \"{}\" could not be loaded by \"module_parsed\"",
+            resolved_id.id
+          ),
+          None,
+          None,
+        ))
+      }
+      None => {
+        return Err(self.error(
+          format!(
+            "Module \"{}\" could only be loaded while modules are loaded",
+            resolved_id.id
+          ),
+          None,
+          None,
+        ))
+      }
+    };
+    let id = resolved_id.id.clone();
+    worker.job_queue.push(resolved_id);
+    loop {
+      if let Some(info) = self.get_module_info(&id) {
+        return Ok(info);
+      }
+      if !errors.lock().unwrap().is_empty() {
+        return Err(self.error(format!("Module \"{}\" could not be loaded", id), None, None));
+      }
+      // Jobs are run on this thread as well, so the module is loaded even if every worker is waiting.
+      worker.run()?;
+    }
+  }
+
+  /// Modules are added as they are parsed, and the information is complete once the build ends.
+  pub fn get_module_info(&self, id: &str) -> Option<ModuleInfo> {
+    self.driver.module_infos.read().unwrap().get(id).cloned()
+  }
+
+  pub fn get_module_ids(&self) -> Vec<SmolStr> {
+    self
+      .driver
+      .module_infos
+      .read()
+      .unwrap()
+      .keys()
+      .cloned()
+      .collect()
+  }
+
+  /// Returns the reference id of the file, which `get_file_name` takes.
+  pub fn emit_file(&self, file: EmittedFile) -> Result<String, RolldownError> {
+    let reference_id = self.driver.next_reference_id();
+    match file {
+      EmittedFile::Asset(asset) => {
+        if let Some(file_name) = &asset.file_name {
+          self
+            .driver
+            .file_names
+            .write()
+            .unwrap()
+            .insert(reference_id.clone(), file_name.clone());
+        }
+        self
+          .driver
+          .emitted_assets
+          .lock()
+          .unwrap()
+          .push((reference_id.clone(), asset));
+      }
+      EmittedFile::Chunk { id } => {
+        let resolved_id = self.driver.resolve(&id, None, &[])?;
+        let id = resolved_id.id.clone();
+        self
+          .driver
+          .emit_chunk(resolved_id)
+          .map_err(|message| self.error(message, None, None))?;
+        self
+          .driver
+          .chunk_references
+          .lock()
+          .unwrap()
+          .insert(reference_id.clone(), id);
+      }
+    }
+    Ok(reference_id)
+  }
+
+  /// File name of a file emitted by `emit_file`. It's known once the output is generated, such as in the
+  /// `generate_bundle` hook, or at once for assets with a `file_name`.
+  pub fn get_file_name(&self, reference_id: &str) -> Result<String, RolldownError> {
+    self
+      .driver
+      .file_names
+      .read()
+      .unwrap()
+      .get(reference_id)
+      .cloned()
+      .ok_or_else(|| {
+        self.error(
+          format!(
+            "Unable to get the file name of \"{}\", as it's unknown or the output is not generated yet",
+            reference_id
+          ),
+          None,
+          None,
+        )
+      })
+  }
+
+  /// Rebuild in watch mode when the file changes.
+  pub fn add_watch_file(&self, id: impl Into<String>) {
+    let id = id.into();
+    let mut watch_files = self.driver.watch_files.lock().unwrap();
+    if !watch_files.contains(&id) {
+      watch_files.push(id);
+    }
+  }
+
+  /// Report a warning with the `on_warn` option, without failing the build.
+  pub fn warn(&self, message: impl Into<String>, id: Option<&str>, pos: Option<Position>) {
+    let warning = PluginWarning {
+      plugin: self.plugin_name().to_string(),
+      message: message.into(),
+      id: id.map(|id| id.to_string()),
+      pos,
+    };
+    match self.driver.on_warn.read().unwrap().as_ref() {
+      Some(on_warn) => on_warn(&warning),
+      None => log::warn!("{}", warning),
+    }
+  }
+
+  /// The error to return from the hook, which fails the build.
+  pub fn error(
+    &self,
+    message: impl Into<String>,
+    id: Option<&str>,
+    pos: Option<Position>,
+  ) -> RolldownError {
+    RolldownError::Plugin {
+      plugin: self.plugin_name().to_string(),
+      message: message.into(),
+      id: id.map(|id| id.to_string()),
+      pos,
+    }
+  }
+}
@@ -1,72 +1,622 @@
-use std::sync::Mutex;
+use std::{
+  collections::HashMap,
+  fs,
+  sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, Mutex, RwLock,
+  },
+  thread::ThreadId,
+};
 
-use crate::types::ResolveIdResult;
+use rayon::prelude::*;
+use smol_str::SmolStr;
+use sourcemap::SourceMap;
 
+use crate::{
+  structs::{ChunkInfo, EmittedAsset, RolldownOutput},
+  types::{
+    IsExternal, ModuleInfo, NormalizedInputOptions, NormalizedOutputOptions, OnWarn,
+    ResolveIdResult, ResolvedId,
+  },
+  utils::{
+    resolve_id,
+    source_map::{collapse_source_map, read_input_source_map},
+  },
+  worker::{RolldownError, Worker},
+};
+
+mod context;
+pub use context::*;
+
+#[derive(Debug, Clone)]
+pub struct SourceDescription {
+  pub code: String,
+  // Maps `code` back to the code it was generated from.
+  pub map: Option<SourceMap>,
+}
+
+impl From<String> for SourceDescription {
+  fn from(code: String) -> Self {
+    Self { code, map: None }
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TransformResult {
+  pub code: String,
+  // Maps `code` back to the code passed to the hook.
+  pub map: Option<SourceMap>,
+  // `Some(false)` lets statements of the module be removed by tree-shaking even if they have side effects.
+  pub module_side_effects: Option<bool>,
+  // Custom data of the module by plugin name, merged into the data returned by previous plugins.
+  pub meta: Option<HashMap<String, serde_json::Value>>,
+}
+
+impl From<String> for TransformResult {
+  fn from(code: String) -> Self {
+    Self {
+      code,
+      ..Default::default()
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeEvent {
+  Create,
+  Update,
+  Delete,
+}
+
+// State shared with `PluginContext`. A watcher passes its driver to every graph it builds, so hooks called between
+// builds, such as `watch_change`, see the latest build.
 #[derive(Default)]
 pub struct PluginDriver {
-  pub plugins: Mutex<Vec<Box<dyn Plugin + Send>>>,
+  pub plugins: Vec<Arc<dyn Plugin>>,
+  // Set by the graph after the `options` hook.
+  pub(crate) external: RwLock<Option<IsExternal>>,
+  // Updated by the graph as modules are parsed, and for all modules once the build ends.
+  pub(crate) module_infos: RwLock<HashMap<SmolStr, ModuleInfo>>,
+  // Chunks could only be emitted until modules are loaded.
+  pub(crate) is_module_loaded: AtomicBool,
+  pub(crate) emitted_chunks: Mutex<Vec<ResolvedId>>,
+  // Assets by reference id.
+  pub(crate) emitted_assets: Mutex<Vec<(String, EmittedAsset)>>,
+  // Module ids of emitted chunks by reference id.
+  pub(crate) chunk_references: Mutex<HashMap<String, SmolStr>>,
+  // File names of emitted files by reference id. Assets named by the `file_name` option are known once emitted,
+  // and other files once the output is generated.
+  pub(crate) file_names: RwLock<HashMap<String, String>>,
+  reference_count: AtomicUsize,
+  pub(crate) watch_files: Mutex<Vec<String>>,
+  // Set by the graph after the `options` hook.
+  pub(crate) on_warn: RwLock<Option<OnWarn>>,
+  // Set by the graph while modules are loaded.
+  pub(crate) loader: Mutex<Option<Loader>>,
+}
+
+// Lets `PluginContext::load` add modules to the build.
+pub(crate) struct Loader {
+  pub worker: Worker,
+  pub errors: Arc<Mutex<Vec<RolldownError>>>,
+  // The thread receiving parsed modules, which can't wait for them.
+  pub thread: ThreadId,
 }
 
 impl PluginDriver {
-  pub fn new() -> Self {
+  pub fn new(plugins: Vec<Arc<dyn Plugin>>) -> Self {
     Self {
-      plugins: Default::default(),
+      plugins,
+      ..Default::default()
+    }
+  }
+
+  #[inline]
+  fn context(&self, index: usize) -> PluginContext {
+    PluginContext {
+      driver: self,
+      index,
+      skipped: &[],
     }
   }
+
+  /// Resolve `source` with the `resolve_id` hooks of plugins other than `skipped`, falling back to the default
+  /// resolution. Sources and ids matching the `external` option are external.
+  pub fn resolve(
+    &self,
+    source: &str,
+    importer: Option<&str>,
+    skipped: &[usize],
+  ) -> Result<ResolvedId, RolldownError> {
+    if self.is_external(source, importer, false) {
+      return Ok(ResolvedId::new(source.to_string().into(), true));
+    }
+    let resolved_id = match self.resolve_id_skipping(source, importer, skipped)? {
+      Some(resolved_id) => resolved_id,
+      None => resolve_id(source, importer, false),
+    };
+    Ok(self.with_external(resolved_id, importer))
+  }
+
+  /// Resolve the specifier of `import()` with the `resolve_dynamic_import` hooks, falling back to `resolve`. Ids
+  /// returned by the hooks are checked against the `external` option as well.
+  pub fn resolve_dynamic(
+    &self,
+    specifier: &str,
+    importer: &str,
+  ) -> Result<ResolvedId, RolldownError> {
+    match self.resolve_dynamic_import(specifier, importer)? {
+      Some(resolved_id) => Ok(self.with_external(resolved_id, Some(importer))),
+      None => self.resolve(specifier, Some(importer), &[]),
+    }
+  }
+
+  // Entries are never external.
+  fn is_external(&self, id: &str, importer: Option<&str>, is_resolved: bool) -> bool {
+    importer.is_some()
+      && self
+        .external
+        .read()
+        .unwrap()
+        .as_ref()
+        .map_or(false, |external| external(id, importer, is_resolved))
+  }
+
+  fn with_external(&self, resolved_id: ResolvedId, importer: Option<&str>) -> ResolvedId {
+    if !resolved_id.external && self.is_external(&resolved_id.id, importer, true) {
+      ResolvedId::new(resolved_id.id, true)
+    } else {
+      resolved_id
+    }
+  }
+
+  /// Load a module with the `load` hook or from the file system, and pass it through the `transform` hook.
+  pub fn load_module(&self, id: &str) -> Result<TransformResult, RolldownError> {
+    let source = match self.load(id)? {
+      Some(source) => source,
+      None => {
+        let code = fs::read_to_string(id).map_err(RolldownError::IO)?;
+        let map = read_input_source_map(id, &code);
+        SourceDescription { code, map }
+      }
+    };
+    self.transform(source, id)
+  }
+
+  // Modules and chunks of the previous build are dropped. Assets and watch files added between builds are kept, as
+  // the graph takes them once the build ends.
+  pub(crate) fn start_build(&self) {
+    self.module_infos.write().unwrap().clear();
+    self.is_module_loaded.store(false, Ordering::SeqCst);
+    self.emitted_chunks.lock().unwrap().clear();
+    self.chunk_references.lock().unwrap().clear();
+  }
+
+  pub(crate) fn next_reference_id(&self) -> String {
+    format!("{:x}", self.reference_count.fetch_add(1, Ordering::SeqCst))
+  }
+
+  pub(crate) fn emit_chunk(&self, resolved_id: ResolvedId) -> Result<(), String> {
+    if resolved_id.external {
+      return Err(format!(
+        "Chunk \"{}\" could not be emitted as it's external",
+        resolved_id.id
+      ));
+    }
+    if self.is_module_loaded.load(Ordering::SeqCst) {
+      return Err(format!(
+        "Chunk \"{}\" could not be emitted after modules are loaded",
+        resolved_id.id
+      ));
+    }
+    self.emitted_chunks.lock().unwrap().push(resolved_id);
+    Ok(())
+  }
 }
 
 // Align to https://rollupjs.org/guide/en/#build-hooks
+// Sequential hooks run one plugin after another. Parallel hooks run every plugin at the same time.
+// First hooks run until a plugin returns a result.
 
 impl PluginDriver {
-  #[inline]
-  pub fn resolve_id(&self, source: &str, importer: Option<&str>) -> ResolveIdResult {
-    let result = self
+  pub fn options(&self, options: &mut NormalizedInputOptions) -> Result<(), RolldownError> {
+    self
       .plugins
-      .lock()
-      .unwrap()
-      .iter_mut()
-      .map(|plugin| plugin.resolve_id(source, importer))
-      .take_while(|result| result.is_some())
-      .map(|r| r.unwrap())
-      .next();
+      .iter()
+      .enumerate()
+      .try_for_each(|(index, plugin)| plugin.options(&self.context(index), options))
+  }
 
-    result
+  pub fn build_start(&self, options: &NormalizedInputOptions) -> Result<(), RolldownError> {
+    self
+      .plugins
+      .par_iter()
+      .enumerate()
+      .try_for_each(|(index, plugin)| plugin.build_start(&self.context(index), options))
   }
 
-  #[inline]
-  pub fn load(&self, id: &str) -> Option<String> {
-    let result = self
+  pub fn resolve_dynamic_import(
+    &self,
+    specifier: &str,
+    importer: &str,
+  ) -> Result<ResolveIdResult, RolldownError> {
+    for (index, plugin) in self.plugins.iter().enumerate() {
+      if let Some(resolved_id) =
+        plugin.resolve_dynamic_import(&self.context(index), specifier, importer)?
+      {
+        return Ok(Some(resolved_id));
+      }
+    }
+    Ok(None)
+  }
+
+  pub fn module_parsed(&self, info: &ModuleInfo) -> Result<(), RolldownError> {
+    self
       .plugins
-      .lock()
-      .unwrap()
-      .iter_mut()
-      .map(|plugin| plugin.load(id))
-      .take_while(|result| result.is_some())
-      .map(|r| r.unwrap())
-      .next();
+      .par_iter()
+      .enumerate()
+      .try_for_each(|(index, plugin)| plugin.module_parsed(&self.context(index), info))
+  }
 
-    result
+  pub fn build_end(&self, error: Option<&RolldownError>) -> Result<(), RolldownError> {
+    self
+      .plugins
+      .par_iter()
+      .enumerate()
+      .try_for_each(|(index, plugin)| plugin.build_end(&self.context(index), error))
   }
 
-  pub fn transform(&self, _code: String, _id: &str) -> Option<String> {
-    None
+  pub fn render_start(
+    &self,
+    output_options: &NormalizedOutputOptions,
+    input_options: &NormalizedInputOptions,
+  ) -> Result<(), RolldownError> {
+    self
+      .plugins
+      .par_iter()
+      .enumerate()
+      .try_for_each(|(index, plugin)| {
+        plugin.render_start(&self.context(index), output_options, input_options)
+      })
+  }
+
+  /// Pass the rendered code through the `render_chunk` hook of every plugin in order. `map` is `None` if source maps
+  /// are disabled, and maps of the plugins are ignored then.
+  pub fn render_chunk(
+    &self,
+    source: SourceDescription,
+    chunk: &ChunkInfo,
+    options: &NormalizedOutputOptions,
+  ) -> Result<SourceDescription, RolldownError> {
+    self
+      .plugins
+      .iter()
+      .enumerate()
+      .try_fold(source, |source, (index, plugin)| {
+        Ok(
+          match plugin.render_chunk(&self.context(index), &source.code, chunk, options)? {
+            Some(result) => SourceDescription {
+              code: result.code,
+              map: source
+                .map
+                .map(|prev| chain_source_maps(result.map, Some(prev)).unwrap()),
+            },
+            None => source,
+          },
+        )
+      })
+  }
+
+  // Strings of plugins are joined, so the hash changes if any of them changes.
+  pub fn augment_chunk_hash(&self, chunk: &ChunkInfo) -> String {
+    self
+      .plugins
+      .iter()
+      .enumerate()
+      .filter_map(|(index, plugin)| plugin.augment_chunk_hash(&self.context(index), chunk))
+      .collect::<Vec<_>>()
+      .join("\0")
+  }
+
+  pub fn generate_bundle(
+    &self,
+    options: &NormalizedOutputOptions,
+    bundle: &mut Vec<RolldownOutput>,
+    is_write: bool,
+  ) -> Result<(), RolldownError> {
+    self
+      .plugins
+      .iter()
+      .enumerate()
+      .try_for_each(|(index, plugin)| {
+        plugin.generate_bundle(&self.context(index), options, bundle, is_write)
+      })
+  }
+
+  pub fn write_bundle(
+    &self,
+    options: &NormalizedOutputOptions,
+    bundle: &[RolldownOutput],
+  ) -> Result<(), RolldownError> {
+    self
+      .plugins
+      .par_iter()
+      .enumerate()
+      .try_for_each(|(index, plugin)| plugin.write_bundle(&self.context(index), options, bundle))
+  }
+
+  pub fn watch_change(&self, id: &str, event: ChangeEvent) -> Result<(), RolldownError> {
+    self
+      .plugins
+      .iter()
+      .enumerate()
+      .try_for_each(|(index, plugin)| plugin.watch_change(&self.context(index), id, event))
+  }
+
+  pub fn close_watcher(&self) -> Result<(), RolldownError> {
+    self
+      .plugins
+      .par_iter()
+      .enumerate()
+      .try_for_each(|(index, plugin)| plugin.close_watcher(&self.context(index)))
+  }
+
+  // The first plugin returning a result wins.
+  #[inline]
+  pub fn resolve_id(
+    &self,
+    source: &str,
+    importer: Option<&str>,
+  ) -> Result<ResolveIdResult, RolldownError> {
+    self.resolve_id_skipping(source, importer, &[])
+  }
+
+  // Hooks calling `PluginContext::resolve` are given `skipped`, so plugins skipped by an outer call stay skipped.
+  fn resolve_id_skipping(
+    &self,
+    source: &str,
+    importer: Option<&str>,
+    skipped: &[usize],
+  ) -> Result<ResolveIdResult, RolldownError> {
+    for (index, plugin) in self.plugins.iter().enumerate() {
+      if skipped.contains(&index) {
+        continue;
+      }
+      let ctx = PluginContext {
+        driver: self,
+        index,
+        skipped,
+      };
+      if let Some(resolved_id) = plugin.resolve_id(&ctx, source, importer)? {
+        return Ok(Some(resolved_id));
+      }
+    }
+    Ok(None)
+  }
+
+  pub fn load(&self, id: &str) -> Result<Option<SourceDescription>, RolldownError> {
+    for (index, plugin) in self.plugins.iter().enumerate() {
+      if let Some(source) = plugin.load(&self.context(index), id)? {
+        return Ok(Some(source));
+      }
+    }
+    Ok(None)
+  }
+
+  /// Pass the code through the `transform` hook of every plugin in order. Maps of the plugins are collapsed into one,
+  /// which maps the result back to `source.code`.
+  pub fn transform(
+    &self,
+    source: SourceDescription,
+    id: &str,
+  ) -> Result<TransformResult, RolldownError> {
+    let SourceDescription { code, map } = source;
+    let mut output = TransformResult {
+      code,
+      map,
+      ..Default::default()
+    };
+    for (index, plugin) in self.plugins.iter().enumerate() {
+      if let Some(result) = plugin.transform(&self.context(index), &output.code, id)? {
+        output.map = chain_source_maps(result.map, output.map.take());
+        output.code = result.code;
+        if result.module_side_effects.is_some() {
+          output.module_side_effects = result.module_side_effects;
+        }
+        if let Some(meta) = result.meta {
+          output
+            .meta
+            .get_or_insert_with(Default::default)
+            .extend(meta);
+        }
+      }
+    }
+    Ok(output)
   }
 }
 
-pub trait Plugin {
+// `map` maps the code generated by a hook back to its input, which is mapped back to the original code by `prev`.
+fn chain_source_maps(map: Option<SourceMap>, prev: Option<SourceMap>) -> Option<SourceMap> {
+  match (map, prev) {
+    (Some(map), Some(prev)) => {
+      let mut chained =
+        collapse_source_map(&map, |_| Some(&prev), |source| source.to_string(), false);
+      chained.set_file(prev.get_file());
+      Some(chained)
+    }
+    // Without a map, the hook is assumed to keep the positions of the code.
+    (map, prev) => map.or(prev),
+  }
+}
+
+// Hooks are called from multiple threads at the same time, so plugins keep their state behind locks or atomics.
+// `PluginContext` calls back into the build, e.g. `ctx.resolve` runs the `resolve_id` hook of other plugins.
+pub trait Plugin: Send + Sync {
   // Align to https://rollupjs.org/guide/en/#build-hooks
 
   fn get_name(&self) -> &'static str;
 
+  /// Entries of the disk cache are reused only if the version is the same, as the plugin might transform
+  /// modules differently. Plugins without a version are told apart by the name only.
   #[inline]
-  fn resolve_id(&mut self, _source: &str, _importer: Option<&str>) -> ResolveIdResult {
-    //  async, first
+  fn get_version(&self) -> Option<&'static str> {
     None
   }
 
+  /// Replace options of the build before it starts. It's called for each rebuild in watch mode.
+  #[inline]
+  fn options(
+    &self,
+    _ctx: &PluginContext,
+    _options: &mut NormalizedInputOptions,
+  ) -> Result<(), RolldownError> {
+    // async, sequential
+    Ok(())
+  }
+
+  #[inline]
+  fn build_start(
+    &self,
+    _ctx: &PluginContext,
+    _options: &NormalizedInputOptions,
+  ) -> Result<(), RolldownError> {
+    // async, parallel
+    Ok(())
+  }
+
+  #[inline]
+  fn resolve_id(
+    &self,
+    _ctx: &PluginContext,
+    _source: &str,
+    _importer: Option<&str>,
+  ) -> Result<ResolveIdResult, RolldownError> {
+    //  async, first
+    Ok(None)
+  }
+
+  #[inline]
+  fn load(
+    &self,
+    _ctx: &PluginContext,
+    _id: &str,
+  ) -> Result<Option<SourceDescription>, RolldownError> {
+    // async, first
+    Ok(None)
+  }
+
+  #[inline]
+  fn transform(
+    &self,
+    _ctx: &PluginContext,
+    _code: &str,
+    _id: &str,
+  ) -> Result<Option<TransformResult>, RolldownError> {
+    // async, sequential
+    Ok(None)
+  }
+
+  /// Called for every module once it's parsed or taken from the cache, with the imports known so far.
+  #[inline]
+  fn module_parsed(&self, _ctx: &PluginContext, _info: &ModuleInfo) -> Result<(), RolldownError> {
+    // async, parallel
+    Ok(())
+  }
+
+  /// Resolve `import(specifier)` before `resolve_id`.
   #[inline]
-  fn load(&mut self, _id: &str) -> Option<String> {
+  fn resolve_dynamic_import(
+    &self,
+    _ctx: &PluginContext,
+    _specifier: &str,
+    _importer: &str,
+  ) -> Result<ResolveIdResult, RolldownError> {
     // async, first
+    Ok(None)
+  }
+
+  /// Called when the module graph is built, or the build failed with `error`.
+  #[inline]
+  fn build_end(
+    &self,
+    _ctx: &PluginContext,
+    _error: Option<&RolldownError>,
+  ) -> Result<(), RolldownError> {
+    // async, parallel
+    Ok(())
+  }
+
+  #[inline]
+  fn render_start(
+    &self,
+    _ctx: &PluginContext,
+    _output_options: &NormalizedOutputOptions,
+    _input_options: &NormalizedInputOptions,
+  ) -> Result<(), RolldownError> {
+    // async, parallel
+    Ok(())
+  }
+
+  /// Replace the rendered code of a chunk. Hashes in file names are not resolved yet.
+  #[inline]
+  fn render_chunk(
+    &self,
+    _ctx: &PluginContext,
+    _code: &str,
+    _chunk: &ChunkInfo,
+    _options: &NormalizedOutputOptions,
+  ) -> Result<Option<SourceDescription>, RolldownError> {
+    // async, sequential
+    Ok(None)
+  }
+
+  /// Add a string to the `[hash]` of the chunk, for changes that are not visible in its code.
+  #[inline]
+  fn augment_chunk_hash(&self, _ctx: &PluginContext, _chunk: &ChunkInfo) -> Option<String> {
+    // sync, sequential
     None
   }
+
+  /// Add, modify or delete outputs before they are written.
+  #[inline]
+  fn generate_bundle(
+    &self,
+    _ctx: &PluginContext,
+    _options: &NormalizedOutputOptions,
+    _bundle: &mut Vec<RolldownOutput>,
+    _is_write: bool,
+  ) -> Result<(), RolldownError> {
+    // async, sequential
+    Ok(())
+  }
+
+  /// Called after the outputs are written.
+  #[inline]
+  fn write_bundle(
+    &self,
+    _ctx: &PluginContext,
+    _options: &NormalizedOutputOptions,
+    _bundle: &[RolldownOutput],
+  ) -> Result<(), RolldownError> {
+    // async, parallel
+    Ok(())
+  }
+
+  /// Called in watch mode for each changed file before the rebuild.
+  #[inline]
+  fn watch_change(
+    &self,
+    _ctx: &PluginContext,
+    _id: &str,
+    _event: ChangeEvent,
+  ) -> Result<(), RolldownError> {
+    // async, sequential
+    Ok(())
+  }
+
+  #[inline]
+  fn close_watcher(&self, _ctx: &PluginContext) -> Result<(), RolldownError> {
+    // async, parallel
+    Ok(())
+  }
 }
@@ -2,7 +2,13 @@ use std::{collections::HashSet, path::Path};
 
 use once_cell::sync::Lazy;
 
-use crate::{ext::StrExt, plugin_driver::Plugin, types::ResolvedId, utils::is_external_module};
+use crate::{
+  ext::StrExt,
+  plugin_driver::{Plugin, PluginContext},
+  types::{ResolveIdResult, ResolvedId},
+  utils::is_external_module,
+  worker::RolldownError,
+};
 
 struct NodeResolver {}
 
@@ -11,7 +17,12 @@ impl Plugin for NodeResolver {
     "node-resolve"
   }
 
-  fn resolve_id(&mut self, source: &str, importer: Option<&str>) -> crate::types::ResolveIdResult {
+  fn resolve_id(
+    &self,
+    ctx: &PluginContext,
+    source: &str,
+    importer: Option<&str>,
+  ) -> Result<ResolveIdResult, RolldownError> {
     if importer.is_some() && is_external_module(source) {
       let result = {
         let normalized_source = source.replace("node:", "");
@@ -27,13 +38,19 @@ impl Plugin for NodeResolver {
               // External should be judged based on `external options`
               ResolvedId::new(file.to_string_lossy().to_string(), false)
             }
-            Err(_) => panic!("Module {} is not exist.", normalized_source),
+            Err(_) => {
+              return Err(ctx.error(
+                format!("Module {} is not exist.", normalized_source),
+                importer,
+                None,
+              ))
+            }
           }
         }
       };
-      Some(result)
+      Ok(Some(result))
     } else {
-      None
+      Ok(None)
     }
   }
 }
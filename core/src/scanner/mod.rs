@@ -64,6 +64,8 @@ pub struct Scanner {
   pub dynamic_imports: HashSet<DynImportDesc>,
   pub symbol_box: Arc<Mutex<SymbolBox>>,
   pub tx: Sender<Msg>,
+  // Unsupported syntax found while scanning, which fails the module.
+  pub errors: Vec<RolldownError>,
 }
 
 impl Scanner {
@@ -83,6 +85,7 @@ impl Scanner {
       ident_type: IdentType::Ref,
       symbol_box,
       tx,
+      errors: Default::default(),
     }
   }
 
@@ -203,9 +206,8 @@ impl VisitMut for Scanner {
   }
 
   fn visit_mut_module_decl(&mut self, node: &mut ModuleDecl) {
-    self.add_import(node);
-    if let Err(e) = self.add_export(node) {
-      eprintln!("{}", e);
+    if let Err(err) = self.add_import(node).and_then(|_| self.add_export(node)) {
+      self.errors.push(err);
     }
 
     node.visit_mut_children_with(self);
@@ -1,12 +1,12 @@
 use std::collections::HashSet;
 
 use swc_atoms::JsWord;
-use swc_common::Mark;
+use swc_common::{FileName, Mark};
 use swc_ecma_ast::{
   CallExpr, Callee, Decl, DefaultDecl, ExportSpecifier, Expr, Lit, ModuleDecl, ModuleExportName,
 };
 
-use crate::{ext::SyntaxContextExt, graph::Rel, worker::RolldownError};
+use crate::{compiler::SOURCE_MAP, ext::SyntaxContextExt, graph::Rel, worker::RolldownError};
 
 use super::{helper::collect_js_word_of_pat, Scanner};
 
@@ -35,7 +35,7 @@ pub struct DynImportDesc {
 }
 
 impl Scanner {
-  pub fn add_import(&mut self, module_decl: &mut ModuleDecl) {
+  pub fn add_import(&mut self, module_decl: &mut ModuleDecl) -> Result<(), RolldownError> {
     if let ModuleDecl::Import(import_decl) = module_decl {
       let source = &import_decl.src.value;
       let import_info = self.import_infos.entry(source.clone()).or_insert_with(|| {
@@ -45,47 +45,46 @@ impl Scanner {
       });
 
       // We separate each specifier to support later tree-shaking.
-      import_decl.specifiers.iter_mut().for_each(|specifier| {
-        let used;
-        let original;
-        let mark;
-        match specifier {
-          // import foo from './foo'
-          swc_ecma_ast::ImportSpecifier::Default(n) => {
-            used = n.local.sym.clone();
-            original = "default".into();
-            mark = n.local.span.ctxt.as_mark();
-          }
-          // import { foo } from './foo'
-          // import { foo as foo2 } from './foo'
-          swc_ecma_ast::ImportSpecifier::Named(n) => {
-            used = n.local.sym.clone();
-            original = n
-              .imported // => foo2 in `import { foo as foo2 } from './foo'`
-              .as_ref()
-              .map_or(used.clone(), |module_export_name| {
-                if let ModuleExportName::Ident(ident) = module_export_name {
-                  ident.sym.clone()
-                } else {
-                  panic!("")
-                }
-              });
-            mark = n.local.span.ctxt.as_mark();
-          }
-          // import * as foo from './foo'
-          swc_ecma_ast::ImportSpecifier::Namespace(n) => {
-            used = n.local.sym.clone();
-            original = "*".into();
-            mark = n.local.span.ctxt.as_mark();
+      import_decl
+        .specifiers
+        .iter_mut()
+        .try_for_each(|specifier| {
+          let used;
+          let original;
+          let mark;
+          match specifier {
+            // import foo from './foo'
+            swc_ecma_ast::ImportSpecifier::Default(n) => {
+              used = n.local.sym.clone();
+              original = "default".into();
+              mark = n.local.span.ctxt.as_mark();
+            }
+            // import { foo } from './foo'
+            // import { foo as foo2 } from './foo'
+            swc_ecma_ast::ImportSpecifier::Named(n) => {
+              used = n.local.sym.clone();
+              original = n
+                .imported // => foo2 in `import { foo as foo2 } from './foo'`
+                .as_ref()
+                .map_or_else(|| Ok(used.clone()), get_sym_from_module_export)?;
+              mark = n.local.span.ctxt.as_mark();
+            }
+            // import * as foo from './foo'
+            swc_ecma_ast::ImportSpecifier::Namespace(n) => {
+              used = n.local.sym.clone();
+              original = "*".into();
+              mark = n.local.span.ctxt.as_mark();
+            }
           }
-        }
-        import_info.names.insert(Specifier {
-          original,
-          used,
-          mark,
-        });
-      });
+          import_info.names.insert(Specifier {
+            original,
+            used,
+            mark,
+          });
+          Ok::<(), RolldownError>(())
+        })?;
     }
+    Ok(())
   }
 
   pub fn add_dynamic_import(&mut self, call_exp: &CallExpr) {
@@ -108,7 +107,7 @@ impl Scanner {
     }
   }
 
-  pub fn add_export(&mut self, module_decl: &ModuleDecl) -> Result<(), anyhow::Error> {
+  pub fn add_export(&mut self, module_decl: &ModuleDecl) -> Result<(), RolldownError> {
     match module_decl {
       ModuleDecl::ExportDefaultDecl(node) => {
         let identifier = match &node.decl {
@@ -173,19 +172,17 @@ impl Scanner {
                     });
                 // export { name } from './other'
                 let source = source_node.value.clone();
-                let name = s
-                  .exported
-                  .as_ref()
-                  .map_or(get_sym_from_module_export(&s.orig), |id| {
-                    get_sym_from_module_export(id)
-                  });
+                let name = s.exported.as_ref().map_or_else(
+                  || get_sym_from_module_export(&s.orig),
+                  get_sym_from_module_export,
+                )?;
                 let re_export_mark = self
                   .symbol_box
                   .lock()
                   .map_err(|_| RolldownError::Lock)?
                   .new_mark();
                 re_export_info.names.insert(Specifier {
-                  original: get_sym_from_module_export(&s.orig),
+                  original: get_sym_from_module_export(&s.orig)?,
                   used: name.clone(),
                   mark: re_export_mark,
                 });
@@ -193,7 +190,7 @@ impl Scanner {
                 self.re_exports.insert(
                   name.clone(),
                   ReExportDesc {
-                    local_name: get_sym_from_module_export(&s.orig),
+                    local_name: get_sym_from_module_export(&s.orig)?,
                     source,
                     original: name,
                     mark: re_export_mark,
@@ -202,13 +199,11 @@ impl Scanner {
               } else {
                 // export { foo, bar, baz }
                 log::debug!("export var {:#?}", s);
-                let local_name = get_sym_from_module_export(&s.orig);
-                let exported_name: JsWord = s
-                  .exported
-                  .as_ref()
-                  .map_or(get_sym_from_module_export(&s.orig), |id| {
-                    get_sym_from_module_export(id)
-                  });
+                let local_name = get_sym_from_module_export(&s.orig)?;
+                let exported_name: JsWord = s.exported.as_ref().map_or_else(
+                  || get_sym_from_module_export(&s.orig),
+                  get_sym_from_module_export,
+                )?;
 
                 let mark = self.symbol_box.lock().unwrap().new_mark();
                 self.statement_infos[self.cur_stmt_index].export_mark = Some(mark);
@@ -245,11 +240,11 @@ impl Scanner {
 
               re_export_info.names.insert(Specifier {
                 original: "*".into(),
-                used: get_sym_from_module_export(&s.name),
+                used: get_sym_from_module_export(&s.name)?,
                 mark: re_export_mark,
               });
               // export * as name from './other'
-              let name = get_sym_from_module_export(&s.name);
+              let name = get_sym_from_module_export(&s.name)?;
               self.statement_infos[self.cur_stmt_index].export_mark = Some(re_export_mark);
               self.re_exports.insert(
                 name.clone(),
@@ -383,9 +378,24 @@ impl RelationInfo {
 }
 
 #[inline]
-fn get_sym_from_module_export(module_export_name: &ModuleExportName) -> JsWord {
+// String names such as `export { foo as "a-b" }` are valid since ES2022, but not supported yet.
+pub fn get_sym_from_module_export(
+  module_export_name: &ModuleExportName,
+) -> Result<JsWord, RolldownError> {
   match module_export_name {
-    ModuleExportName::Ident(i) => i.sym.clone(),
-    _ => panic!(""),
+    ModuleExportName::Ident(i) => Ok(i.sym.clone()),
+    ModuleExportName::Str(s) => {
+      let loc = SOURCE_MAP.lookup_char_pos(s.span.lo);
+      let id = match &loc.file.name {
+        FileName::Custom(id) => id.clone(),
+        name => name.to_string(),
+      };
+      Err(RolldownError::UnsupportedSyntax {
+        id,
+        line: loc.line,
+        column: loc.col_display + 1,
+        message: format!("String export name \"{}\" is not supported", s.value),
+      })
+    }
   }
 }
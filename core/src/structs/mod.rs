@@ -1,21 +1,63 @@
+use std::borrow::Cow;
+
+use sourcemap::SourceMap;
+
 use crate::{external_module::ExternalModule, module::Module};
 
 #[derive(Debug)]
 pub struct OutputChunk {
   pub code: String,
   pub file_name: String,
+  pub map: Option<SourceMap>,
+  pub is_entry: bool,
+  pub facade_module_id: Option<String>,
+  pub module_ids: Vec<String>,
+}
+
+// Information about a chunk, which is passed to addons like `banner`
+#[derive(Debug, Clone)]
+pub struct ChunkInfo {
+  pub file_name: String,
+  pub name: String,
+  pub is_entry: bool,
+  pub is_dynamic_entry: bool,
+  pub facade_module_id: Option<String>,
+  pub module_ids: Vec<String>,
+  pub exports: Vec<String>,
+  pub imports: Vec<String>,
 }
 
 #[derive(Debug)]
 pub struct RenderedChunk {
   pub code: String,
   pub file_name: String,
+  pub map: Option<SourceMap>,
+  // Added to the hash of the chunk by the `augment_chunk_hash` hook.
+  pub hash_augmentation: String,
+  pub is_entry: bool,
+  pub facade_module_id: Option<String>,
+  pub module_ids: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct OutputAsset {
+  pub file_name: String,
+  pub source: Vec<u8>,
+  pub name: Option<String>,
+}
+
+// An asset to be emitted. It's named by `assetFileNames` unless `file_name` is given.
+#[derive(Debug, Clone)]
+pub struct EmittedAsset {
+  pub name: Option<String>,
+  pub file_name: Option<String>,
+  pub source: Vec<u8>,
 }
 
 #[derive(Debug)]
 pub enum RolldownOutput {
   Chunk(OutputChunk),
-  Asset,
+  Asset(OutputAsset),
 }
 
 impl RolldownOutput {
@@ -23,15 +65,24 @@ impl RolldownOutput {
   pub fn get_file_name(&self) -> &str {
     match self {
       RolldownOutput::Chunk(c) => c.file_name.as_ref(),
-      RolldownOutput::Asset => panic!(""),
+      RolldownOutput::Asset(a) => a.file_name.as_ref(),
+    }
+  }
+
+  // Binary assets are converted lossily.
+  #[inline]
+  pub fn get_content(&self) -> Cow<str> {
+    match self {
+      RolldownOutput::Chunk(c) => Cow::Borrowed(&c.code),
+      RolldownOutput::Asset(a) => String::from_utf8_lossy(&a.source),
     }
   }
 
   #[inline]
-  pub fn get_content(&self) -> &str {
+  pub fn get_source(&self) -> &[u8] {
     match self {
-      RolldownOutput::Chunk(c) => c.code.as_ref(),
-      RolldownOutput::Asset => panic!(""),
+      RolldownOutput::Chunk(c) => c.code.as_bytes(),
+      RolldownOutput::Asset(a) => &a.source,
     }
   }
 }
@@ -2,7 +2,7 @@ use ena::unify::{InPlaceUnificationTable, UnifyKey};
 use once_cell::sync::Lazy;
 use swc_common::{Globals, Mark, GLOBALS};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SymbolBox {
   pub mark_uf: InPlaceUnificationTable<MarkIndex>,
   // globals: Globals,
@@ -1,9 +1,13 @@
 use std::hash::Hash;
 
+mod module_info;
+pub use module_info::*;
 mod normalized_input_options;
 pub use normalized_input_options::*;
 mod normalized_output_options;
 pub use normalized_output_options::*;
+mod raw_options;
+pub use raw_options::*;
 use smol_str::SmolStr;
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
@@ -0,0 +1,19 @@
+use std::collections::HashMap;
+
+use smol_str::SmolStr;
+
+// Modeled after `ModuleInfo` of Rollup's `this.getModuleInfo`
+#[derive(Debug, Clone)]
+pub struct ModuleInfo {
+  pub id: SmolStr,
+  pub is_entry: bool,
+  pub is_external: bool,
+  pub importers: Vec<SmolStr>,
+  pub dynamic_importers: Vec<SmolStr>,
+  pub imported_ids: Vec<SmolStr>,
+  pub dynamically_imported_ids: Vec<SmolStr>,
+  // Sorted names. They are complete once the build ends, as `export *` is linked then.
+  pub exports: Vec<String>,
+  // Set by the `transform` hook of plugins
+  pub meta: HashMap<String, serde_json::Value>,
+}
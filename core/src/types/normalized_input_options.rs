@@ -1,11 +1,17 @@
+use std::sync::Arc;
+
+use crate::plugin_driver::{Plugin, PluginWarning};
+
 // (source: &str, importer: Option<&str>, is_resolved: bool)
-pub type IsExternal = Box<dyn Fn(&str, Option<&str>, bool) -> bool>;
+pub type IsExternal = Arc<dyn Fn(&str, Option<&str>, bool) -> bool + Send + Sync>;
+
+pub type OnWarn = Arc<dyn Fn(&PluginWarning) + Send + Sync>;
 
 // type ModuleContext = Box<dyn Fn(&str) -> &str>;
 
 // type EntryAlias = String;
 
-#[derive(Default)]
+#[derive(Clone)]
 pub struct NormalizedInputOptions {
   // --- Options that Rolldown doesn't need to be supported
   // acorn: Record<string, unknown>;
@@ -22,9 +28,12 @@ pub struct NormalizedInputOptions {
 
   // --- Options that Rolldown might need to supported
   // cache: false | undefined | RollupCache;
+  // Directory to store parsed modules, which are reused by later builds, even in other processes.
+  pub cache_dir: Option<String>,
   // makeAbsoluteExternalsRelative: boolean | 'ifRelativeSource';
   // maxParallelFileReads: number;
-  // onwarn: WarningHandler;
+  // Called with warnings of plugins, which are logged by default.
+  pub on_warn: OnWarn,
   // perf: boolean;
   // preserveEntrySignatures: PreserveEntrySignaturesOption;
   // shimMissingExports: boolean;
@@ -33,11 +42,25 @@ pub struct NormalizedInputOptions {
   // --- Options that Rolldown must need to be supported
   pub treeshake: bool,
   // pub treeshake: bool | NormalizedTreeshakingOptions;
-  // pub plugins: Vec<Box<dyn Plugin>>,
+  pub plugins: Vec<Arc<dyn Plugin>>,
   // By default, the context of a module – i.e., the value of this at the top level – is undefined. In rare cases you might need to change this to something else, like 'window'.
   // pub context: Option<String>,
-  // pub external: IsExternal,
+  pub external: IsExternal,
   // (alias: Option<String>, path: String)
   pub input: Vec<String>,
   // pub preserve_symlinks: bool,
 }
+
+impl Default for NormalizedInputOptions {
+  fn default() -> Self {
+    Self {
+      treeshake: false,
+      // Bare imports are treated as external modules regardless of this option.
+      external: Arc::new(|_, _, _| false),
+      input: Default::default(),
+      cache_dir: None,
+      plugins: Default::default(),
+      on_warn: Arc::new(|warning| log::warn!("{}", warning)),
+    }
+  }
+}
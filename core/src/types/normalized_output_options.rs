@@ -1,69 +1,288 @@
-#[derive(Clone, Copy, PartialEq, Eq)]
+use std::collections::HashMap;
+
+use crate::{graph::Graph, structs::ChunkInfo, types::ModuleInfo};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InternalModuleFormat {
   ES,
   CJS,
   AMD,
   UMD,
+  IIFE,
+  System,
+}
+
+impl InternalModuleFormat {
+  #[inline]
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      InternalModuleFormat::ES => "es",
+      InternalModuleFormat::CJS => "cjs",
+      InternalModuleFormat::AMD => "amd",
+      InternalModuleFormat::UMD => "umd",
+      InternalModuleFormat::IIFE => "iife",
+      InternalModuleFormat::System => "system",
+    }
+  }
+}
+
+impl std::str::FromStr for InternalModuleFormat {
+  type Err = String;
+
+  fn from_str(format: &str) -> Result<Self, Self::Err> {
+    match format {
+      "es" | "esm" | "module" => Ok(InternalModuleFormat::ES),
+      "cjs" | "commonjs" => Ok(InternalModuleFormat::CJS),
+      "amd" => Ok(InternalModuleFormat::AMD),
+      "umd" => Ok(InternalModuleFormat::UMD),
+      "iife" => Ok(InternalModuleFormat::IIFE),
+      "system" | "systemjs" => Ok(InternalModuleFormat::System),
+      _ => Err(format!(
+        "Invalid value \"{}\" for option \"output.format\" - Valid values are \"amd\", \"cjs\", \"system\", \"es\", \"iife\" or \"umd\".",
+        format
+      )),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportMode {
+  Auto,
+  Default,
+  Named,
+  None,
+}
+
+impl ExportMode {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      ExportMode::Auto => "auto",
+      ExportMode::Default => "default",
+      ExportMode::Named => "named",
+      ExportMode::None => "none",
+    }
+  }
+}
+
+impl std::str::FromStr for ExportMode {
+  type Err = String;
+
+  fn from_str(mode: &str) -> Result<Self, Self::Err> {
+    match mode {
+      "auto" => Ok(ExportMode::Auto),
+      "default" => Ok(ExportMode::Default),
+      "named" => Ok(ExportMode::Named),
+      "none" => Ok(ExportMode::None),
+      _ => Err(format!(
+        "Invalid value \"{}\" for option \"output.exports\" - Valid values are \"auto\", \"default\", \"named\" or \"none\".",
+        mode
+      )),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceMapMode {
+  Disabled,
+  // `sourcemap: true`. The map is emitted as a separate `.map` file with a `sourceMappingURL` comment.
+  File,
+  Inline,
+  // Like `File`, but without the `sourceMappingURL` comment.
+  Hidden,
+}
+
+impl SourceMapMode {
+  #[inline]
+  pub fn is_enabled(&self) -> bool {
+    *self != SourceMapMode::Disabled
+  }
+}
+
+impl std::str::FromStr for SourceMapMode {
+  type Err = String;
+
+  fn from_str(mode: &str) -> Result<Self, Self::Err> {
+    match mode {
+      "true" => Ok(SourceMapMode::File),
+      "false" => Ok(SourceMapMode::Disabled),
+      "inline" => Ok(SourceMapMode::Inline),
+      "hidden" => Ok(SourceMapMode::Hidden),
+      _ => Err(format!(
+        "Invalid value \"{}\" for option \"output.sourcemap\" - Valid values are true, false, \"inline\" or \"hidden\".",
+        mode
+      )),
+    }
+  }
+}
+
+// (relativeSourcePath, sourcemapPath) => string
+pub type SourcemapPathTransform = Box<dyn Fn(&str, &str) -> String + Send + Sync>;
+
+#[derive(Debug, Clone)]
+pub struct AmdOptions {
+  // Use the file name of the chunk, without the `.js` extension, as the module id. Can't be used with `id`.
+  pub auto_id: bool,
+  // basePath?: string;
+  pub define: String,
+  pub id: Option<String>,
+}
+
+impl Default for AmdOptions {
+  fn default() -> Self {
+    Self {
+      auto_id: false,
+      define: "define".to_string(),
+      id: None,
+    }
+  }
+}
+
+// `banner`, `footer`, `intro` and `outro` could be static or computed for each chunk.
+pub enum Addon {
+  Static(String),
+  Fn(Box<dyn Fn(&ChunkInfo) -> String + Send + Sync>),
+}
+
+impl Addon {
+  pub fn render(&self, chunk: &ChunkInfo) -> String {
+    match self {
+      Addon::Static(content) => content.clone(),
+      Addon::Fn(render) => render(chunk),
+    }
+  }
+}
+
+impl From<&str> for Addon {
+  fn from(content: &str) -> Self {
+    Addon::Static(content.to_string())
+  }
+}
+
+// Passed to the `manualChunks` function
+pub struct ChunkContext<'a> {
+  pub(crate) graph: &'a Graph,
+}
+
+impl<'a> ChunkContext<'a> {
+  pub fn get_module_info(&self, id: &str) -> Option<ModuleInfo> {
+    self.graph.get_module_info(id)
+  }
+
+  pub fn get_module_ids(&self) -> impl Iterator<Item = &str> {
+    self.graph.module_graph.node_weights().map(|id| id.as_str())
+  }
+}
+
+pub enum ManualChunksOption {
+  // Chunk name -> ids of modules in the chunk
+  Map(HashMap<String, Vec<String>>),
+  // (id, context) => chunk name
+  Fn(Box<dyn Fn(&str, &ChunkContext) -> Option<String> + Send + Sync>),
+}
+
+#[derive(Debug, Clone)]
+pub struct MinifyOptions {
+  pub compress: bool,
+  pub mangle: bool,
+  pub keep_classnames: bool,
+  pub keep_fnames: bool,
+  // Mangle top-level names. Names referenced by the wrapper of the format are kept.
+  pub toplevel: bool,
+  // Times to run compress
+  pub passes: usize,
+}
+
+impl Default for MinifyOptions {
+  fn default() -> Self {
+    Self {
+      compress: true,
+      mangle: true,
+      keep_classnames: false,
+      keep_fnames: false,
+      toplevel: false,
+      passes: 1,
+    }
+  }
 }
 
 pub struct NormalizedOutputOptions {
   // --- Options Rolldown doesn't need to be supported
   // /** @deprecated Use the "renderDynamicImport" plugin hook instead. */
   // dynamicImportFunction: string | undefined;
-
-  // amd: NormalizedAmdOptions;
-  // assetFileNames: string | ((chunkInfo: PreRenderedAsset) => string);
-  // banner: () => string | Promise<string>;
-  // chunkFileNames: string | ((chunkInfo: PreRenderedChunk) => string);
+  pub amd: AmdOptions,
+  pub asset_file_names: String, // | ((chunkInfo: PreRenderedAsset) => string)
+  pub banner: Option<Addon>,
+  pub chunk_file_names: String, // | ((chunkInfo: PreRenderedChunk) => string)
   // compact: boolean;
   pub dir: Option<String>,
   pub entry_file_names: String, // | ((chunkInfo: PreRenderedChunk) => string)
-  // esModule: boolean;
-  // exports: 'default' | 'named' | 'none' | 'auto';
-  // extend: boolean;
+  pub es_module: bool,
+  pub exports: ExportMode,
+  pub extend: bool,
   // externalLiveBindings: boolean;
   pub file: Option<String>,
-  // footer: () => string | Promise<string>;
+  pub footer: Option<Addon>,
   pub format: InternalModuleFormat,
   // freeze: boolean;
   // generatedCode: NormalizedGeneratedCodeOptions;
-  // globals: GlobalsOption;
+  // Map external module ids to global variable names for UMD and IIFE
+  pub globals: HashMap<String, String>,
   // hoistTransitiveImports: boolean;
   // indent: true | string;
-  // inlineDynamicImports: boolean;
+  pub inline_dynamic_imports: bool,
   // interop: GetInterop;
-  // intro: () => string | Promise<string>;
-  // manualChunks: ManualChunksOption;
+  pub intro: Option<Addon>,
+  pub manual_chunks: Option<ManualChunksOption>,
   // minifyInternalExports: boolean;
-  // name: string | undefined;
+  pub name: Option<String>,
   // namespaceToStringTag: boolean;
   // noConflict: boolean;
-  // outro: () => string | Promise<string>;
+  pub outro: Option<Addon>,
   // paths: OptionsPaths;
   // plugins: OutputPlugin[];
   // preferConst: boolean;
-  // preserveModules: boolean;
-  // preserveModulesRoot: string | undefined;
+  pub preserve_modules: bool,
+  pub preserve_modules_root: Option<String>,
   // sanitizeFileName: (fileName: string) => string;
-  // sourcemap: boolean | 'inline' | 'hidden';
-  // sourcemapExcludeSources: boolean;
-  // sourcemapFile: string | undefined;
-  // sourcemapPathTransform: SourcemapPathTransformOption | undefined;
+  pub sourcemap: SourceMapMode,
+  pub sourcemap_exclude_sources: bool,
+  pub sourcemap_file: Option<String>,
+  pub sourcemap_path_transform: Option<SourcemapPathTransform>,
   // strict: boolean;
   // systemNullSetters: boolean;
   // validate: boolean;
   // --- Enhanced options
-  pub minify: bool,
+  pub minify: Option<MinifyOptions>,
 }
 
 impl Default for NormalizedOutputOptions {
   fn default() -> Self {
     Self {
       format: InternalModuleFormat::ES,
+      amd: Default::default(),
+      banner: None,
+      footer: None,
+      intro: None,
+      outro: None,
+      es_module: true,
+      exports: ExportMode::Auto,
+      extend: false,
+      globals: Default::default(),
+      inline_dynamic_imports: false,
+      manual_chunks: None,
+      preserve_modules: false,
+      preserve_modules_root: None,
+      name: None,
       file: Default::default(),
       dir: Default::default(),
-      minify: Default::default(),
+      minify: None,
       entry_file_names: "[name].js".to_string(),
+      chunk_file_names: "[name]-[hash].js".to_string(),
+      asset_file_names: "assets/[name]-[hash][extname]".to_string(),
+      sourcemap: SourceMapMode::Disabled,
+      sourcemap_exclude_sources: false,
+      sourcemap_file: None,
+      sourcemap_path_transform: None,
     }
   }
 }
@@ -0,0 +1,351 @@
+use std::{collections::HashMap, fmt, marker::PhantomData, sync::Arc};
+
+use serde::{
+  de::{self, value::MapAccessDeserializer, value::SeqAccessDeserializer, Visitor},
+  Deserialize, Deserializer,
+};
+
+use crate::{
+  types::{
+    AmdOptions, ExportMode, InternalModuleFormat, ManualChunksOption, MinifyOptions,
+    NormalizedInputOptions, NormalizedOutputOptions, SourceMapMode,
+  },
+  utils::resolve_id,
+  worker::RolldownError,
+};
+
+// Options as written in `rolldown.config.json` or `rolldown.config.toml`. Keys are the same as Rollup's.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RawInputOptions {
+  #[serde(default, deserialize_with = "string_or_vec")]
+  pub input: Vec<String>,
+  #[serde(default, deserialize_with = "string_or_vec")]
+  pub external: Vec<String>,
+  pub treeshake: Option<bool>,
+  pub cache_dir: Option<String>,
+  #[serde(default, deserialize_with = "one_or_many")]
+  pub output: Vec<RawOutputOptions>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RawOutputOptions {
+  pub amd: Option<RawAmdOptions>,
+  pub asset_file_names: Option<String>,
+  pub banner: Option<String>,
+  pub chunk_file_names: Option<String>,
+  pub dir: Option<String>,
+  pub entry_file_names: Option<String>,
+  pub es_module: Option<bool>,
+  #[serde(default, deserialize_with = "from_str")]
+  pub exports: Option<ExportMode>,
+  pub extend: Option<bool>,
+  pub file: Option<String>,
+  pub footer: Option<String>,
+  #[serde(default, deserialize_with = "from_str")]
+  pub format: Option<InternalModuleFormat>,
+  pub globals: Option<HashMap<String, String>>,
+  pub inline_dynamic_imports: Option<bool>,
+  pub intro: Option<String>,
+  pub manual_chunks: Option<HashMap<String, Vec<String>>>,
+  #[serde(default, deserialize_with = "minify")]
+  pub minify: Option<MinifyOptions>,
+  pub name: Option<String>,
+  pub outro: Option<String>,
+  pub preserve_modules: Option<bool>,
+  pub preserve_modules_root: Option<String>,
+  #[serde(default, deserialize_with = "sourcemap")]
+  pub sourcemap: Option<SourceMapMode>,
+  pub sourcemap_exclude_sources: Option<bool>,
+  pub sourcemap_file: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RawAmdOptions {
+  pub auto_id: Option<bool>,
+  pub define: Option<String>,
+  pub id: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct RawMinifyOptions {
+  compress: Option<bool>,
+  mangle: Option<bool>,
+  keep_classnames: Option<bool>,
+  keep_fnames: Option<bool>,
+  toplevel: Option<bool>,
+  passes: Option<usize>,
+}
+
+// Multiple builds in TOML are written as `[[config]]` tables.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TomlConfigs {
+  config: Vec<RawInputOptions>,
+}
+
+/// Read a JSON or TOML config file, which contains a config object or an array of them.
+pub fn load_config_file(path: &str) -> Result<Vec<RawInputOptions>, RolldownError> {
+  let content = std::fs::read_to_string(path).map_err(RolldownError::IO)?;
+  if path.ends_with(".toml") {
+    let value = toml::from_str::<toml::Value>(&content).map_err(|err| toml_error(path, err))?;
+    let is_multiple = value.as_table().map_or(false, |table| {
+      table.len() == 1 && table.contains_key("config")
+    });
+    if is_multiple {
+      toml::from_str::<TomlConfigs>(&content)
+        .map(|configs| configs.config)
+        .map_err(|err| toml_error(path, err))
+    } else {
+      toml::from_str::<RawInputOptions>(&content)
+        .map(|config| vec![config])
+        .map_err(|err| toml_error(path, err))
+    }
+  } else {
+    let mut deserializer = serde_json::Deserializer::from_str(&content);
+    one_or_many(&mut deserializer)
+      .and_then(|configs| deserializer.end().map(|_| configs))
+      .map_err(|err| RolldownError::Config {
+        file: path.to_string(),
+        line: err.line(),
+        column: err.column(),
+        message: strip_location(err.to_string()),
+      })
+  }
+}
+
+fn toml_error(path: &str, err: toml::de::Error) -> RolldownError {
+  let (line, column) = err
+    .line_col()
+    .map_or((0, 0), |(line, col)| (line + 1, col + 1));
+  RolldownError::Config {
+    file: path.to_string(),
+    line,
+    column,
+    message: strip_location(err.to_string()),
+  }
+}
+
+// Locations are reported separately.
+fn strip_location(message: String) -> String {
+  match message.rfind(" at line ") {
+    Some(index) => message[..index].to_string(),
+    None => message,
+  }
+}
+
+impl RawInputOptions {
+  pub fn normalize(
+    self,
+  ) -> Result<(NormalizedInputOptions, Vec<NormalizedOutputOptions>), RolldownError> {
+    if self.input.is_empty() {
+      return Err(RolldownError::InvalidOption(
+        "You must supply \"input\" in the config.".to_string(),
+      ));
+    }
+    let external = self
+      .external
+      .into_iter()
+      .map(|id| {
+        let resolved = resolve_id(&id, None, false).id.to_string();
+        (id, resolved)
+      })
+      .collect::<Vec<_>>();
+    let input_options = NormalizedInputOptions {
+      input: self.input,
+      treeshake: self.treeshake.unwrap_or(false),
+      cache_dir: self.cache_dir,
+      // Plugins are Rust objects, which can't be set in config files.
+      plugins: Default::default(),
+      external: Arc::new(move |source, _, is_resolved| {
+        external.iter().any(|(id, resolved)| {
+          source == id.as_str() || (is_resolved && source == resolved.as_str())
+        })
+      }),
+      ..Default::default()
+    };
+    let output_options = if self.output.is_empty() {
+      vec![Default::default()]
+    } else {
+      self.output
+    };
+    Ok((
+      input_options,
+      output_options
+        .into_iter()
+        .map(RawOutputOptions::normalize)
+        .collect(),
+    ))
+  }
+}
+
+impl RawOutputOptions {
+  pub fn normalize(self) -> NormalizedOutputOptions {
+    let defaults = NormalizedOutputOptions::default();
+    let amd = self.amd.unwrap_or_default();
+    NormalizedOutputOptions {
+      amd: AmdOptions {
+        auto_id: amd.auto_id.unwrap_or(defaults.amd.auto_id),
+        define: amd.define.unwrap_or(defaults.amd.define),
+        id: amd.id,
+      },
+      asset_file_names: self.asset_file_names.unwrap_or(defaults.asset_file_names),
+      banner: self.banner.map(|banner| banner.as_str().into()),
+      chunk_file_names: self.chunk_file_names.unwrap_or(defaults.chunk_file_names),
+      dir: self.dir,
+      entry_file_names: self.entry_file_names.unwrap_or(defaults.entry_file_names),
+      es_module: self.es_module.unwrap_or(defaults.es_module),
+      exports: self.exports.unwrap_or(defaults.exports),
+      extend: self.extend.unwrap_or(defaults.extend),
+      file: self.file,
+      footer: self.footer.map(|footer| footer.as_str().into()),
+      format: self.format.unwrap_or(defaults.format),
+      globals: self.globals.unwrap_or_default(),
+      inline_dynamic_imports: self
+        .inline_dynamic_imports
+        .unwrap_or(defaults.inline_dynamic_imports),
+      intro: self.intro.map(|intro| intro.as_str().into()),
+      manual_chunks: self.manual_chunks.map(ManualChunksOption::Map),
+      minify: self.minify,
+      name: self.name,
+      outro: self.outro.map(|outro| outro.as_str().into()),
+      preserve_modules: self.preserve_modules.unwrap_or(defaults.preserve_modules),
+      preserve_modules_root: self.preserve_modules_root,
+      sourcemap: self.sourcemap.unwrap_or(defaults.sourcemap),
+      sourcemap_exclude_sources: self
+        .sourcemap_exclude_sources
+        .unwrap_or(defaults.sourcemap_exclude_sources),
+      sourcemap_file: self.sourcemap_file,
+      ..defaults
+    }
+  }
+}
+
+fn from_str<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+  D: Deserializer<'de>,
+  T: std::str::FromStr<Err = String>,
+{
+  let value = String::deserialize(deserializer)?;
+  value.parse().map(Some).map_err(de::Error::custom)
+}
+
+// `true`, `false`, `"inline"` or `"hidden"`
+fn sourcemap<'de, D>(deserializer: D) -> Result<Option<SourceMapMode>, D::Error>
+where
+  D: Deserializer<'de>,
+{
+  struct SourcemapVisitor;
+
+  impl<'de> Visitor<'de> for SourcemapVisitor {
+    type Value = Option<SourceMapMode>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      f.write_str("a boolean, \"inline\" or \"hidden\"")
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<Self::Value, E> {
+      Ok(Some(if value {
+        SourceMapMode::File
+      } else {
+        SourceMapMode::Disabled
+      }))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+      value.parse().map(Some).map_err(E::custom)
+    }
+  }
+
+  deserializer.deserialize_any(SourcemapVisitor)
+}
+
+// `true` or an object of minify options
+fn minify<'de, D>(deserializer: D) -> Result<Option<MinifyOptions>, D::Error>
+where
+  D: Deserializer<'de>,
+{
+  struct MinifyVisitor;
+
+  impl<'de> Visitor<'de> for MinifyVisitor {
+    type Value = Option<MinifyOptions>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      f.write_str("a boolean or an object of minify options")
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<Self::Value, E> {
+      Ok(value.then(Default::default))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+      let raw = RawMinifyOptions::deserialize(MapAccessDeserializer::new(map))?;
+      let defaults = MinifyOptions::default();
+      Ok(Some(MinifyOptions {
+        compress: raw.compress.unwrap_or(defaults.compress),
+        mangle: raw.mangle.unwrap_or(defaults.mangle),
+        keep_classnames: raw.keep_classnames.unwrap_or(defaults.keep_classnames),
+        keep_fnames: raw.keep_fnames.unwrap_or(defaults.keep_fnames),
+        toplevel: raw.toplevel.unwrap_or(defaults.toplevel),
+        passes: raw.passes.unwrap_or(defaults.passes),
+      }))
+    }
+  }
+
+  deserializer.deserialize_any(MinifyVisitor)
+}
+
+fn string_or_vec<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+  D: Deserializer<'de>,
+{
+  struct StringOrVecVisitor;
+
+  impl<'de> Visitor<'de> for StringOrVecVisitor {
+    type Value = Vec<String>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      f.write_str("a string or an array of strings")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+      Ok(vec![value.to_string()])
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+      Vec::deserialize(SeqAccessDeserializer::new(seq))
+    }
+  }
+
+  deserializer.deserialize_any(StringOrVecVisitor)
+}
+
+// An object or an array of objects. Unlike `#[serde(untagged)]`, errors of the object are kept.
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+  D: Deserializer<'de>,
+  T: Deserialize<'de>,
+{
+  struct OneOrManyVisitor<T>(PhantomData<T>);
+
+  impl<'de, T: Deserialize<'de>> Visitor<'de> for OneOrManyVisitor<T> {
+    type Value = Vec<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      f.write_str("an object or an array of objects")
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+      T::deserialize(MapAccessDeserializer::new(map)).map(|value| vec![value])
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+      Vec::deserialize(SeqAccessDeserializer::new(seq))
+    }
+  }
+
+  deserializer.deserialize_any(OneOrManyVisitor(PhantomData))
+}
@@ -2,13 +2,13 @@ use std::collections::HashMap;
 use swc_atoms::JsWord;
 use swc_common::{util::take::Take, Mark, Span, DUMMY_SP};
 use swc_ecma_ast::{
-  BindingIdent, CallExpr, Callee, Decl, ExportNamedSpecifier, ExportSpecifier, Expr, ExprOrSpread,
-  Ident, KeyValueProp, Lit, MemberExpr, MemberProp, ModuleDecl, ModuleExportName, NamedExport,
-  Null, ObjectLit, Pat, Prop, PropName, PropOrSpread, Stmt, Str, VarDecl, VarDeclKind,
-  VarDeclarator,
+  ArrayLit, ArrowExpr, BindingIdent, BlockStmtOrExpr, CallExpr, Callee, Decl, ExportNamedSpecifier,
+  ExportSpecifier, Expr, ExprOrSpread, Ident, KeyValueProp, Lit, MemberExpr, MemberProp,
+  ModuleDecl, ModuleExportName, NamedExport, NewExpr, Null, ObjectLit, Pat, Prop, PropName,
+  PropOrSpread, Stmt, Str, VarDecl, VarDeclKind, VarDeclarator,
 };
 
-use crate::ext::MarkExt;
+use crate::{ext::MarkExt, types::InternalModuleFormat};
 
 #[inline]
 fn jsword(s: &str) -> JsWord {
@@ -16,7 +16,7 @@ fn jsword(s: &str) -> JsWord {
 }
 
 #[inline]
-fn str(s: &str) -> Str {
+pub fn str(s: &str) -> Str {
   Str {
     value: jsword(s),
     ..Str::dummy()
@@ -129,3 +129,106 @@ pub fn namespace(var_name: (JsWord, Mark), key_values: &HashMap<JsWord, Mark>) -
     }],
   }))
 }
+
+#[inline]
+fn expr_or_spread(expr: Box<Expr>) -> ExprOrSpread {
+  ExprOrSpread { expr, spread: None }
+}
+
+#[inline]
+pub fn member(obj: Box<Expr>, prop: &str) -> Box<Expr> {
+  Box::new(Expr::Member(MemberExpr {
+    obj,
+    prop: MemberProp::Ident(Ident {
+      sym: jsword(prop),
+      ..Ident::dummy()
+    }),
+    ..MemberExpr::dummy()
+  }))
+}
+
+#[inline]
+fn call(callee: Box<Expr>, args: Vec<Box<Expr>>) -> Box<Expr> {
+  Box::new(Expr::Call(CallExpr {
+    callee: Callee::Expr(callee),
+    args: args.into_iter().map(expr_or_spread).collect(),
+    ..CallExpr::dummy()
+  }))
+}
+
+fn arrow(params: &[&str], body: Box<Expr>) -> Box<Expr> {
+  Box::new(Expr::Arrow(ArrowExpr {
+    span: DUMMY_SP,
+    params: params
+      .iter()
+      .map(|param| {
+        Pat::Ident(BindingIdent {
+          id: Ident {
+            sym: jsword(param),
+            ..Ident::dummy()
+          },
+          type_ann: None,
+        })
+      })
+      .collect(),
+    body: BlockStmtOrExpr::Expr(body),
+    is_async: false,
+    is_generator: false,
+    type_params: None,
+    return_type: None,
+  }))
+}
+
+// exports('name', value) of System chunks, which returns the value.
+pub fn system_export(name: &str, value: Box<Expr>) -> Box<Expr> {
+  call(
+    expr_ident("exports"),
+    vec![Box::new(Expr::Lit(Lit::Str(str(name)))), value],
+  )
+}
+
+// Promise.resolve().then(() => value)
+pub fn resolved_promise(value: Box<Expr>) -> Box<Expr> {
+  call(
+    member(
+      call(member(expr_ident("Promise"), "resolve"), vec![]),
+      "then",
+    ),
+    vec![arrow(&[], value)],
+  )
+}
+
+/// `import('./foo.js')` in formats without native dynamic imports.
+/// Returns `None` for formats that keep `import()` as it is.
+pub fn dynamic_import(format: InternalModuleFormat, source: &str) -> Option<Box<Expr>> {
+  let source = Box::new(Expr::Lit(Lit::Str(str(source))));
+  match format {
+    // Promise.resolve().then(() => require('./foo.js'))
+    InternalModuleFormat::CJS => Some(resolved_promise(call(expr_ident("require"), vec![source]))),
+    // new Promise((resolve, reject) => require(['./foo.js'], resolve, reject))
+    InternalModuleFormat::AMD => Some(Box::new(Expr::New(NewExpr {
+      span: DUMMY_SP,
+      callee: expr_ident("Promise"),
+      args: Some(vec![expr_or_spread(arrow(
+        &["resolve", "reject"],
+        call(
+          expr_ident("require"),
+          vec![
+            Box::new(Expr::Array(ArrayLit {
+              span: DUMMY_SP,
+              elems: vec![Some(expr_or_spread(source))],
+            })),
+            expr_ident("resolve"),
+            expr_ident("reject"),
+          ],
+        ),
+      ))]),
+      type_args: None,
+    }))),
+    // module.import('./foo.js')
+    InternalModuleFormat::System => {
+      Some(call(member(expr_ident("module"), "import"), vec![source]))
+    }
+    _ => None,
+  }
+}
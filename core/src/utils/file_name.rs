@@ -0,0 +1,97 @@
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+
+use crate::worker::RolldownError;
+
+pub const DEFAULT_HASH_SIZE: usize = 8;
+// Hex digits of SHA-256
+const MAX_HASH_SIZE: usize = 64;
+const HASH_PLACEHOLDER_LEFT: &str = "!~{";
+const HASH_PLACEHOLDER_RIGHT: &str = "}~";
+const MIN_HASH_SIZE: usize = HASH_PLACEHOLDER_LEFT.len() + HASH_PLACEHOLDER_RIGHT.len() + 1;
+
+static PLACEHOLDER_RE: Lazy<regex::Regex> =
+  Lazy::new(|| regex::Regex::new(r"\[(\w+)(?::(\d+))?\]").unwrap());
+pub static HASH_PLACEHOLDER_RE: Lazy<regex::Regex> =
+  Lazy::new(|| regex::Regex::new(r"!~\{[0-9a-z]+\}~").unwrap());
+
+/// Replace `[placeholder]` and `[placeholder:size]` in `pattern`. `replace` returns `None` for placeholders
+/// not supported by the pattern, which is an error.
+pub fn render_file_name_pattern(
+  pattern: &str,
+  pattern_name: &str,
+  mut replace: impl FnMut(&str, Option<usize>) -> Result<Option<String>, RolldownError>,
+) -> Result<String, RolldownError> {
+  if nodejs_path::is_absolute(pattern) || pattern.starts_with("./") || pattern.starts_with("../") {
+    return Err(RolldownError::InvalidOption(format!(
+      "Invalid pattern \"{}\" for \"output.{}\", patterns can be neither absolute nor relative paths.",
+      pattern, pattern_name
+    )));
+  }
+  let mut result = String::with_capacity(pattern.len());
+  let mut last = 0;
+  for captures in PLACEHOLDER_RE.captures_iter(pattern) {
+    let whole = captures.get(0).unwrap();
+    let placeholder = &captures[1];
+    let size = captures.get(2).and_then(|size| size.as_str().parse().ok());
+    if size.is_some() && placeholder != "hash" {
+      return Err(RolldownError::InvalidOption(format!(
+        "\"[{}]\" in \"output.{}\" doesn't accept a size.",
+        placeholder, pattern_name
+      )));
+    }
+    let replacement = replace(placeholder, size)?.ok_or_else(|| {
+      RolldownError::InvalidOption(format!(
+        "\"[{}]\" is not a valid placeholder in the \"output.{}\" pattern.",
+        placeholder, pattern_name
+      ))
+    })?;
+    result.push_str(&pattern[last..whole.start()]);
+    result.push_str(&replacement);
+    last = whole.end();
+  }
+  result.push_str(&pattern[last..]);
+  Ok(result)
+}
+
+/// Hashes depend on the final code of chunks, which is only known after rendering. Chunks are named
+/// with placeholders of the same length as the hashes first, so they could be replaced in place later.
+pub fn hash_placeholder(index: usize, size: usize) -> Result<String, RolldownError> {
+  if !(MIN_HASH_SIZE..=MAX_HASH_SIZE).contains(&size) {
+    return Err(RolldownError::InvalidOption(format!(
+      "Hashes must be between {} and {} characters long, received {}.",
+      MIN_HASH_SIZE, MAX_HASH_SIZE, size
+    )));
+  }
+  let width = size - HASH_PLACEHOLDER_LEFT.len() - HASH_PLACEHOLDER_RIGHT.len();
+  let index = to_base36(index);
+  if index.len() > width {
+    return Err(RolldownError::InvalidOption(format!(
+      "A hash size of {} is too short for the number of chunks.",
+      size
+    )));
+  }
+  Ok(format!(
+    "{}{:0>width$}{}",
+    HASH_PLACEHOLDER_LEFT,
+    index,
+    HASH_PLACEHOLDER_RIGHT,
+    width = width
+  ))
+}
+
+fn to_base36(mut n: usize) -> String {
+  let mut digits = vec![];
+  loop {
+    digits.push(std::char::from_digit((n % 36) as u32, 36).unwrap());
+    n /= 36;
+    if n == 0 {
+      break;
+    }
+  }
+  digits.iter().rev().collect()
+}
+
+pub fn content_hash(content: &[u8]) -> String {
+  format!("{:x}", Sha256::digest(content))
+}
@@ -2,6 +2,7 @@ use std::{ffi::OsString, path::Path};
 
 use crate::{
   ext::PathExt, plugin_driver::PluginDriver, types::ResolvedId, utils::is_external_module,
+  worker::RolldownError,
 };
 
 #[inline]
@@ -29,7 +30,7 @@ pub fn resolve_id_via_plugins(
   source: &str,
   importer: Option<&str>,
   plugin_driver: &PluginDriver,
-) -> Option<ResolvedId> {
+) -> Result<Option<ResolvedId>, RolldownError> {
   plugin_driver.resolve_id(source, importer)
 }
 
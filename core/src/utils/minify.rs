@@ -0,0 +1,60 @@
+use swc_atoms::JsWord;
+use swc_common::{Globals, Mark, Span, SyntaxContext, GLOBALS};
+use swc_ecma_ast::Module;
+use swc_ecma_minifier::option::{terser::TerserCompressorOptions, ExtraOptions, MangleOptions};
+use swc_ecma_transforms_base::{fixer::fixer, hygiene::hygiene, resolver::resolver_with_mark};
+use swc_ecma_visit::{FoldWith, VisitMut, VisitMutWith};
+
+use crate::{compiler::SOURCE_MAP, types::MinifyOptions};
+
+// Symbols of Rolldown are stored in `SyntaxContext`, which means nothing to the minifier.
+struct ClearSyntaxContext;
+
+impl VisitMut for ClearSyntaxContext {
+  fn visit_mut_span(&mut self, span: &mut Span) {
+    span.ctxt = SyntaxContext::empty();
+  }
+}
+
+/// Compress and mangle the de-conflicted code of a chunk. `reserved` are top-level names referenced by
+/// the wrapper of the format, such as exported locals, which must not be mangled or dropped.
+pub fn minify(mut module: Module, options: &MinifyOptions, reserved: Vec<JsWord>) -> Module {
+  GLOBALS.set(&Globals::new(), || {
+    module.visit_mut_with(&mut ClearSyntaxContext);
+    let top_level_mark = Mark::fresh(Mark::root());
+    let module = module.fold_with(&mut resolver_with_mark(top_level_mark));
+
+    let compress = options.compress.then(|| {
+      let terser_options: TerserCompressorOptions = serde_json::from_value(serde_json::json!({
+        "keep_classnames": options.keep_classnames,
+        "keep_fnames": options.keep_fnames,
+        "passes": options.passes,
+        // The wrapper of the format references top-level declarations, so they are never unused.
+        "toplevel": false,
+      }))
+      .unwrap();
+      terser_options.into_config(SOURCE_MAP.clone())
+    });
+    let mangle = options.mangle.then(|| MangleOptions {
+      top_level: options.toplevel,
+      keep_class_names: options.keep_classnames,
+      keep_fn_names: options.keep_fnames,
+      reserved,
+      ..Default::default()
+    });
+
+    let module = swc_ecma_minifier::optimize(
+      module,
+      SOURCE_MAP.clone(),
+      None,
+      None,
+      &swc_ecma_minifier::option::MinifyOptions {
+        compress,
+        mangle,
+        ..Default::default()
+      },
+      &ExtraOptions { top_level_mark },
+    );
+    module.fold_with(&mut hygiene()).fold_with(&mut fixer(None))
+  })
+}
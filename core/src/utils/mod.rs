@@ -1,7 +1,10 @@
 pub mod ast_sugar;
+pub mod file_name;
 mod lcp;
+pub mod minify;
 pub mod name_helpers;
 pub mod side_effect;
+pub mod source_map;
 pub use lcp::*;
 use std::path::Path;
 
@@ -19,7 +22,7 @@ mod statement;
 pub use hook::*;
 pub use statement::*;
 
-use crate::compiler::SOURCE_MAP;
+use crate::{compiler::SOURCE_MAP, worker::RolldownError};
 
 pub mod path {
   pub fn relative_id(id: String) -> String {
@@ -48,7 +51,10 @@ pub fn is_decl_or_stmt(node: &ModuleItem) -> bool {
   )
 }
 
-pub fn parse_file(source_code: String, filename: &str) -> swc_ecma_ast::Module {
+pub fn parse_file(
+  source_code: String,
+  filename: &str,
+) -> Result<swc_ecma_ast::Module, RolldownError> {
   let handler = Handler::with_tty_emitter(ColorConfig::Auto, true, false, Some(SOURCE_MAP.clone()));
   let p = Path::new(filename);
   let fm = SOURCE_MAP.new_source_file(FileName::Custom(filename.to_owned()), source_code);
@@ -83,5 +89,13 @@ pub fn parse_file(source_code: String, filename: &str) -> swc_ecma_ast::Module {
   parser.take_errors().into_iter().for_each(|e| {
     e.into_diagnostic(&handler).emit();
   });
-  parser.parse_module().unwrap()
+  parser.parse_module().map_err(|err| {
+    let loc = SOURCE_MAP.lookup_char_pos(err.span().lo);
+    RolldownError::Parse {
+      id: filename.to_string(),
+      line: loc.line,
+      column: loc.col_display + 1,
+      message: err.kind().msg().to_string(),
+    }
+  })
 }
@@ -40,9 +40,22 @@ pub static UN_LEGAL_RE: Lazy<regex::Regex> = Lazy::new(|| regex::Regex::new(r"-(
 
 pub fn make_legal(s: &str) -> String {
   // 	str = str.replace(/-(\w)/g, (_, letter) => letter.toUpperCase()).replace(ILLEGAL_CHARACTERS, '_');
-  let mut s = s.to_string();
-  if starts_with_digit(&s) || BLACKLISTED.contains(s.as_str()) {
+  let s = UN_LEGAL_RE.replace_all(s, |caps: &regex::Captures| caps[1].to_uppercase());
+  let mut s = ILLEGAL_CHARACTERS_RE.replace_all(&s, "_").to_string();
+  if s.is_empty() || starts_with_digit(&s) || BLACKLISTED.contains(s.as_str()) {
     s.insert(0, '_');
   }
   s
 }
+
+/// Returns `name` if it's not used yet, otherwise `name$0`, `name$1` and so on. The returned name is marked as used.
+pub fn get_safe_name(name: &str, used_names: &mut HashSet<String>) -> String {
+  let mut safe_name = name.to_string();
+  let mut count = 0;
+  while used_names.contains(&safe_name) {
+    safe_name = format!("{}${}", name, count);
+    count += 1;
+  }
+  used_names.insert(safe_name.clone());
+  safe_name
+}
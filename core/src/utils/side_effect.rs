@@ -1,6 +1,7 @@
+use serde::{Deserialize, Serialize};
 use swc_ecma_ast::{Expr, ModuleDecl, ModuleItem, OptChainBase, OptChainExpr, PatOrExpr, Stmt};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SideEffect {
   Todo,
   FnCall,
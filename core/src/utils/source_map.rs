@@ -0,0 +1,168 @@
+use std::collections::HashSet;
+
+use once_cell::sync::Lazy;
+use sourcemap::{SourceMap, SourceMapBuilder};
+use swc_common::{source_map::SourceMapGenConfig, BytePos, FileName, LineCol};
+
+use crate::{compiler::SOURCE_MAP, types::NormalizedOutputOptions};
+
+static SOURCE_MAPPING_URL_RE: Lazy<regex::Regex> =
+  Lazy::new(|| regex::Regex::new(r"(?m)^//[#@]\s*sourceMappingURL=(\S+)\s*$").unwrap());
+
+// Sources of the intermediate map are module ids. They are made relative in `collapse_source_map`.
+struct SourceMapConfig;
+
+impl SourceMapGenConfig for SourceMapConfig {
+  fn file_name_to_source(&self, f: &FileName) -> String {
+    match f {
+      FileName::Custom(id) | FileName::Url(id) => id.to_string(),
+      FileName::Real(path) => path.display().to_string(),
+      _ => f.to_string(),
+    }
+  }
+
+  fn inline_sources_content(&self, _f: &FileName) -> bool {
+    true
+  }
+}
+
+/// Absolute path of the generated file, which decides the `file` and `sources` fields of its source map.
+pub fn get_output_file_path(file_name: &str, options: &NormalizedOutputOptions) -> String {
+  if let Some(file) = options.sourcemap_file.as_ref().or(options.file.as_ref()) {
+    nodejs_path::resolve!(file)
+  } else if let Some(dir) = &options.dir {
+    nodejs_path::resolve!(dir, file_name)
+  } else {
+    nodejs_path::resolve!(file_name)
+  }
+}
+
+pub fn build_source_map<'a>(
+  mappings: &mut Vec<(BytePos, LineCol)>,
+  file: &str,
+  options: &NormalizedOutputOptions,
+  get_input_map: impl Fn(&str) -> Option<&'a SourceMap>,
+) -> SourceMap {
+  let map = SOURCE_MAP.build_source_map_with_config(mappings, None, SourceMapConfig);
+  let map_file = format!("{}.map", file);
+  let mut map = collapse_source_map(
+    &map,
+    get_input_map,
+    |source| {
+      let source = if nodejs_path::is_absolute(source) {
+        nodejs_path::relative(&nodejs_path::dirname(file), source)
+      } else {
+        source.to_string()
+      };
+      match &options.sourcemap_path_transform {
+        Some(transform) => transform(&source, &map_file),
+        None => source,
+      }
+    },
+    options.sourcemap_exclude_sources,
+  );
+  map.set_file(Some(nodejs_path::basename!(file).as_str()));
+  map
+}
+
+/// Trace every segment of `map` back through the input map of its source, so the result points at the original code
+/// instead of the intermediate code. Sources without an input map are kept as they are.
+pub fn collapse_source_map<'a>(
+  map: &SourceMap,
+  get_input_map: impl Fn(&str) -> Option<&'a SourceMap>,
+  transform_source: impl Fn(&str) -> String,
+  exclude_sources: bool,
+) -> SourceMap {
+  let mut builder = SourceMapBuilder::new(map.get_file());
+  let mut contents_set = HashSet::new();
+
+  map.tokens().for_each(|token| {
+    let source = token.get_source();
+    let input_map = source.and_then(|source| get_input_map(source).map(|map| (source, map)));
+    let (src_line, src_col, source, name, contents) = match input_map {
+      Some((id, input_map)) => {
+        let original = match input_map
+          .lookup_token(token.get_src_line(), token.get_src_col())
+          .filter(|original| original.get_dst_line() == token.get_src_line())
+        {
+          Some(original) => original,
+          // The segment was generated by a transform and has no original location.
+          None => return,
+        };
+        (
+          original.get_src_line(),
+          original.get_src_col(),
+          original
+            .get_source()
+            .map(|source| resolve_source(id, source)),
+          original.get_name().or_else(|| token.get_name()),
+          input_map.get_source_contents(original.get_src_id()),
+        )
+      }
+      None => (
+        token.get_src_line(),
+        token.get_src_col(),
+        source.map(|source| source.to_string()),
+        token.get_name(),
+        map.get_source_contents(token.get_src_id()),
+      ),
+    };
+
+    let source = source.map(|source| transform_source(&source));
+    let raw = builder.add(
+      token.get_dst_line(),
+      token.get_dst_col(),
+      src_line,
+      src_col,
+      source.as_deref(),
+      name,
+    );
+    if source.is_some() && !exclude_sources && contents_set.insert(raw.src_id) {
+      builder.set_source_contents(raw.src_id, contents);
+    }
+  });
+
+  builder.into_sourcemap()
+}
+
+// Sources of an input map are relative to the module it belongs to.
+#[inline]
+fn resolve_source(id: &str, source: &str) -> String {
+  if source.contains("://") || !nodejs_path::is_absolute(id) {
+    source.to_string()
+  } else {
+    nodejs_path::resolve!(&nodejs_path::dirname(id), source)
+  }
+}
+
+/// Read the source map referenced by the `sourceMappingURL` comment of `code`, either inlined as a data URL
+/// or as a file next to the module.
+pub fn read_input_source_map(id: &str, code: &str) -> Option<SourceMap> {
+  let url = SOURCE_MAPPING_URL_RE
+    .captures_iter(code)
+    .last()?
+    .get(1)?
+    .as_str();
+  let content = if let Some(data) = url.strip_prefix("data:") {
+    let (_, encoded) = data.split_once("base64,")?;
+    base64::decode(encoded).ok()?
+  } else {
+    std::fs::read(nodejs_path::resolve!(&nodejs_path::dirname(id), url)).ok()?
+  };
+  SourceMap::from_slice(&content)
+    .map_err(|err| log::warn!("Failed to parse the source map of {}: {}", id, err))
+    .ok()
+}
+
+pub fn source_map_to_string(map: &SourceMap) -> String {
+  let mut buf = vec![];
+  map.to_writer(&mut buf).unwrap();
+  String::from_utf8(buf).unwrap()
+}
+
+pub fn source_map_to_data_url(map: &SourceMap) -> String {
+  format!(
+    "data:application/json;charset=utf-8;base64,{}",
+    base64::encode(source_map_to_string(map))
+  )
+}
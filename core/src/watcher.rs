@@ -0,0 +1,208 @@
+use std::{
+  collections::HashSet,
+  path::Path,
+  sync::{mpsc, Arc, Mutex},
+  time::{Duration, Instant},
+};
+
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode};
+
+use crate::{
+  bundle::Bundle,
+  generate_bundle,
+  graph::{Graph, GraphCache},
+  plugin_driver::{ChangeEvent, PluginDriver},
+  structs::RolldownOutput,
+  types::{NormalizedInputOptions, NormalizedOutputOptions},
+  worker::RolldownError,
+};
+
+// Changes within this period are batched into one rebuild.
+const DEBOUNCE_DELAY: Duration = Duration::from_millis(50);
+
+pub enum WatcherEvent {
+  // The initial build or a rebuild after changes is started.
+  BuildStart,
+  // The bundle is written.
+  BundleEnd {
+    duration: Duration,
+    output: Vec<RolldownOutput>,
+  },
+  // The build failed. The watcher keeps watching and rebuilds after the next change.
+  Error(RolldownError),
+}
+
+pub struct Watcher {
+  // Dropping the inner watcher disconnects the channel, which stops the watching thread.
+  watcher: Arc<Mutex<Option<RecommendedWatcher>>>,
+  plugin_driver: Arc<PluginDriver>,
+}
+
+impl Watcher {
+  /// Build and write the bundle, then rebuild it whenever a module or a file added by
+  /// `Graph::add_watch_file` changes. Only changed modules are parsed again.
+  /// `on_event` is called on the watching thread.
+  pub fn new(
+    input_options: NormalizedInputOptions,
+    output_options: NormalizedOutputOptions,
+    on_event: impl FnMut(WatcherEvent) + Send + 'static,
+  ) -> Result<Self, RolldownError> {
+    if output_options.dir.is_none() && output_options.file.is_none() {
+      return Err(RolldownError::InvalidOption(
+        "You must specify \"output.file\" or \"output.dir\" to watch the build.".to_string(),
+      ));
+    }
+    let plugin_driver = Arc::new(PluginDriver::new(input_options.plugins.clone()));
+    let (tx, rx) = mpsc::channel();
+    let watcher = Arc::new(Mutex::new(Some(
+      notify::watcher(tx, DEBOUNCE_DELAY).map_err(RolldownError::Watch)?,
+    )));
+    let mut state = WatchState {
+      input_options,
+      output_options: Some(output_options),
+      cache: Default::default(),
+      watched: Default::default(),
+      watcher: watcher.clone(),
+      plugin_driver: plugin_driver.clone(),
+      on_event: Box::new(on_event),
+    };
+    std::thread::spawn(move || {
+      state.rebuild(&[]);
+      while let Ok(event) = rx.recv() {
+        let mut changes = vec![];
+        collect_changes(event, &mut changes);
+        while let Ok(event) = rx.try_recv() {
+          collect_changes(event, &mut changes);
+        }
+        if !changes.is_empty() {
+          state.rebuild(&changes);
+        }
+      }
+    });
+    Ok(Self {
+      watcher,
+      plugin_driver,
+    })
+  }
+
+  /// Stop watching, and call the `close_watcher` hook of plugins.
+  pub fn close(&self) -> Result<(), RolldownError> {
+    if self.watcher.lock().unwrap().take().is_some() {
+      self.plugin_driver.close_watcher()?;
+    }
+    Ok(())
+  }
+}
+
+impl Drop for Watcher {
+  fn drop(&mut self) {
+    if let Err(err) = self.close() {
+      log::warn!("{}", err);
+    }
+  }
+}
+
+/// Collect changed paths of a file system event. A later event of the same path replaces the earlier one.
+pub fn collect_changes(event: DebouncedEvent, changes: &mut Vec<(String, ChangeEvent)>) {
+  let events = match event {
+    DebouncedEvent::Create(path) => vec![(path, ChangeEvent::Create)],
+    DebouncedEvent::Write(path) | DebouncedEvent::Chmod(path) => vec![(path, ChangeEvent::Update)],
+    DebouncedEvent::Remove(path) => vec![(path, ChangeEvent::Delete)],
+    DebouncedEvent::Rename(from, to) => {
+      vec![(from, ChangeEvent::Delete), (to, ChangeEvent::Create)]
+    }
+    _ => vec![],
+  };
+  events.into_iter().for_each(|(path, event)| {
+    let id = path.to_string_lossy().to_string();
+    match changes.iter_mut().find(|(changed, _)| changed == &id) {
+      Some(change) => change.1 = event,
+      None => changes.push((id, event)),
+    }
+  });
+}
+
+/// Watch `watch_files`, and unwatch the files of `watched` which are not watched anymore. Ids of virtual
+/// modules, which start with `\0`, and other ids that are not files on disk are skipped.
+pub fn update_watched(
+  watcher: &mut impl notify::Watcher,
+  watched: &mut HashSet<String>,
+  watch_files: HashSet<String>,
+) -> Result<(), RolldownError> {
+  let watch_files = watch_files
+    .into_iter()
+    .filter(|id| !id.starts_with('\0') && Path::new(id).exists())
+    .collect::<HashSet<_>>();
+  for id in watched.difference(&watch_files) {
+    // The file might be removed already.
+    watcher.unwatch(Path::new(id)).ok();
+  }
+  for id in watch_files.difference(watched) {
+    watcher
+      .watch(Path::new(id), RecursiveMode::NonRecursive)
+      .map_err(RolldownError::Watch)?;
+  }
+  *watched = watch_files;
+  Ok(())
+}
+
+struct WatchState {
+  input_options: NormalizedInputOptions,
+  // Taken by the bundle while generating.
+  output_options: Option<NormalizedOutputOptions>,
+  cache: GraphCache,
+  watched: HashSet<String>,
+  watcher: Arc<Mutex<Option<RecommendedWatcher>>>,
+  plugin_driver: Arc<PluginDriver>,
+  on_event: Box<dyn FnMut(WatcherEvent) + Send>,
+}
+
+impl WatchState {
+  fn rebuild(&mut self, changes: &[(String, ChangeEvent)]) {
+    (self.on_event)(WatcherEvent::BuildStart);
+    let start = Instant::now();
+    let event = match self.build(changes) {
+      Ok(output) => WatcherEvent::BundleEnd {
+        duration: start.elapsed(),
+        output,
+      },
+      Err(err) => WatcherEvent::Error(err),
+    };
+    (self.on_event)(event);
+  }
+
+  fn build(
+    &mut self,
+    changes: &[(String, ChangeEvent)],
+  ) -> Result<Vec<RolldownOutput>, RolldownError> {
+    changes
+      .iter()
+      .try_for_each(|(id, event)| self.plugin_driver.watch_change(id, *event))?;
+    let changed_ids = changes
+      .iter()
+      .map(|(id, _)| id.as_str())
+      .collect::<Vec<_>>();
+    let mut cache = std::mem::take(&mut self.cache);
+    cache.invalidate(&changed_ids);
+    let mut graph = Graph::with_cache(self.input_options.clone(), cache);
+    graph.plugin_driver = self.plugin_driver.clone();
+    // A failed build hands the cache back, so it is kept for the next change.
+    let result = graph.try_build();
+    self.cache = graph.cache.take().unwrap_or_default();
+    result?;
+    let watch_files = graph
+      .module_by_id
+      .keys()
+      .map(|id| id.to_string())
+      .chain(graph.watch_files.iter().cloned())
+      .collect::<HashSet<_>>();
+    if let Some(watcher) = self.watcher.lock().unwrap().as_mut() {
+      update_watched(watcher, &mut self.watched, watch_files)?;
+    }
+
+    let mut bundle = Bundle::new(graph, self.output_options.take().unwrap());
+    let output = generate_bundle(true, &mut bundle);
+    self.output_options = Some(bundle.output_options);
+    output
+  }
+}
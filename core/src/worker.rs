@@ -1,19 +1,23 @@
 use std::{
-  fs,
+  collections::HashMap,
   sync::{Arc, Mutex},
 };
 
 use crossbeam::{channel::Sender, queue::SegQueue};
 use dashmap::{DashMap, DashSet};
 use smol_str::SmolStr;
+use swc_atoms::JsWord;
 use swc_common::Mark;
 use swc_ecma_ast::{ModuleDecl, ModuleItem};
 use swc_ecma_visit::VisitMutWith;
 use thiserror::Error;
 
 use crate::{
-  graph::{Msg, Rel},
+  disk_cache::DiskCache,
+  external_module::ExternalModule,
+  graph::{CachedModule, Msg, Rel},
   module::Module,
+  plugin_driver::{location, PluginDriver, Position, TransformResult},
   scanner::{scope::BindType, Scanner},
   symbol_box::SymbolBox,
   types::ResolvedId,
@@ -28,14 +32,59 @@ pub enum RolldownError {
   Channel(crossbeam::channel::SendError<Msg>),
   #[error("[Mutex error]")]
   Lock,
+  #[error(
+    "\"{option}\" was specified for \"output.exports\", but entry module \"{id}\" has the following exports: {exports}"
+  )]
+  InvalidExportOption {
+    option: &'static str,
+    id: String,
+    exports: String,
+  },
+  #[error("[Invalid option] {0}")]
+  InvalidOption(String),
+  #[error("[Parse error] {id}:{line}:{column}: {message}")]
+  Parse {
+    id: String,
+    line: usize,
+    column: usize,
+    message: String,
+  },
+  #[error("[Unsupported syntax] {id}:{line}:{column}: {message}")]
+  UnsupportedSyntax {
+    id: String,
+    line: usize,
+    column: usize,
+    message: String,
+  },
+  #[error("[Watch error `{0}`]")]
+  Watch(notify::Error),
+  #[error("[Invalid config] {file}:{line}:{column}: {message}")]
+  Config {
+    file: String,
+    line: usize,
+    column: usize,
+    message: String,
+  },
+  #[error("[plugin {plugin}] {}{message}", location(.id, .pos))]
+  Plugin {
+    plugin: String,
+    message: String,
+    id: Option<String>,
+    pos: Option<Position>,
+  },
 }
 
+#[derive(Clone)]
 pub struct Worker {
   pub symbol_box: Arc<Mutex<SymbolBox>>,
   pub job_queue: Arc<SegQueue<ResolvedId>>,
   pub tx: Sender<Msg>,
   pub processed_id: Arc<DashSet<SmolStr>>,
   pub mark_to_stmt: Arc<DashMap<Mark, (SmolStr, usize)>>,
+  // Modules of the previous build, which are reused instead of being parsed again.
+  pub cache: Arc<HashMap<SmolStr, CachedModule>>,
+  pub disk_cache: Option<Arc<DiskCache>>,
+  pub plugin_driver: Arc<PluginDriver>,
 }
 
 impl Worker {
@@ -50,108 +99,209 @@ impl Worker {
       })
   }
 
+  // Resolved ids are cached in the module, so later lookups via `Module::resolve_id` agree with the worker.
+  fn resolve_id(&self, module: &Module, source: &JsWord) -> Result<ResolvedId, RolldownError> {
+    let cached = module
+      .resolved_ids
+      .get(source)
+      .map(|resolved_id| resolved_id.clone());
+    if let Some(resolved_id) = cached {
+      return Ok(resolved_id);
+    }
+    let resolved_id = self.resolve_source(source, &module.id)?;
+    module
+      .resolved_ids
+      .insert(source.clone(), resolved_id.clone());
+    Ok(resolved_id)
+  }
+
+  #[inline]
+  fn resolve_source(&self, source: &str, importer: &str) -> Result<ResolvedId, RolldownError> {
+    self.plugin_driver.resolve(source, Some(importer), &[])
+  }
+
+  // Send the module and its dependencies to the graph.
+  fn emit(&self, cached: CachedModule) -> Result<(), RolldownError> {
+    let CachedModule {
+      module,
+      dependencies,
+      marks,
+      ..
+    } = cached;
+    module
+      .resolved_ids
+      .iter()
+      .for_each(|pair| self.job_queue.push(pair.value().clone()));
+    marks.into_iter().for_each(|(mark, idx)| {
+      self
+        .mark_to_stmt
+        .entry(mark)
+        .or_insert_with(|| (module.id.clone(), idx));
+    });
+    dependencies.into_iter().try_for_each(|(dep_id, rel)| {
+      self
+        .tx
+        .send(Msg::DependencyReference(module.id.clone(), dep_id, rel))
+        .map_err(RolldownError::Channel)
+    })?;
+    self
+      .tx
+      .send(Msg::NewMod(Box::new(module)))
+      .map_err(RolldownError::Channel)
+  }
+
   pub fn run(&mut self) -> Result<(), RolldownError> {
     if let Some(resolved_id) = self.fetch_job() {
-      if resolved_id.external {
-        // TODO: external module
+      if let Some(cached) = self.cache.get(&resolved_id.id) {
+        let cached = cached.remark(&mut self.symbol_box.lock().map_err(|_| RolldownError::Lock)?);
+        self.emit(cached)?;
+      } else if resolved_id.external {
+        self
+          .tx
+          .send(Msg::NewExtMod(ExternalModule::new(
+            resolved_id.id.to_string(),
+          )))
+          .map_err(RolldownError::Channel)?;
       } else {
-        let mut module = Module::new(resolved_id.id.clone());
-        let id: &str = &resolved_id.id;
-        let source = fs::read_to_string(id).map_err(RolldownError::IO)?;
-        let mut ast = parse_file(source, &module.id);
-        self.pre_analyze_imported_module(&mut module, &ast);
-
-        let mut scanner = Scanner::new(self.symbol_box.clone(), self.tx.clone());
-        ast.visit_mut_with(&mut scanner);
-
-        scanner
-          .import_infos
-          .iter()
-          .try_for_each(|(imported, info)| {
-            let resolved_id = module.resolve_id(imported);
-            self
-              .tx
-              .send(Msg::DependencyReference(
-                module.id.clone(),
-                resolved_id.id,
-                info.clone().into(),
-              ))
-              .map_err(RolldownError::Channel)
-          })?;
-        scanner
-          .re_export_infos
-          .iter()
-          .try_for_each(|(re_exported, info)| {
-            let resolved_id = module.resolve_id(re_exported);
-            self
-              .tx
-              .send(Msg::DependencyReference(
-                module.id.clone(),
-                resolved_id.id,
-                info.clone().into(),
-              ))
-              .map_err(RolldownError::Channel)
-          })?;
-        scanner
-          .export_all_sources
-          .iter()
-          .try_for_each(|re_exported| {
-            let resolved_id = module.resolve_id(&re_exported.0);
-            self
-              .tx
-              .send(Msg::DependencyReference(
-                module.id.clone(),
-                resolved_id.id,
-                Rel::ReExportAll(re_exported.1),
-              ))
-              .map_err(RolldownError::Channel)
-          })?;
-
-        module.local_exports = scanner.local_exports;
-        module.re_exports = scanner.re_exports;
-        module.re_export_all_sources = scanner
-          .export_all_sources
-          .into_iter()
-          .map(|s| s.0)
-          .collect();
-        {
-          let root_scope = scanner.stacks.into_iter().next().unwrap();
-          let declared_symbols = root_scope.declared_symbols;
-          let mut declared_symbols_kind = root_scope.declared_symbols_kind;
-          declared_symbols.into_iter().for_each(|(name, mark)| {
-            let bind_type = declared_symbols_kind.remove(&name).unwrap();
-            if BindType::Import == bind_type {
-              module.imported_symbols.insert(name, mark);
-            } else {
-              module.declared_symbols.insert(name, mark);
+        let TransformResult {
+          code,
+          map,
+          module_side_effects,
+          meta,
+        } = self.plugin_driver.load_module(&resolved_id.id)?;
+        let set_transform_result = |module: &mut Module| {
+          module.input_map = map;
+          module.module_side_effects = module_side_effects.unwrap_or(true);
+          module.meta = meta.unwrap_or_default();
+        };
+        // Entries are keyed by the transformed code, so they are reused only if the transforms agree.
+        let disk_cache = self
+          .disk_cache
+          .as_ref()
+          .map(|disk_cache| (disk_cache, disk_cache.key(&resolved_id.id, &code)));
+        if let Some((disk_cache, key)) = &disk_cache {
+          if let Some(entry) = disk_cache.get(&resolved_id.id, key) {
+            // Resolving depends on options that are not part of the key, such as `external`.
+            let is_resolved_alike = entry.resolved_ids().all(|(source, resolved, is_dynamic)| {
+              let resolved_again = if is_dynamic {
+                self.plugin_driver.resolve_dynamic(&source, &resolved_id.id)
+              } else {
+                self.resolve_source(&source, &resolved_id.id)
+              };
+              // A failed resolution is reported when the module is parsed again.
+              resolved_again.map_or(false, |resolved_again| resolved_again == resolved)
+            });
+            if is_resolved_alike {
+              let mut cached = entry.into_cached_module(&resolved_id.id, code, &self.symbol_box);
+              set_transform_result(&mut cached.module);
+              return self.emit(cached);
             }
-          });
+          }
+        }
+        let mut cached = self.parse(resolved_id.id, code)?;
+        set_transform_result(&mut cached.module);
+        if let Some((disk_cache, key)) = &disk_cache {
+          disk_cache.set(key, &cached, &self.symbol_box);
         }
-        module.namespace.mark = self
-          .symbol_box
-          .lock()
-          .map_err(|_| RolldownError::Lock)?
-          .new_mark();
+        self.emit(cached)?;
+      }
+    }
+    Ok(())
+  }
 
-        module.set_statements(ast, scanner.statement_infos, self.mark_to_stmt.clone());
+  fn parse(&self, id: SmolStr, code: String) -> Result<CachedModule, RolldownError> {
+    let mut module = Module::new(id);
+    let mut ast = parse_file(code, &module.id)?;
+    self.pre_analyze_imported_module(&mut module, &ast)?;
 
-        module.bind_local_references(&mut self.symbol_box.lock().unwrap());
+    let mut scanner = Scanner::new(self.symbol_box.clone(), self.tx.clone());
+    ast.visit_mut_with(&mut scanner);
+    if let Some(err) = scanner.errors.drain(..).next() {
+      return Err(err);
+    }
 
-        module.link_local_exports();
+    let mut dependencies = vec![];
+    for (imported, info) in scanner.import_infos.iter() {
+      let resolved_id = self.resolve_id(&module, imported)?;
+      dependencies.push((resolved_id.id, info.clone().into()));
+    }
+    for (re_exported, info) in scanner.re_export_infos.iter() {
+      let resolved_id = self.resolve_id(&module, re_exported)?;
+      dependencies.push((resolved_id.id, info.clone().into()));
+    }
+    for re_exported in scanner.export_all_sources.iter() {
+      let resolved_id = self.resolve_id(&module, &re_exported.0)?;
+      dependencies.push((resolved_id.id, Rel::ReExportAll(re_exported.1)));
+    }
+    for desc in scanner.dynamic_imports.iter() {
+      let cached = module
+        .resolved_ids
+        .get(&desc.argument)
+        .map(|resolved_id| resolved_id.clone());
+      let resolved_id = match cached {
+        Some(resolved_id) => resolved_id,
+        None => {
+          let resolved_id = self
+            .plugin_driver
+            .resolve_dynamic(&desc.argument, &module.id)?;
+          module
+            .resolved_ids
+            .insert(desc.argument.clone(), resolved_id.clone());
+          resolved_id
+        }
+      };
+      self.job_queue.push(resolved_id.clone());
+      dependencies.push((resolved_id.id, Rel::DynImport));
+    }
 
-        log::debug!("[worker]: emit module {:#?}", module);
-        self
-          .tx
-          .send(Msg::NewMod(Box::new(module)))
-          .map_err(RolldownError::Channel)?;
-      }
+    module.local_exports = scanner.local_exports;
+    module.re_exports = scanner.re_exports;
+    module.re_export_all_sources = scanner
+      .export_all_sources
+      .into_iter()
+      .map(|s| s.0)
+      .collect();
+    {
+      let root_scope = scanner.stacks.into_iter().next().unwrap();
+      let declared_symbols = root_scope.declared_symbols;
+      let mut declared_symbols_kind = root_scope.declared_symbols_kind;
+      declared_symbols.into_iter().for_each(|(name, mark)| {
+        let bind_type = declared_symbols_kind.remove(&name).unwrap();
+        if BindType::Import == bind_type {
+          module.imported_symbols.insert(name, mark);
+        } else {
+          module.declared_symbols.insert(name, mark);
+        }
+      });
     }
-    Ok(())
+    module.namespace.mark = self
+      .symbol_box
+      .lock()
+      .map_err(|_| RolldownError::Lock)?
+      .new_mark();
+
+    let marks = module.set_statements(ast, scanner.statement_infos, self.mark_to_stmt.clone());
+
+    module.bind_local_references(&mut self.symbol_box.lock().unwrap());
+
+    module.link_local_exports();
+
+    log::debug!("[worker]: emit module {:#?}", module);
+    Ok(CachedModule {
+      module,
+      dependencies,
+      marks,
+      unions: Default::default(),
+    })
   }
 
   // Fast path for analyzing static import and export.
-  pub fn pre_analyze_imported_module(&self, module: &mut Module, ast: &swc_ecma_ast::Module) {
-    ast.body.iter().for_each(|module_item| {
+  pub fn pre_analyze_imported_module(
+    &self,
+    module: &mut Module,
+    ast: &swc_ecma_ast::Module,
+  ) -> Result<(), RolldownError> {
+    ast.body.iter().try_for_each(|module_item| {
       if let ModuleItem::ModuleDecl(module_decl) = module_item {
         let mut depended = None;
         match module_decl {
@@ -169,10 +319,11 @@ impl Worker {
           _ => {}
         }
         if let Some(depended) = depended {
-          let resolved_id = module.resolve_id(depended);
+          let resolved_id = self.resolve_id(module, depended)?;
           self.job_queue.push(resolved_id);
         }
       }
-    });
+      Ok(())
+    })
   }
 }
@@ -0,0 +1,28 @@
+mod common;
+
+use rolldown::types::{Addon, InternalModuleFormat, NormalizedOutputOptions};
+
+fn generate(output_options: NormalizedOutputOptions) -> String {
+  common::generate_code("./tests/fixtures/code_splitting/shared.js", output_options)
+}
+
+#[test]
+fn addons_around_wrapper() {
+  let code = generate(NormalizedOutputOptions {
+    format: InternalModuleFormat::IIFE,
+    name: Some("Shared".to_string()),
+    banner: Some("/*! license */".into()),
+    footer: Some("// footer".into()),
+    intro: Some("var intro = true;".into()),
+    outro: Some(Addon::Fn(Box::new(|chunk| {
+      format!("var exported = '{}';", chunk.exports.join(","))
+    }))),
+    ..Default::default()
+  });
+  assert!(code.starts_with("/*! license */\nvar Shared = (function (exports) {"));
+  assert!(code.trim_end().ends_with("// footer"));
+  let intro = code.find("var intro = true;").unwrap();
+  let outro = code.find("var exported = 'shared';").unwrap();
+  assert!(intro < code.find("const shared = 'shared';").unwrap());
+  assert!(outro < code.find("return exports;").unwrap());
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use rolldown::{
+  structs::{EmittedAsset, RolldownOutput},
+  types::NormalizedOutputOptions,
+  RolldownBuild,
+};
+
+fn generate(asset: EmittedAsset, output_options: NormalizedOutputOptions) -> Vec<RolldownOutput> {
+  let build = RolldownBuild::new(common::input_options(&[
+    "./tests/fixtures/code_splitting/shared.js",
+  ]));
+  build.graph.emit_asset(asset);
+  build.generate(output_options).unwrap()
+}
+
+#[test]
+fn asset_file_names() {
+  let output = generate(
+    EmittedAsset {
+      name: Some("logo.png".to_string()),
+      file_name: None,
+      source: vec![0x89, b'P', b'N', b'G', 0xff],
+    },
+    NormalizedOutputOptions {
+      asset_file_names: "static/[name].[hash:6].[ext]".to_string(),
+      ..Default::default()
+    },
+  );
+  assert_eq!(output.len(), 2);
+  let file_name = output[1].get_file_name();
+  assert!(file_name.starts_with("static/logo."));
+  assert!(file_name.ends_with(".png"));
+  assert_eq!(file_name.len(), "static/logo.123456.png".len());
+  assert_eq!(output[1].get_source(), &[0x89, b'P', b'N', b'G', 0xff]);
+}
+
+#[test]
+fn asset_with_file_name() {
+  let output = generate(
+    EmittedAsset {
+      name: None,
+      file_name: Some("robots.txt".to_string()),
+      source: b"User-agent: *".to_vec(),
+    },
+    Default::default(),
+  );
+  assert_eq!(output[1].get_file_name(), "robots.txt");
+  assert_eq!(output[1].get_content(), "User-agent: *");
+}
@@ -0,0 +1,248 @@
+mod common;
+
+use std::sync::Arc;
+
+use rolldown::{
+  plugin_driver::{Plugin, PluginContext},
+  structs::RolldownOutput,
+  types::{
+    InternalModuleFormat, ManualChunksOption, NormalizedInputOptions, NormalizedOutputOptions,
+    ResolveIdResult,
+  },
+  utils::resolve_id,
+  worker::RolldownError,
+  RolldownBuild,
+};
+
+// Outputs sorted by file name.
+fn generate(output_options: NormalizedOutputOptions) -> Vec<(String, String)> {
+  let mut output = common::generate_files(
+    &[
+      "./tests/fixtures/code_splitting/a.js",
+      "./tests/fixtures/code_splitting/b.js",
+    ],
+    output_options,
+  );
+  output.sort();
+  output
+}
+
+#[test]
+fn shared_chunk() {
+  let output = generate(NormalizedOutputOptions {
+    chunk_file_names: "[name].js".to_string(),
+    ..Default::default()
+  });
+  let file_names = output
+    .iter()
+    .map(|(file_name, _)| file_name.as_str())
+    .collect::<Vec<_>>();
+  assert_eq!(file_names, vec!["a.js", "b.js", "shared.js"]);
+  let (_, a) = &output[0];
+  let (_, shared) = &output[2];
+  assert!(a.starts_with("import { shared } from './shared.js';"));
+  assert!(a.contains("const a = shared + 'a';"));
+  assert!(a.contains("export { a };"));
+  assert!(shared.contains("const shared = 'shared';"));
+  assert!(shared.contains("export { shared };"));
+}
+
+#[test]
+fn shared_chunk_cjs() {
+  let output = generate(NormalizedOutputOptions {
+    format: InternalModuleFormat::CJS,
+    chunk_file_names: "[name].js".to_string(),
+    ..Default::default()
+  });
+  let (_, b) = &output[1];
+  assert!(b.contains("var shared = require('./shared.js');"));
+  assert!(b.contains("shared.shared + 'b'"));
+}
+
+#[test]
+fn hashed_chunk_file_names() {
+  let output = generate(Default::default());
+  let (shared_file_name, _) = &output[2];
+  let hash = shared_file_name
+    .strip_prefix("shared-")
+    .and_then(|rest| rest.strip_suffix(".js"))
+    .unwrap();
+  assert_eq!(hash.len(), 8);
+  assert!(hash.chars().all(|c| c.is_ascii_hexdigit()));
+  let (_, a) = &output[0];
+  assert!(a.starts_with(&format!(
+    "import {{ shared }} from './{}';",
+    shared_file_name
+  )));
+  // Hashes are stable across builds.
+  assert_eq!(generate(Default::default()), output);
+}
+
+#[test]
+fn manual_chunks_map() {
+  let output = generate(NormalizedOutputOptions {
+    manual_chunks: Some(ManualChunksOption::Map(
+      [(
+        "vendor".to_string(),
+        vec!["./tests/fixtures/code_splitting/shared.js".to_string()],
+      )]
+      .into_iter()
+      .collect(),
+    )),
+    chunk_file_names: "[name].js".to_string(),
+    ..Default::default()
+  });
+  let file_names = output
+    .iter()
+    .map(|(file_name, _)| file_name.as_str())
+    .collect::<Vec<_>>();
+  assert_eq!(file_names, vec!["a.js", "b.js", "vendor.js"]);
+  assert!(output[0]
+    .1
+    .starts_with("import { shared } from './vendor.js';"));
+}
+
+// Resolves `shared` to the shared module of the fixture.
+struct Alias;
+
+impl Plugin for Alias {
+  fn get_name(&self) -> &'static str {
+    "alias"
+  }
+
+  fn resolve_id(
+    &self,
+    _ctx: &PluginContext,
+    source: &str,
+    _importer: Option<&str>,
+  ) -> Result<ResolveIdResult, RolldownError> {
+    Ok(
+      (source == "shared")
+        .then(|| resolve_id("./tests/fixtures/code_splitting/shared.js", None, false)),
+    )
+  }
+}
+
+#[test]
+fn manual_chunks_map_resolved_by_plugin() {
+  let build = RolldownBuild::new(NormalizedInputOptions {
+    input: vec![
+      "./tests/fixtures/code_splitting/a.js".to_string(),
+      "./tests/fixtures/code_splitting/b.js".to_string(),
+    ],
+    plugins: vec![Arc::new(Alias)],
+    ..Default::default()
+  });
+  let output = build
+    .generate(NormalizedOutputOptions {
+      manual_chunks: Some(ManualChunksOption::Map(
+        [("vendor".to_string(), vec!["shared".to_string()])]
+          .into_iter()
+          .collect(),
+      )),
+      chunk_file_names: "[name].js".to_string(),
+      ..Default::default()
+    })
+    .unwrap();
+  let mut file_names = output
+    .iter()
+    .map(|output| output.get_file_name())
+    .collect::<Vec<_>>();
+  file_names.sort_unstable();
+  assert_eq!(file_names, vec!["a.js", "b.js", "vendor.js"]);
+}
+
+#[test]
+fn manual_chunks_fn() {
+  let output = generate(NormalizedOutputOptions {
+    manual_chunks: Some(ManualChunksOption::Fn(Box::new(|id, ctx| {
+      let info = ctx.get_module_info(id).unwrap();
+      // Modules imported by multiple entries
+      (info.importers.len() > 1).then(|| "common".to_string())
+    }))),
+    chunk_file_names: "[name].js".to_string(),
+    ..Default::default()
+  });
+  let file_names = output
+    .iter()
+    .map(|(file_name, _)| file_name.as_str())
+    .collect::<Vec<_>>();
+  assert_eq!(file_names, vec!["a.js", "b.js", "common.js"]);
+}
+
+#[test]
+fn multiple_chunks_with_iife() {
+  let build = RolldownBuild::new(NormalizedInputOptions {
+    input: vec![
+      "./tests/fixtures/code_splitting/a.js".to_string(),
+      "./tests/fixtures/code_splitting/b.js".to_string(),
+    ],
+    ..Default::default()
+  });
+  let result = build.generate(NormalizedOutputOptions {
+    format: InternalModuleFormat::IIFE,
+    ..Default::default()
+  });
+  assert!(result.is_err());
+}
+
+fn generate_dynamic_import(output_options: NormalizedOutputOptions) -> Vec<RolldownOutput> {
+  let build = RolldownBuild::new(NormalizedInputOptions {
+    input: vec!["./tests/fixtures/dynamic_import/main.js".to_string()],
+    ..Default::default()
+  });
+  build.generate(output_options).unwrap()
+}
+
+#[test]
+fn dynamic_import() {
+  let output = generate_dynamic_import(NormalizedOutputOptions {
+    chunk_file_names: "[name].js".to_string(),
+    ..Default::default()
+  });
+  let file_names = output
+    .iter()
+    .map(|output| output.get_file_name())
+    .collect::<Vec<_>>();
+  assert_eq!(file_names, vec!["main.js", "lazy.js"]);
+  let main = output[0].get_content();
+  assert!(main.contains("import(") && main.contains("./lazy.js"));
+  assert!(output[1].get_content().contains("export { lazy };"));
+}
+
+#[test]
+fn dynamic_import_cjs() {
+  let output = generate_dynamic_import(NormalizedOutputOptions {
+    format: InternalModuleFormat::CJS,
+    ..Default::default()
+  });
+  let main = output[0].get_content();
+  assert!(main.contains("Promise.resolve().then(") && main.contains("require("));
+  assert!(!main.contains("import("));
+}
+
+#[test]
+fn inline_dynamic_imports() {
+  let output = generate_dynamic_import(NormalizedOutputOptions {
+    inline_dynamic_imports: true,
+    ..Default::default()
+  });
+  assert_eq!(output.len(), 1);
+  let main = output[0].get_content();
+  assert!(main.contains("const lazy = 'lazy';"));
+  assert!(main.contains("Object.freeze("));
+  assert!(main.contains("Promise.resolve().then(") && !main.contains("import("));
+}
+
+#[test]
+fn invalid_file_name_pattern() {
+  let build = RolldownBuild::new(NormalizedInputOptions {
+    input: vec!["./tests/fixtures/code_splitting/a.js".to_string()],
+    ..Default::default()
+  });
+  let result = build.generate(NormalizedOutputOptions {
+    entry_file_names: "[name]-[ext].js".to_string(),
+    ..Default::default()
+  });
+  assert!(result.is_err());
+}
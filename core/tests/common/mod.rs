@@ -0,0 +1,44 @@
+// Helpers shared by the integration tests. Each test crate uses only some of them.
+#![allow(dead_code)]
+
+use rolldown::{
+  structs::RolldownOutput,
+  types::{NormalizedInputOptions, NormalizedOutputOptions},
+  RolldownBuild,
+};
+
+pub fn input_options(input: &[&str]) -> NormalizedInputOptions {
+  NormalizedInputOptions {
+    input: input.iter().map(|input| input.to_string()).collect(),
+    ..Default::default()
+  }
+}
+
+pub fn generate(input: &[&str], output_options: NormalizedOutputOptions) -> Vec<RolldownOutput> {
+  RolldownBuild::new(input_options(input))
+    .generate(output_options)
+    .unwrap()
+}
+
+// Content of the first chunk.
+pub fn generate_code(input: &str, output_options: NormalizedOutputOptions) -> String {
+  generate(&[input], output_options)[0]
+    .get_content()
+    .to_string()
+}
+
+// (file name, content) of every output in the order they are generated.
+pub fn generate_files(
+  input: &[&str],
+  output_options: NormalizedOutputOptions,
+) -> Vec<(String, String)> {
+  generate(input, output_options)
+    .into_iter()
+    .map(|output| {
+      (
+        output.get_file_name().to_string(),
+        output.get_content().to_string(),
+      )
+    })
+    .collect()
+}
@@ -0,0 +1,63 @@
+use rolldown::{
+  types::{load_config_file, InternalModuleFormat, SourceMapMode},
+  worker::RolldownError,
+  RolldownBuild,
+};
+
+#[test]
+fn json_config_with_multiple_outputs() {
+  let configs = load_config_file("./tests/fixtures/config/rolldown.config.json").unwrap();
+  assert_eq!(configs.len(), 2);
+
+  let (input_options, outputs) = configs[0].clone().normalize().unwrap();
+  assert_eq!(
+    input_options.input,
+    vec!["./tests/fixtures/code_splitting/a.js"]
+  );
+  assert_eq!(outputs.len(), 2);
+  assert_eq!(outputs[0].format, InternalModuleFormat::CJS);
+  assert_eq!(outputs[0].sourcemap, SourceMapMode::Hidden);
+  let minify = outputs[1].minify.as_ref().unwrap();
+  assert!(minify.compress && !minify.mangle);
+
+  let (input_options, mut outputs) = configs[1].clone().normalize().unwrap();
+  assert_eq!(outputs[0].name.as_deref(), Some("B"));
+  let output = RolldownBuild::new(input_options)
+    .generate(outputs.remove(0))
+    .unwrap();
+  // `shared.js` is external, so it's loaded from a global variable.
+  assert!(!output[0].get_content().contains("const shared = 'shared';"));
+}
+
+#[test]
+fn toml_config() {
+  let configs = load_config_file("./tests/fixtures/config/rolldown.config.toml").unwrap();
+  let (input_options, outputs) = configs[0].clone().normalize().unwrap();
+  assert!(input_options.treeshake);
+  assert_eq!(outputs[0].format, InternalModuleFormat::UMD);
+  assert_eq!(outputs[0].sourcemap, SourceMapMode::File);
+}
+
+#[test]
+fn unknown_key() {
+  let err = load_config_file("./tests/fixtures/config/unknown_key.json").unwrap_err();
+  match err {
+    RolldownError::Config { line, message, .. } => {
+      assert_eq!(line, 4);
+      assert!(message.contains("unknown field `fromat`"));
+    }
+    _ => panic!("unexpected error: {}", err),
+  }
+}
+
+#[test]
+fn invalid_value() {
+  let err = load_config_file("./tests/fixtures/config/invalid_format.toml").unwrap_err();
+  match err {
+    RolldownError::Config { line, message, .. } => {
+      assert_eq!(line, 7);
+      assert!(message.contains("Invalid value \"mjs\" for option \"output.format\""));
+    }
+    _ => panic!("unexpected error: {}", err),
+  }
+}
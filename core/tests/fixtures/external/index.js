@@ -0,0 +1,6 @@
+import { useState } from 'react'
+import path from 'path'
+
+export const a = useState(path.sep)
+
+export default a
@@ -0,0 +1,11 @@
+export let count = 0;
+
+export function increment() {
+  count++;
+}
+
+export function reset() {
+  let other = count;
+  other = 0;
+  count = other;
+}
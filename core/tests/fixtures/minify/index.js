@@ -0,0 +1,5 @@
+export function sum(firstValue, secondValue) {
+  const intermediateResult = firstValue + secondValue;
+  return intermediateResult;
+}
+export const result = sum(1, 2);
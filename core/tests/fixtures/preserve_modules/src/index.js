@@ -0,0 +1,2 @@
+import { add } from './utils/math';
+export const three = add(1, 2);
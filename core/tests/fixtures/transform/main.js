@@ -0,0 +1,2 @@
+import './polyfill';
+export const value = __VALUE__;
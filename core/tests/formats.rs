@@ -0,0 +1,244 @@
+mod common;
+
+use rolldown::{
+  graph::Graph,
+  types::{
+    AmdOptions, ExportMode, InternalModuleFormat, NormalizedInputOptions, NormalizedOutputOptions,
+  },
+  worker::RolldownError,
+  RolldownBuild,
+};
+
+use common::generate_code as generate;
+
+#[test]
+fn cjs() {
+  let code = generate(
+    "./tests/fixtures/external/index.js",
+    NormalizedOutputOptions {
+      format: InternalModuleFormat::CJS,
+      ..Default::default()
+    },
+  );
+  assert!(code.starts_with("'use strict';"));
+  assert!(code.contains("Object.defineProperty(exports, '__esModule', { value: true });"));
+  assert!(code.contains("var react = require('react');"));
+  assert!(code.contains("var path__default = _interopDefault(path);"));
+  assert!(code.contains("react.useState(path__default.sep)"));
+  assert!(code.contains("exports.a = a;"));
+  assert!(code.contains("exports.default = a;"));
+}
+
+#[test]
+fn cjs_default_export_mode_with_named_exports() {
+  let build = RolldownBuild::new(NormalizedInputOptions {
+    input: vec!["./tests/fixtures/external/index.js".to_string()],
+    ..Default::default()
+  });
+  let result = build.generate(NormalizedOutputOptions {
+    format: InternalModuleFormat::CJS,
+    exports: ExportMode::Default,
+    ..Default::default()
+  });
+  assert!(result.is_err());
+}
+
+#[test]
+fn string_export_name() {
+  let err = Graph::new(NormalizedInputOptions {
+    input: vec!["./tests/fixtures/string_export_name/index.js".to_string()],
+    ..Default::default()
+  })
+  .try_build()
+  .unwrap_err();
+  assert!(err
+    .to_string()
+    .ends_with("index.js:2:15: String export name \"a-b\" is not supported"));
+}
+
+#[test]
+fn umd() {
+  let code = generate(
+    "./tests/fixtures/external/index.js",
+    NormalizedOutputOptions {
+      format: InternalModuleFormat::UMD,
+      name: Some("my.lib".to_string()),
+      globals: [("react".to_string(), "React".to_string())]
+        .into_iter()
+        .collect(),
+      ..Default::default()
+    },
+  );
+  assert!(code.contains("factory(exports, require('react'), require('path'))"));
+  assert!(code.contains("define(['exports', 'react', 'path'], factory)"));
+  assert!(code.contains(
+    "factory((global.my = global.my || {}, global.my.lib = {}), global.React, global.path)"
+  ));
+  assert!(code.contains("(function (exports, react, path) {"));
+}
+
+#[test]
+fn umd_without_name() {
+  let build = RolldownBuild::new(NormalizedInputOptions {
+    input: vec!["./tests/fixtures/external/index.js".to_string()],
+    ..Default::default()
+  });
+  let result = build.generate(NormalizedOutputOptions {
+    format: InternalModuleFormat::UMD,
+    ..Default::default()
+  });
+  assert!(result.is_err());
+}
+
+#[test]
+fn iife() {
+  let code = generate(
+    "./tests/fixtures/external/index.js",
+    NormalizedOutputOptions {
+      format: InternalModuleFormat::IIFE,
+      name: Some("MyLib".to_string()),
+      globals: [
+        ("react".to_string(), "React".to_string()),
+        ("path".to_string(), "Path".to_string()),
+      ]
+      .into_iter()
+      .collect(),
+      ..Default::default()
+    },
+  );
+  assert!(code.starts_with("var MyLib = (function (exports, react, path) {"));
+  assert!(code.contains("return exports;"));
+  assert!(code.trim_end().ends_with("})({}, React, Path);"));
+}
+
+#[test]
+fn iife_extend_with_dotted_name() {
+  let code = generate(
+    "./tests/fixtures/external/index.js",
+    NormalizedOutputOptions {
+      format: InternalModuleFormat::IIFE,
+      name: Some("my.lib".to_string()),
+      extend: true,
+      globals: [
+        ("react".to_string(), "React".to_string()),
+        ("path".to_string(), "Path".to_string()),
+      ]
+      .into_iter()
+      .collect(),
+      ..Default::default()
+    },
+  );
+  assert!(code.starts_with("this.my = this.my || {};\n(function (exports, react, path) {"));
+  assert!(code
+    .trim_end()
+    .ends_with("})(this.my.lib = this.my.lib || {}, React, Path);"));
+}
+
+#[test]
+fn iife_with_dotted_name() {
+  let code = generate(
+    "./tests/fixtures/external/index.js",
+    NormalizedOutputOptions {
+      format: InternalModuleFormat::IIFE,
+      name: Some("a.b.c".to_string()),
+      globals: [
+        ("react".to_string(), "React".to_string()),
+        ("path".to_string(), "Path".to_string()),
+      ]
+      .into_iter()
+      .collect(),
+      ..Default::default()
+    },
+  );
+  assert!(code.starts_with(
+    "this.a = this.a || {};\nthis.a.b = this.a.b || {};\nthis.a.b.c = (function (exports, react, path) {"
+  ));
+  assert!(code.trim_end().ends_with("})({}, React, Path);"));
+}
+
+#[test]
+fn invalid_global_name() {
+  let build = RolldownBuild::new(NormalizedInputOptions {
+    input: vec!["./tests/fixtures/external/index.js".to_string()],
+    ..Default::default()
+  });
+  for format in [InternalModuleFormat::IIFE, InternalModuleFormat::UMD] {
+    for name in ["my-lib", "my.lib-name", "my..lib"] {
+      let result = build.generate(NormalizedOutputOptions {
+        format,
+        name: Some(name.to_string()),
+        ..Default::default()
+      });
+      assert!(matches!(result, Err(RolldownError::InvalidOption(_))));
+    }
+  }
+}
+
+#[test]
+fn amd() {
+  let code = generate(
+    "./tests/fixtures/external/index.js",
+    NormalizedOutputOptions {
+      format: InternalModuleFormat::AMD,
+      amd: AmdOptions {
+        id: Some("my-lib".to_string()),
+        define: "requirejs.define".to_string(),
+        ..Default::default()
+      },
+      ..Default::default()
+    },
+  );
+  assert!(code.starts_with(
+    "requirejs.define('my-lib', ['exports', 'react', 'path'], (function (exports, react, path) { 'use strict';"
+  ));
+  assert!(code.contains("exports.a = a;"));
+}
+
+#[test]
+fn amd_auto_id() {
+  let code = generate(
+    "./tests/fixtures/external/index.js",
+    NormalizedOutputOptions {
+      format: InternalModuleFormat::AMD,
+      amd: AmdOptions {
+        auto_id: true,
+        ..Default::default()
+      },
+      ..Default::default()
+    },
+  );
+  assert!(code.starts_with("define('index', ['exports', 'react', 'path'],"));
+}
+
+#[test]
+fn system() {
+  let code = generate(
+    "./tests/fixtures/external/index.js",
+    NormalizedOutputOptions {
+      format: InternalModuleFormat::System,
+      ..Default::default()
+    },
+  );
+  assert!(code.starts_with("System.register(['react', 'path'], (function (exports, module) {"));
+  assert!(code.contains("var useState, path;"));
+  assert!(code.contains("useState = module.useState;"));
+  assert!(code.contains("path = module['default'];"));
+  assert!(code.contains("exports({ 'a': a, 'default': a });"));
+}
+
+#[test]
+fn system_live_bindings() {
+  let code = generate(
+    "./tests/fixtures/live_binding/main.js",
+    NormalizedOutputOptions {
+      format: InternalModuleFormat::System,
+      ..Default::default()
+    },
+  )
+  .replace('"', "'");
+  // `count` is reassigned after the chunk is executed.
+  assert!(code.contains("(exports('count', +count + 1), count++);"));
+  assert!(code.contains("exports('count', count = other);"));
+  assert!(code.contains("other = 0;") && !code.contains("exports('other'"));
+  assert!(code.contains("exports({ 'count': count, 'increment': increment, 'reset': reset });"));
+}
@@ -0,0 +1,37 @@
+use rolldown::{hmr::DevBuild, types::NormalizedInputOptions};
+
+#[test]
+fn rebuild_changed_modules() {
+  // Removed when the test ends.
+  let dir = tempfile::tempdir().unwrap();
+  let main = dir.path().join("main.js");
+  let counter = dir.path().join("counter.js");
+  std::fs::write(
+    &main,
+    "import { count } from './counter.js';\nconsole.log(count);\nif (import.meta.hot) {\n  import.meta.hot.accept('./counter.js');\n}\n",
+  )
+  .unwrap();
+  std::fs::write(&counter, "export const count = 1;\n").unwrap();
+
+  let mut build = DevBuild::new(NormalizedInputOptions {
+    input: vec![main.to_string_lossy().to_string()],
+    ..Default::default()
+  })
+  .unwrap();
+  let script = build.script();
+  assert!(script.contains("module.hot.accept('./counter.js')"));
+  assert!(!script.contains("import.meta"));
+  assert!(script.contains("__rolldown_hmr__.start([\"main.js\"]);"));
+  assert_eq!(build.entries(), ["main.js".to_string()]);
+  assert!(build
+    .watch_files()
+    .contains(&counter.to_string_lossy().to_string()));
+
+  std::fs::write(&counter, "export const count = 2;\n").unwrap();
+  let changed = build
+    .rebuild(&[counter.to_string_lossy().as_ref()])
+    .unwrap();
+  assert_eq!(changed.len(), 1);
+  assert_eq!(changed[0].file_name, "counter.js");
+  assert!(changed[0].code.contains("count = 2"));
+}
@@ -0,0 +1,248 @@
+use std::{
+  collections::HashMap,
+  sync::{Arc, Mutex},
+};
+
+use rolldown::{
+  bundle::Bundle,
+  disk_cache::DiskCache,
+  graph::Graph,
+  plugin_driver::{Plugin, PluginContext, TransformResult},
+  types::NormalizedInputOptions,
+  utils::resolve_id,
+  worker::RolldownError,
+  RolldownBuild,
+};
+
+fn input_options() -> NormalizedInputOptions {
+  NormalizedInputOptions {
+    input: vec![
+      "./tests/fixtures/code_splitting/a.js".to_string(),
+      "./tests/fixtures/code_splitting/b.js".to_string(),
+    ],
+    ..Default::default()
+  }
+}
+
+fn generate(graph: Graph) -> Vec<String> {
+  Bundle::new(graph, Default::default())
+    .generate()
+    .unwrap()
+    .iter()
+    .map(|output| output.get_content().to_string())
+    .collect()
+}
+
+#[test]
+fn rebuild_with_cache() {
+  let mut graph = Graph::with_cache(input_options(), Default::default());
+  graph.build();
+  let mut cache = graph.cache.take().unwrap();
+  let first = generate(graph);
+
+  let a = resolve_id("./tests/fixtures/code_splitting/a.js", None, false).id;
+  let shared = resolve_id("./tests/fixtures/code_splitting/shared.js", None, false).id;
+  assert!(cache.invalidate(&[&a]));
+  assert!(!cache.contains(&a));
+  assert!(cache.contains(&shared));
+
+  let mut graph = Graph::with_cache(input_options(), cache);
+  graph.build();
+  assert!(graph.cache.as_ref().unwrap().contains(&a));
+  assert_eq!(generate(graph), first);
+}
+
+#[test]
+fn rolldown_build_rebuild() {
+  let mut build = RolldownBuild::new(input_options());
+  let first = build.generate(Default::default()).unwrap();
+  // The linked graph is bundled again.
+  let second = build.generate(Default::default()).unwrap();
+  assert_eq!(first[0].get_content(), second[0].get_content());
+
+  let shared = resolve_id("./tests/fixtures/code_splitting/shared.js", None, false).id;
+  build.rebuild(&[&shared]).unwrap();
+  let third = build.generate(Default::default()).unwrap();
+  assert_eq!(first.len(), third.len());
+  assert_eq!(first[2].get_content(), third[2].get_content());
+}
+
+#[test]
+fn disk_cache() {
+  let temp_dir = tempfile::tempdir().unwrap();
+  let cache_dir = temp_dir.path().join("cache");
+  let generate_with_cache = || {
+    RolldownBuild::new(NormalizedInputOptions {
+      cache_dir: Some(cache_dir.to_string_lossy().to_string()),
+      ..input_options()
+    })
+    .generate(Default::default())
+    .unwrap()
+    .iter()
+    .map(|output| output.get_content().to_string())
+    .collect::<Vec<_>>()
+  };
+  let first = generate_with_cache();
+  assert_eq!(std::fs::read_dir(&cache_dir).unwrap().count(), 3);
+  // Modules are read from the cache, with marks of the new build.
+  assert_eq!(generate_with_cache(), first);
+
+  // Entries with invalid marks are parsed again and replaced.
+  let namespace_marks = || {
+    std::fs::read_dir(&cache_dir)
+      .unwrap()
+      .map(|entry| {
+        let content = std::fs::read(entry.unwrap().path()).unwrap();
+        serde_json::from_slice::<serde_json::Value>(&content).unwrap()["namespace"][1].clone()
+      })
+      .collect::<Vec<_>>()
+  };
+  std::fs::read_dir(&cache_dir).unwrap().for_each(|entry| {
+    let path = entry.unwrap().path();
+    let mut value =
+      serde_json::from_slice::<serde_json::Value>(&std::fs::read(&path).unwrap()).unwrap();
+    value["namespace"][1] = 0.into();
+    std::fs::write(&path, serde_json::to_vec(&value).unwrap()).unwrap();
+  });
+  assert_eq!(generate_with_cache(), first);
+  assert!(namespace_marks().iter().all(|mark| mark != 0));
+}
+
+struct Versioned(&'static str);
+
+impl Plugin for Versioned {
+  fn get_name(&self) -> &'static str {
+    "versioned"
+  }
+
+  fn get_version(&self) -> Option<&'static str> {
+    Some(self.0)
+  }
+}
+
+#[test]
+fn disk_cache_key() {
+  let key = |options: NormalizedInputOptions| DiskCache::new("cache", &options).key("a.js", "code");
+  let with_plugin = |version| NormalizedInputOptions {
+    plugins: vec![Arc::new(Versioned(version))],
+    ..input_options()
+  };
+  assert_eq!(key(with_plugin("1.0.0")), key(with_plugin("1.0.0")));
+  assert_ne!(key(with_plugin("1.0.0")), key(with_plugin("1.1.0")));
+  assert_ne!(key(input_options()), key(with_plugin("1.0.0")));
+  assert_ne!(
+    key(input_options()),
+    key(NormalizedInputOptions {
+      treeshake: true,
+      ..input_options()
+    })
+  );
+}
+
+#[test]
+fn try_new_with_missing_entry() {
+  let result = RolldownBuild::try_new(NormalizedInputOptions {
+    input: vec!["./tests/fixtures/dynamic_import/missing.js".to_string()],
+    ..Default::default()
+  });
+  assert!(result.is_err());
+}
+
+#[test]
+fn rebuild_through_re_export_chain() {
+  let temp_dir = tempfile::tempdir().unwrap();
+  let dir = temp_dir.path();
+  std::fs::write(
+    dir.join("c.js"),
+    "import { value } from './b.js';\nconsole.log(value);\n",
+  )
+  .unwrap();
+  std::fs::write(dir.join("b.js"), "export { value } from './shared.js';\n").unwrap();
+  std::fs::write(dir.join("shared.js"), "export const value = 1;\n").unwrap();
+  let input_options = || NormalizedInputOptions {
+    input: vec![dir.join("c.js").to_string_lossy().to_string()],
+    ..Default::default()
+  };
+  let mut build = RolldownBuild::new(input_options());
+  build.generate(Default::default()).unwrap();
+
+  // `c.js` is kept in the cache, and reaches the new export through `b.js`.
+  std::fs::write(
+    dir.join("shared.js"),
+    "const value = 'unused';\nconst next = 2;\nexport { next as value };\n",
+  )
+  .unwrap();
+  let shared = resolve_id(&dir.join("shared.js").to_string_lossy(), None, false).id;
+  build.rebuild(&[&shared]).unwrap();
+  let rebuilt = build.generate(Default::default()).unwrap();
+  let fresh = RolldownBuild::new(input_options())
+    .generate(Default::default())
+    .unwrap();
+  assert_eq!(rebuilt[0].get_content(), fresh[0].get_content());
+  assert!(rebuilt[0].get_content().contains("const next = 2;"));
+}
+
+// Counts transformed modules by file name.
+#[derive(Default)]
+struct Count {
+  transformed: Mutex<HashMap<String, usize>>,
+}
+
+impl Plugin for Count {
+  fn get_name(&self) -> &'static str {
+    "count"
+  }
+
+  fn transform(
+    &self,
+    _ctx: &PluginContext,
+    _code: &str,
+    id: &str,
+  ) -> Result<Option<TransformResult>, RolldownError> {
+    let name = id.rsplit('/').next().unwrap().to_string();
+    *self.transformed.lock().unwrap().entry(name).or_default() += 1;
+    Ok(None)
+  }
+}
+
+#[test]
+fn rebuild_after_an_error() {
+  let temp_dir = tempfile::tempdir().unwrap();
+  let dir = temp_dir.path();
+  let main = dir.join("main.js");
+  std::fs::write(
+    &main,
+    "import { value } from './dep.js';\nconsole.log(value);\n",
+  )
+  .unwrap();
+  std::fs::write(dir.join("dep.js"), "export const value = 1;\n").unwrap();
+  let count = Arc::new(Count::default());
+  let mut build = RolldownBuild::new(NormalizedInputOptions {
+    input: vec![main.to_string_lossy().to_string()],
+    plugins: vec![count.clone()],
+    ..Default::default()
+  });
+  let first = build.generate(Default::default()).unwrap();
+  let main_id = resolve_id(&main.to_string_lossy(), None, false).id;
+
+  std::fs::write(
+    &main,
+    "import { value } from './dep.js';\nconsole.log(value;\n",
+  )
+  .unwrap();
+  assert!(build.rebuild(&[&main_id]).is_err());
+  // The previous graph is kept.
+  let output = build.generate(Default::default()).unwrap();
+  assert_eq!(output[0].get_content(), first[0].get_content());
+
+  std::fs::write(
+    &main,
+    "import { value } from './dep.js';\nconsole.log(value + 1);\n",
+  )
+  .unwrap();
+  build.rebuild(&[&main_id]).unwrap();
+  let output = build.generate(Default::default()).unwrap();
+  assert!(output[0].get_content().contains("value + 1"));
+  // `dep.js` is taken from the cache in both rebuilds.
+  assert_eq!(count.transformed.lock().unwrap()["dep.js"], 1);
+}
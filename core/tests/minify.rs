@@ -0,0 +1,32 @@
+mod common;
+
+use rolldown::types::{InternalModuleFormat, MinifyOptions, NormalizedOutputOptions};
+
+fn generate(output_options: NormalizedOutputOptions) -> String {
+  common::generate_code("./tests/fixtures/minify/index.js", output_options)
+}
+
+#[test]
+fn minify() {
+  let code = generate(NormalizedOutputOptions {
+    minify: Some(Default::default()),
+    ..Default::default()
+  });
+  assert!(!code.contains("intermediateResult"));
+  assert!(!code.contains("firstValue"));
+  assert!(code.contains("export { result, sum };"));
+}
+
+#[test]
+fn minify_toplevel_keeps_exports() {
+  let code = generate(NormalizedOutputOptions {
+    format: InternalModuleFormat::CJS,
+    minify: Some(MinifyOptions {
+      toplevel: true,
+      ..Default::default()
+    }),
+    ..Default::default()
+  });
+  assert!(code.contains("exports.result = result;"));
+  assert!(code.contains("exports.sum = sum;"));
+}
@@ -0,0 +1,788 @@
+use std::{
+  collections::HashMap,
+  sync::{mpsc, Arc, Mutex},
+  time::Duration,
+};
+
+use rolldown::{
+  bundle::Bundle,
+  graph::Graph,
+  plugin_driver::{
+    EmittedFile, Plugin, PluginContext, Position, ResolveOptions, SourceDescription,
+    TransformResult,
+  },
+  structs::{ChunkInfo, EmittedAsset, OutputAsset, RolldownOutput},
+  types::{
+    ModuleInfo, NormalizedInputOptions, NormalizedOutputOptions, ResolveIdResult, ResolvedId,
+  },
+  watcher::{Watcher, WatcherEvent},
+  worker::RolldownError,
+  RolldownBuild,
+};
+
+struct Replace;
+
+impl Plugin for Replace {
+  fn get_name(&self) -> &'static str {
+    "replace"
+  }
+
+  fn transform(
+    &self,
+    _ctx: &PluginContext,
+    code: &str,
+    _id: &str,
+  ) -> Result<Option<TransformResult>, RolldownError> {
+    Ok(Some(code.replace("__VALUE__", "'replaced'").into()))
+  }
+}
+
+// Runs after `Replace`, so it sees the replaced code.
+struct Append;
+
+impl Plugin for Append {
+  fn get_name(&self) -> &'static str {
+    "append"
+  }
+
+  fn transform(
+    &self,
+    _ctx: &PluginContext,
+    code: &str,
+    id: &str,
+  ) -> Result<Option<TransformResult>, RolldownError> {
+    if id.ends_with("polyfill.js") {
+      return Ok(Some(TransformResult {
+        code: code.to_string(),
+        module_side_effects: Some(false),
+        ..Default::default()
+      }));
+    }
+    assert!(!code.contains("__VALUE__"));
+    Ok(Some(
+      format!("{}export const appended = true;\n", code).into(),
+    ))
+  }
+}
+
+fn generate(treeshake: bool) -> String {
+  let mut graph = Graph::new(NormalizedInputOptions {
+    input: vec!["./tests/fixtures/transform/main.js".to_string()],
+    treeshake,
+    plugins: vec![Arc::new(Replace), Arc::new(Append)],
+    ..Default::default()
+  });
+  graph.build();
+  let output = Bundle::new(graph, Default::default()).generate().unwrap();
+  output[0].get_content().to_string()
+}
+
+#[test]
+fn transform_in_order() {
+  let code = generate(false);
+  assert!(code.contains("const value = 'replaced';"));
+  assert!(code.contains("const appended = true;"));
+  assert!(code.contains("console.log('polyfill');"));
+}
+
+#[test]
+fn module_without_side_effects() {
+  assert!(!generate(true).contains("console.log('polyfill');"));
+}
+
+// Doesn't handle anything, so later plugins should still be called.
+struct Noop;
+
+impl Plugin for Noop {
+  fn get_name(&self) -> &'static str {
+    "noop"
+  }
+}
+
+struct Virtual;
+
+impl Plugin for Virtual {
+  fn get_name(&self) -> &'static str {
+    "virtual"
+  }
+
+  fn resolve_id(
+    &self,
+    _ctx: &PluginContext,
+    source: &str,
+    _importer: Option<&str>,
+  ) -> Result<ResolveIdResult, RolldownError> {
+    Ok((source == "virtual:answer").then(|| ResolvedId::new("\0virtual:answer".into(), false)))
+  }
+
+  fn load(
+    &self,
+    _ctx: &PluginContext,
+    id: &str,
+  ) -> Result<Option<SourceDescription>, RolldownError> {
+    Ok((id == "\0virtual:answer").then(|| "export const answer = 42;\n".to_string().into()))
+  }
+}
+
+#[test]
+fn first_resolved_id_and_source() {
+  let dir = tempfile::tempdir().unwrap();
+  let main = dir.path().join("main.js");
+  std::fs::write(
+    &main,
+    "import { answer } from 'virtual:answer';\nconsole.log(answer);\n",
+  )
+  .unwrap();
+  let build = RolldownBuild::new(NormalizedInputOptions {
+    input: vec![main.to_string_lossy().to_string()],
+    plugins: vec![Arc::new(Noop), Arc::new(Virtual)],
+    ..Default::default()
+  });
+  let output = build.generate(Default::default()).unwrap();
+  assert!(output[0].get_content().contains("const answer = 42;"));
+}
+
+#[test]
+fn watch_with_virtual_module() {
+  let dir = tempfile::tempdir().unwrap();
+  let main = dir.path().join("main.js");
+  let dist = dir.path().join("dist.js");
+  std::fs::write(
+    &main,
+    "import { answer } from 'virtual:answer';\nconsole.log(answer);\n",
+  )
+  .unwrap();
+  let (tx, rx) = mpsc::channel();
+  let _watcher = Watcher::new(
+    NormalizedInputOptions {
+      input: vec![main.to_string_lossy().to_string()],
+      plugins: vec![Arc::new(Virtual)],
+      ..Default::default()
+    },
+    NormalizedOutputOptions {
+      file: Some(dist.to_string_lossy().to_string()),
+      ..Default::default()
+    },
+    move |event| match event {
+      WatcherEvent::BuildStart => {}
+      WatcherEvent::BundleEnd { .. } => {
+        tx.send(Ok(())).ok();
+      }
+      WatcherEvent::Error(err) => {
+        tx.send(Err(err.to_string())).ok();
+      }
+    },
+  )
+  .unwrap();
+  // The virtual module is not watched, instead of failing the build.
+  rx.recv_timeout(Duration::from_secs(10)).unwrap().unwrap();
+  assert!(std::fs::read_to_string(&dist)
+    .unwrap()
+    .contains("answer = 42"));
+}
+
+#[derive(Default)]
+struct Lifecycle {
+  hooks: Mutex<Vec<String>>,
+}
+
+impl Lifecycle {
+  fn record(&self, hook: String) -> Result<(), RolldownError> {
+    self.hooks.lock().unwrap().push(hook);
+    Ok(())
+  }
+}
+
+impl Plugin for Lifecycle {
+  fn get_name(&self) -> &'static str {
+    "lifecycle"
+  }
+
+  fn options(
+    &self,
+    _ctx: &PluginContext,
+    _options: &mut NormalizedInputOptions,
+  ) -> Result<(), RolldownError> {
+    self.record("options".to_string())
+  }
+
+  fn build_start(
+    &self,
+    _ctx: &PluginContext,
+    _options: &NormalizedInputOptions,
+  ) -> Result<(), RolldownError> {
+    self.record("build_start".to_string())
+  }
+
+  fn module_parsed(&self, _ctx: &PluginContext, info: &ModuleInfo) -> Result<(), RolldownError> {
+    self.record(format!(
+      "module_parsed {}",
+      info.id.rsplit('/').next().unwrap()
+    ))
+  }
+
+  fn resolve_dynamic_import(
+    &self,
+    _ctx: &PluginContext,
+    specifier: &str,
+    _importer: &str,
+  ) -> Result<ResolveIdResult, RolldownError> {
+    self.record(format!("resolve_dynamic_import {}", specifier))?;
+    Ok(None)
+  }
+
+  fn build_end(
+    &self,
+    _ctx: &PluginContext,
+    error: Option<&RolldownError>,
+  ) -> Result<(), RolldownError> {
+    self.record(format!("build_end {}", error.is_some()))
+  }
+}
+
+fn build_with_lifecycle(input: &str) -> Vec<String> {
+  let lifecycle = Arc::new(Lifecycle::default());
+  Graph::new(NormalizedInputOptions {
+    input: vec![input.to_string()],
+    plugins: vec![lifecycle.clone()],
+    ..Default::default()
+  })
+  .try_build()
+  .ok();
+  let hooks = lifecycle.hooks.lock().unwrap().clone();
+  hooks
+}
+
+#[test]
+fn build_hooks_in_order() {
+  let hooks = build_with_lifecycle("./tests/fixtures/dynamic_import/main.js");
+  assert_eq!(hooks[..2], ["options", "build_start"]);
+  assert_eq!(hooks.last().unwrap(), "build_end false");
+  assert!(hooks.contains(&"resolve_dynamic_import ./lazy".to_string()));
+  assert!(hooks.contains(&"module_parsed main.js".to_string()));
+  assert!(hooks.contains(&"module_parsed lazy.js".to_string()));
+}
+
+#[test]
+fn build_hooks_once_for_every_output() {
+  let lifecycle = Arc::new(Lifecycle::default());
+  let build = RolldownBuild::new(NormalizedInputOptions {
+    input: vec!["./tests/fixtures/dynamic_import/main.js".to_string()],
+    plugins: vec![lifecycle.clone()],
+    ..Default::default()
+  });
+  let first = build.generate(Default::default()).unwrap();
+  let second = build.generate(Default::default()).unwrap();
+  assert_eq!(first[0].get_content(), second[0].get_content());
+  let hooks = lifecycle.hooks.lock().unwrap();
+  assert_eq!(
+    hooks.iter().filter(|hook| *hook == "build_start").count(),
+    1
+  );
+  assert_eq!(
+    hooks
+      .iter()
+      .filter(|hook| *hook == "build_end false")
+      .count(),
+    1
+  );
+}
+
+#[test]
+fn build_end_with_error() {
+  let hooks = build_with_lifecycle("./tests/fixtures/dynamic_import/missing.js");
+  assert_eq!(hooks.last().unwrap(), "build_end true");
+}
+
+struct DynamicCdn;
+
+impl Plugin for DynamicCdn {
+  fn get_name(&self) -> &'static str {
+    "dynamic-cdn"
+  }
+
+  fn resolve_dynamic_import(
+    &self,
+    _ctx: &PluginContext,
+    specifier: &str,
+    _importer: &str,
+  ) -> Result<ResolveIdResult, RolldownError> {
+    Ok(
+      (specifier == "./lazy")
+        .then(|| ResolvedId::new("https://cdn.example.com/lazy.js".into(), false)),
+    )
+  }
+}
+
+#[test]
+fn external_dynamic_import_resolved_by_plugin() {
+  let output = RolldownBuild::new(NormalizedInputOptions {
+    input: vec!["./tests/fixtures/dynamic_import/main.js".to_string()],
+    external: Arc::new(|id, _, _| id.starts_with("https://")),
+    plugins: vec![Arc::new(DynamicCdn)],
+    ..Default::default()
+  })
+  .generate(Default::default())
+  .unwrap();
+  assert_eq!(output.len(), 1);
+  assert!(output[0]
+    .get_content()
+    .contains("https://cdn.example.com/lazy.js"));
+}
+
+struct Output {
+  salt: &'static str,
+}
+
+impl Plugin for Output {
+  fn get_name(&self) -> &'static str {
+    "output"
+  }
+
+  fn render_chunk(
+    &self,
+    _ctx: &PluginContext,
+    code: &str,
+    chunk: &ChunkInfo,
+    _options: &NormalizedOutputOptions,
+  ) -> Result<Option<SourceDescription>, RolldownError> {
+    Ok(Some(format!("/* {} */\n{}", chunk.name, code).into()))
+  }
+
+  fn augment_chunk_hash(&self, _ctx: &PluginContext, _chunk: &ChunkInfo) -> Option<String> {
+    Some(self.salt.to_string())
+  }
+
+  fn generate_bundle(
+    &self,
+    _ctx: &PluginContext,
+    _options: &NormalizedOutputOptions,
+    bundle: &mut Vec<RolldownOutput>,
+    _is_write: bool,
+  ) -> Result<(), RolldownError> {
+    let file_names = bundle
+      .iter()
+      .map(|output| output.get_file_name().to_string())
+      .collect::<Vec<_>>();
+    bundle.push(RolldownOutput::Asset(OutputAsset {
+      file_name: "manifest.json".to_string(),
+      source: serde_json::to_vec(&file_names).unwrap(),
+      name: None,
+    }));
+    Ok(())
+  }
+}
+
+fn generate_with_output_plugin(salt: &'static str) -> Vec<RolldownOutput> {
+  let build = RolldownBuild::new(NormalizedInputOptions {
+    input: vec!["./tests/fixtures/code_splitting/shared.js".to_string()],
+    plugins: vec![Arc::new(Output { salt })],
+    ..Default::default()
+  });
+  build
+    .generate(NormalizedOutputOptions {
+      entry_file_names: "[name]-[hash].js".to_string(),
+      ..Default::default()
+    })
+    .unwrap()
+}
+
+#[test]
+fn output_hooks() {
+  let output = generate_with_output_plugin("a");
+  assert!(output[0].get_content().starts_with("/* shared */\n"));
+  assert_eq!(output[1].get_file_name(), "manifest.json");
+  assert_eq!(
+    output[1].get_content(),
+    format!("[\"{}\"]", output[0].get_file_name())
+  );
+  // Only the hash augmentation differs.
+  let other = generate_with_output_plugin("b");
+  assert_eq!(output[0].get_content(), other[0].get_content());
+  assert_ne!(output[0].get_file_name(), other[0].get_file_name());
+}
+
+// Resolves through the other plugins and checks the module graph, and warns or fails on code which is not replaced.
+struct Context {
+  strict: bool,
+}
+
+impl Plugin for Context {
+  fn get_name(&self) -> &'static str {
+    "context"
+  }
+
+  fn resolve_id(
+    &self,
+    ctx: &PluginContext,
+    source: &str,
+    importer: Option<&str>,
+  ) -> Result<ResolveIdResult, RolldownError> {
+    // Calls itself again unless it's skipped.
+    ctx
+      .resolve(source, importer, ResolveOptions::default())
+      .map(Some)
+  }
+
+  fn transform(
+    &self,
+    ctx: &PluginContext,
+    code: &str,
+    id: &str,
+  ) -> Result<Option<TransformResult>, RolldownError> {
+    match code.find("__VALUE__") {
+      Some(offset) if self.strict => Err(ctx.error(
+        "__VALUE__ is not replaced",
+        Some(id),
+        Some(Position::from_offset(code, offset)),
+      )),
+      Some(offset) => {
+        ctx.warn(
+          "__VALUE__ is not replaced yet",
+          Some(id),
+          Some(Position::from_offset(code, offset)),
+        );
+        Ok(None)
+      }
+      None => Ok(None),
+    }
+  }
+
+  fn generate_bundle(
+    &self,
+    ctx: &PluginContext,
+    _options: &NormalizedOutputOptions,
+    bundle: &mut Vec<RolldownOutput>,
+    _is_write: bool,
+  ) -> Result<(), RolldownError> {
+    let facade_module_id = match &bundle[0] {
+      RolldownOutput::Chunk(chunk) => chunk.facade_module_id.clone().unwrap(),
+      _ => unreachable!(),
+    };
+    let info = ctx.get_module_info(&facade_module_id).unwrap();
+    let imported = ctx.get_module_info(&info.imported_ids[0]).unwrap();
+    ctx.emit_file(EmittedFile::Asset(EmittedAsset {
+      name: None,
+      file_name: Some("modules.txt".to_string()),
+      source: format!(
+        "{} {:?} {}",
+        info.is_entry,
+        info.exports,
+        imported.importers.len()
+      )
+      .into_bytes(),
+    }))?;
+    Ok(())
+  }
+}
+
+#[test]
+fn plugin_context() {
+  let warnings: Arc<Mutex<Vec<String>>> = Default::default();
+  let build = RolldownBuild::new(NormalizedInputOptions {
+    input: vec!["./tests/fixtures/transform/main.js".to_string()],
+    plugins: vec![Arc::new(Context { strict: false }), Arc::new(Replace)],
+    on_warn: {
+      let warnings = warnings.clone();
+      Arc::new(move |warning| warnings.lock().unwrap().push(warning.to_string()))
+    },
+    ..Default::default()
+  });
+  let warnings = warnings.lock().unwrap().clone();
+  assert_eq!(warnings.len(), 1);
+  assert!(warnings[0].starts_with("[plugin context] "));
+  assert!(warnings[0].ends_with("main.js:2:22: __VALUE__ is not replaced yet"));
+  let output = build.generate(Default::default()).unwrap();
+  assert!(output[0]
+    .get_content()
+    .contains("const value = 'replaced';"));
+  assert_eq!(output[1].get_file_name(), "modules.txt");
+  assert_eq!(output[1].get_content(), "true [\"value\"] 1");
+}
+
+#[test]
+fn plugin_error_with_position() {
+  let err = Graph::new(NormalizedInputOptions {
+    input: vec!["./tests/fixtures/transform/main.js".to_string()],
+    plugins: vec![Arc::new(Context { strict: true })],
+    ..Default::default()
+  })
+  .try_build()
+  .unwrap_err();
+  assert!(err.to_string().starts_with("[plugin context] "));
+  assert!(err
+    .to_string()
+    .ends_with("main.js:2:22: __VALUE__ is not replaced"));
+}
+
+// Resolves through the other plugins.
+struct Delegate;
+
+impl Plugin for Delegate {
+  fn get_name(&self) -> &'static str {
+    "delegate"
+  }
+
+  fn resolve_id(
+    &self,
+    ctx: &PluginContext,
+    source: &str,
+    importer: Option<&str>,
+  ) -> Result<ResolveIdResult, RolldownError> {
+    ctx
+      .resolve(source, importer, ResolveOptions::default())
+      .map(Some)
+  }
+}
+
+#[test]
+fn nested_resolve_skips_every_caller() {
+  // Each plugin calls the other one, until both are skipped.
+  let output = RolldownBuild::new(NormalizedInputOptions {
+    input: vec!["./tests/fixtures/dynamic_import/main.js".to_string()],
+    plugins: vec![Arc::new(Delegate), Arc::new(Delegate)],
+    ..Default::default()
+  })
+  .generate(Default::default())
+  .unwrap();
+  assert_eq!(output.len(), 2);
+}
+
+#[derive(Default)]
+struct CloseWatcher {
+  module_ids: Mutex<Vec<String>>,
+}
+
+impl Plugin for CloseWatcher {
+  fn get_name(&self) -> &'static str {
+    "close-watcher"
+  }
+
+  fn close_watcher(&self, ctx: &PluginContext) -> Result<(), RolldownError> {
+    let mut module_ids = ctx
+      .get_module_ids()
+      .iter()
+      .map(|id| id.rsplit('/').next().unwrap().to_string())
+      .collect::<Vec<_>>();
+    module_ids.sort();
+    *self.module_ids.lock().unwrap() = module_ids;
+    Ok(())
+  }
+}
+
+#[test]
+fn watcher_shares_the_build_context() {
+  let close_watcher = Arc::new(CloseWatcher::default());
+  let dir = tempfile::tempdir().unwrap();
+  let (tx, rx) = mpsc::channel();
+  let watcher = Watcher::new(
+    NormalizedInputOptions {
+      input: vec!["./tests/fixtures/dynamic_import/main.js".to_string()],
+      plugins: vec![close_watcher.clone()],
+      ..Default::default()
+    },
+    NormalizedOutputOptions {
+      dir: Some(dir.path().to_string_lossy().to_string()),
+      ..Default::default()
+    },
+    move |event| {
+      if let WatcherEvent::BundleEnd { .. } | WatcherEvent::Error(_) = event {
+        tx.send(()).ok();
+      }
+    },
+  )
+  .unwrap();
+  rx.recv_timeout(Duration::from_secs(10)).unwrap();
+  watcher.close().unwrap();
+  // Modules of the latest build are known to hooks called by the watcher.
+  assert_eq!(
+    *close_watcher.module_ids.lock().unwrap(),
+    ["lazy.js", "main.js"]
+  );
+}
+
+// Counts transformed modules by file name.
+#[derive(Default)]
+struct Count {
+  transformed: Mutex<HashMap<String, usize>>,
+}
+
+impl Plugin for Count {
+  fn get_name(&self) -> &'static str {
+    "count"
+  }
+
+  fn transform(
+    &self,
+    _ctx: &PluginContext,
+    _code: &str,
+    id: &str,
+  ) -> Result<Option<TransformResult>, RolldownError> {
+    let name = id.rsplit('/').next().unwrap().to_string();
+    *self.transformed.lock().unwrap().entry(name).or_default() += 1;
+    Ok(None)
+  }
+}
+
+#[test]
+fn watcher_keeps_the_cache_after_an_error() {
+  let dir = tempfile::tempdir().unwrap();
+  let main = dir.path().join("main.js");
+  std::fs::write(
+    &main,
+    "import { value } from './dep.js';\nconsole.log(value);\n",
+  )
+  .unwrap();
+  std::fs::write(dir.path().join("dep.js"), "export const value = 1;\n").unwrap();
+  let count = Arc::new(Count::default());
+  let (tx, rx) = mpsc::channel();
+  let _watcher = Watcher::new(
+    NormalizedInputOptions {
+      input: vec![main.to_string_lossy().to_string()],
+      plugins: vec![count.clone()],
+      ..Default::default()
+    },
+    NormalizedOutputOptions {
+      file: Some(dir.path().join("dist.js").to_string_lossy().to_string()),
+      ..Default::default()
+    },
+    move |event| match event {
+      WatcherEvent::BuildStart => {}
+      WatcherEvent::BundleEnd { .. } => {
+        tx.send(true).ok();
+      }
+      WatcherEvent::Error(_) => {
+        tx.send(false).ok();
+      }
+    },
+  )
+  .unwrap();
+  assert!(rx.recv_timeout(Duration::from_secs(10)).unwrap());
+
+  std::fs::write(
+    &main,
+    "import { value } from './dep.js';\nconsole.log(value;\n",
+  )
+  .unwrap();
+  assert!(!rx.recv_timeout(Duration::from_secs(10)).unwrap());
+  std::fs::write(
+    &main,
+    "import { value } from './dep.js';\nconsole.log(value + 1);\n",
+  )
+  .unwrap();
+  assert!(rx.recv_timeout(Duration::from_secs(10)).unwrap());
+
+  // `dep.js` is taken from the cache in both rebuilds.
+  assert_eq!(count.transformed.lock().unwrap()["dep.js"], 1);
+}
+
+// Loads modules from the `transform` hook of the entry.
+#[derive(Default)]
+struct Preload {
+  exports: Mutex<Vec<String>>,
+}
+
+impl Plugin for Preload {
+  fn get_name(&self) -> &'static str {
+    "preload"
+  }
+
+  fn transform(
+    &self,
+    ctx: &PluginContext,
+    _code: &str,
+    id: &str,
+  ) -> Result<Option<TransformResult>, RolldownError> {
+    if id.ends_with("a.js") {
+      for source in ["./shared", "./b.js"] {
+        let resolved_id = ctx.resolve(source, Some(id), ResolveOptions::default())?;
+        let info = ctx.load(resolved_id)?;
+        self.exports.lock().unwrap().extend(info.exports);
+      }
+    }
+    Ok(None)
+  }
+}
+
+#[test]
+fn load_from_plugin_context() {
+  let preload = Arc::new(Preload::default());
+  let build = RolldownBuild::new(NormalizedInputOptions {
+    input: vec!["./tests/fixtures/code_splitting/a.js".to_string()],
+    plugins: vec![preload.clone()],
+    ..Default::default()
+  });
+  assert_eq!(*preload.exports.lock().unwrap(), ["shared", "b"]);
+  // `b.js` is loaded, but not bundled as it's not imported.
+  let output = build.generate(Default::default()).unwrap();
+  assert_eq!(output.len(), 1);
+  assert!(output[0].get_content().contains("'shared'"));
+  assert!(!output[0].get_content().contains("shared + 'b'"));
+}
+
+// Emits files when the build starts, and looks up their file names in the output.
+#[derive(Default)]
+struct Emit {
+  reference_ids: Mutex<Vec<String>>,
+  file_names: Mutex<Vec<String>>,
+}
+
+impl Plugin for Emit {
+  fn get_name(&self) -> &'static str {
+    "emit"
+  }
+
+  fn build_start(
+    &self,
+    ctx: &PluginContext,
+    _options: &NormalizedInputOptions,
+  ) -> Result<(), RolldownError> {
+    let chunk = ctx.emit_file(EmittedFile::Chunk {
+      id: "./tests/fixtures/code_splitting/b.js".to_string(),
+    })?;
+    let asset = ctx.emit_file(EmittedFile::Asset(EmittedAsset {
+      name: Some("data.txt".to_string()),
+      file_name: None,
+      source: b"data".to_vec(),
+    }))?;
+    // Chunks and assets without a file name are named when the output is generated.
+    assert!(ctx.get_file_name(&chunk).is_err());
+    assert!(ctx.get_file_name(&asset).is_err());
+    *self.reference_ids.lock().unwrap() = vec![chunk, asset];
+    Ok(())
+  }
+
+  fn generate_bundle(
+    &self,
+    ctx: &PluginContext,
+    _options: &NormalizedOutputOptions,
+    _bundle: &mut Vec<RolldownOutput>,
+    _is_write: bool,
+  ) -> Result<(), RolldownError> {
+    *self.file_names.lock().unwrap() = self
+      .reference_ids
+      .lock()
+      .unwrap()
+      .iter()
+      .map(|reference_id| ctx.get_file_name(reference_id))
+      .collect::<Result<_, _>>()?;
+    Ok(())
+  }
+}
+
+#[test]
+fn file_names_of_emitted_files() {
+  let emit = Arc::new(Emit::default());
+  let build = RolldownBuild::new(NormalizedInputOptions {
+    input: vec!["./tests/fixtures/code_splitting/a.js".to_string()],
+    plugins: vec![emit.clone()],
+    ..Default::default()
+  });
+  let output = build.generate(Default::default()).unwrap();
+  let file_names = emit.file_names.lock().unwrap().clone();
+  assert_eq!(file_names[0], "b.js");
+  assert!(file_names[1].starts_with("assets/data-"));
+  assert!(file_names.iter().all(|file_name| output
+    .iter()
+    .any(|output| output.get_file_name() == file_name)));
+}
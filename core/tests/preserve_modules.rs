@@ -0,0 +1,40 @@
+mod common;
+
+use rolldown::types::NormalizedOutputOptions;
+
+fn generate(output_options: NormalizedOutputOptions) -> Vec<(String, String)> {
+  common::generate_files(
+    &["./tests/fixtures/preserve_modules/src/index.js"],
+    output_options,
+  )
+}
+
+#[test]
+fn preserve_modules() {
+  let output = generate(NormalizedOutputOptions {
+    preserve_modules: true,
+    ..Default::default()
+  });
+  let file_names = output
+    .iter()
+    .map(|(file_name, _)| file_name.as_str())
+    .collect::<Vec<_>>();
+  assert_eq!(file_names, vec!["utils/math.js", "index.js"]);
+  let (_, index) = &output[1];
+  assert!(index.starts_with("import { add } from './utils/math.js';"));
+  assert!(index.contains("export { three };"));
+}
+
+#[test]
+fn preserve_modules_root() {
+  let output = generate(NormalizedOutputOptions {
+    preserve_modules: true,
+    preserve_modules_root: Some("./tests/fixtures/preserve_modules".to_string()),
+    ..Default::default()
+  });
+  let file_names = output
+    .iter()
+    .map(|(file_name, _)| file_name.as_str())
+    .collect::<Vec<_>>();
+  assert_eq!(file_names, vec!["src/utils/math.js", "src/index.js"]);
+}
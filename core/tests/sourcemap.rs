@@ -0,0 +1,100 @@
+mod common;
+
+use rolldown::types::{InternalModuleFormat, NormalizedOutputOptions, SourceMapMode};
+
+fn generate(output_options: NormalizedOutputOptions) -> Vec<rolldown::structs::RolldownOutput> {
+  generate_with_input("./tests/fixtures/external/index.js", output_options)
+}
+
+fn generate_with_input(
+  input: &str,
+  output_options: NormalizedOutputOptions,
+) -> Vec<rolldown::structs::RolldownOutput> {
+  common::generate(&[input], output_options)
+}
+
+#[test]
+fn sourcemap_file() {
+  let output = generate(NormalizedOutputOptions {
+    sourcemap: SourceMapMode::File,
+    ..Default::default()
+  });
+  assert_eq!(output.len(), 2);
+  assert!(output[0]
+    .get_content()
+    .ends_with("//# sourceMappingURL=index.js.map\n"));
+  assert_eq!(output[1].get_file_name(), "index.js.map");
+  let map = sourcemap::SourceMap::from_slice(output[1].get_content().as_bytes()).unwrap();
+  assert_eq!(map.get_file(), Some("index.js"));
+  assert_eq!(map.get_source(0), Some("tests/fixtures/external/index.js"));
+  assert!(map.get_source_contents(0).is_some());
+}
+
+#[test]
+fn sourcemap_inline_with_path_transform() {
+  let output = generate(NormalizedOutputOptions {
+    sourcemap: SourceMapMode::Inline,
+    sourcemap_exclude_sources: true,
+    sourcemap_path_transform: Some(Box::new(|source, _| format!("webpack://{}", source))),
+    ..Default::default()
+  });
+  assert_eq!(output.len(), 1);
+  let code = output[0].get_content();
+  let data_url = code
+    .lines()
+    .last()
+    .unwrap()
+    .strip_prefix("//# sourceMappingURL=data:application/json;charset=utf-8;base64,")
+    .unwrap();
+  let map = sourcemap::SourceMap::from_slice(&base64::decode(data_url).unwrap()).unwrap();
+  assert_eq!(
+    map.get_source(0),
+    Some("webpack://tests/fixtures/external/index.js")
+  );
+  assert!(map.get_source_contents(0).is_none());
+}
+
+#[test]
+fn sourcemap_with_input_map() {
+  let output = generate_with_input(
+    "./tests/fixtures/input_sourcemap/index.js",
+    NormalizedOutputOptions {
+      sourcemap: SourceMapMode::Hidden,
+      ..Default::default()
+    },
+  );
+  assert!(!output[0].get_content().contains("sourceMappingURL"));
+  let map = sourcemap::SourceMap::from_slice(output[1].get_content().as_bytes()).unwrap();
+  assert_eq!(map.get_source_count(), 1);
+  assert_eq!(
+    map.get_source(0),
+    Some("tests/fixtures/input_sourcemap/index.ts")
+  );
+  assert_eq!(
+    map.get_source_contents(0),
+    Some("export const answer: number = 42;\n")
+  );
+}
+
+#[test]
+fn sourcemap_with_addons() {
+  let output = generate_with_input(
+    "./tests/fixtures/input_sourcemap/index.js",
+    NormalizedOutputOptions {
+      sourcemap: SourceMapMode::Hidden,
+      format: InternalModuleFormat::CJS,
+      banner: Some("/* banner\n */".into()),
+      intro: Some("// intro".into()),
+      ..Default::default()
+    },
+  );
+  let line = output[0]
+    .get_content()
+    .lines()
+    .position(|line| line.contains("answer = 42"))
+    .unwrap() as u32;
+  let map = sourcemap::SourceMap::from_slice(output[1].get_content().as_bytes()).unwrap();
+  let token = map.lookup_token(line, 0).unwrap();
+  assert_eq!(token.get_dst_line(), line);
+  assert_eq!(token.get_src_line(), 0);
+}
@@ -27,8 +27,10 @@ impl Task for Rolldown {
     let mut graph = rolldown::graph::Graph::from_single_entry(self.entry.clone());
     graph.build();
     let mut bundle = rolldown::bundle::Bundle::new(graph, Default::default());
-    let generated = bundle.generate();
-    Ok(generated.values().next().unwrap().code.clone())
+    let generated = bundle
+      .generate()
+      .map_err(|err| napi::Error::new(napi::Status::GenericFailure, format!("{}", err)))?;
+    Ok(generated[0].get_content().to_string())
   }
 
   fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {